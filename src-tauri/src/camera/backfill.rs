@@ -0,0 +1,607 @@
+use crate::camera::journal::Journal;
+use crate::camera::monitor::CameraEvent;
+use crate::camera::platform_macos::{self, ParserState, Subsystem};
+use crate::camera::replay::apply_event;
+use crate::session::{EndReason, Session, SessionManager, SessionSource};
+use crate::storage;
+use anyhow::{Context, Result};
+use chrono::{DateTime, Duration, Local, Utc};
+use serde::Serialize;
+use std::io::{BufRead, BufReader};
+use std::path::Path;
+use std::process::{Command, Stdio};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Mutex;
+use std::thread;
+
+/// How many days of history the first-launch backfill reconstructs.
+pub const DEFAULT_BACKFILL_DAYS: i64 = 7;
+
+/// Progress is reported every this many log lines.
+const PROGRESS_EVERY_LINES: usize = 500;
+
+/// `meta` key recording when the first-launch backfill finished.
+const META_BACKFILL_COMPLETED: &str = "backfill_completed_at";
+
+/// Set while a backfill is running, so two never write at once.
+static BACKFILL_RUNNING: AtomicBool = AtomicBool::new(false);
+
+/// How often downtime recovery checks whether a running backfill is done.
+const BACKFILL_POLL_INTERVAL: std::time::Duration = std::time::Duration::from_millis(500);
+
+// ---------------------------------------------------------------------------
+// Progress reporting
+// ---------------------------------------------------------------------------
+
+/// Progress of a backfill, sent to the UI as `backfill-progress` events.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+#[serde(tag = "state", rename_all = "lowercase")]
+pub enum BackfillProgress {
+    Running {
+        lines: usize,
+        sessions: usize,
+        /// Share of the time window covered so far, from 0.0 to 1.0.
+        fraction: f64,
+    },
+    Completed {
+        lines: usize,
+        sessions: usize,
+    },
+    Failed {
+        error: String,
+    },
+}
+
+/// The time range a backfill reconstructs.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct BackfillWindow {
+    pub start: DateTime<Utc>,
+    pub end: DateTime<Utc>,
+}
+
+impl BackfillWindow {
+    fn contains(&self, timestamp: DateTime<Utc>) -> bool {
+        timestamp >= self.start && timestamp < self.end
+    }
+
+    /// How far into the window `timestamp` is, from 0.0 to 1.0.
+    fn fraction(&self, timestamp: DateTime<Utc>) -> f64 {
+        let total = (self.end - self.start).num_milliseconds();
+        if total <= 0 {
+            return 1.0;
+        }
+        let done = (timestamp - self.start).num_milliseconds();
+        (done as f64 / total as f64).clamp(0.0, 1.0)
+    }
+}
+
+// ---------------------------------------------------------------------------
+// Backfill job
+// ---------------------------------------------------------------------------
+
+pub fn is_running() -> bool {
+    BACKFILL_RUNNING.load(Ordering::Relaxed)
+}
+
+/// Runs the backfill once, the first time the app starts with a given
+/// database. Does nothing on later launches or off macOS.
+pub fn backfill_on_first_launch(
+    db_path: &Path,
+    progress: impl FnMut(BackfillProgress),
+) -> Result<()> {
+    if !cfg!(target_os = "macos") || storage::get_meta(db_path, META_BACKFILL_COMPLETED)?.is_some()
+    {
+        return Ok(());
+    }
+
+    run_backfill(db_path, DEFAULT_BACKFILL_DAYS, progress)?;
+    storage::set_meta(db_path, META_BACKFILL_COMPLETED, &Utc::now().to_rfc3339())
+}
+
+/// Reconstructs sessions from the last `days` days of unified logs and
+/// inserts them marked `SessionSource::Backfill`. Returns how many sessions
+/// were inserted.
+///
+/// Only the time before the oldest recorded session is covered, so history
+/// is never duplicated. Sessions still open when the window ends are closed
+/// at its end.
+pub fn run_backfill(
+    db_path: &Path,
+    days: i64,
+    mut progress: impl FnMut(BackfillProgress),
+) -> Result<usize> {
+    if BACKFILL_RUNNING.swap(true, Ordering::SeqCst) {
+        anyhow::bail!("A backfill is already running");
+    }
+
+    let result = backfill_window(db_path, days, Utc::now()).and_then(|window| match window {
+        Some(window) => {
+            let manager =
+                SessionManager::with_source(db_path.to_path_buf(), SessionSource::Backfill);
+            let counts = replay_log_show(&manager, window, &mut progress);
+            // Whatever is still open when the window ends
+            manager.end_all_active_sessions_at(window.end, EndReason::ReplayEnd)?;
+            counts
+        }
+        None => Ok((0, 0)),
+    });
+    BACKFILL_RUNNING.store(false, Ordering::SeqCst);
+
+    match result {
+        Ok((lines, sessions)) => {
+            progress(BackfillProgress::Completed { lines, sessions });
+            Ok(sessions)
+        }
+        Err(e) => {
+            progress(BackfillProgress::Failed {
+                error: format!("{:#}", e),
+            });
+            Err(e)
+        }
+    }
+}
+
+/// The window to backfill: from `days` before `now` up to the oldest
+/// recorded session, or `now` if there is none. `None` when that is empty.
+pub fn backfill_window(
+    db_path: &Path,
+    days: i64,
+    now: DateTime<Utc>,
+) -> Result<Option<BackfillWindow>> {
+    let start = now - Duration::days(days);
+    let end = storage::earliest_session_start(db_path)?
+        .map(|earliest| earliest.min(now))
+        .unwrap_or(now);
+
+    Ok((start < end).then_some(BackfillWindow { start, end }))
+}
+
+// ---------------------------------------------------------------------------
+// Downtime recovery
+// ---------------------------------------------------------------------------
+
+/// Reconstructs what happened while the tracker was not running, between
+/// `last_alive` (the previous run's last heartbeat) and `now`, next to the
+/// `live` manager. `orphans` are the sessions the previous run left running.
+/// Returns how many sessions were inserted.
+///
+/// Orphans are closed when the log shows them stopping. Any it does not are
+/// closed at their last heartbeat as `EndReason::CrashRecovered`, as they are
+/// when the log cannot be read. Sessions that started in between are inserted
+/// marked `SessionSource::Backfill`; those still running at `now` are handed
+/// over to `live` (`SessionManager::hand_over_sessions`), so the stop it
+/// sees next ends them.
+///
+/// Waits for a running backfill to finish first, so the two never write at
+/// once.
+pub fn recover_downtime(
+    live: &Mutex<SessionManager>,
+    orphans: &[Session],
+    last_alive: Option<DateTime<Utc>>,
+    now: DateTime<Utc>,
+) -> Result<usize> {
+    while BACKFILL_RUNNING.swap(true, Ordering::SeqCst) {
+        thread::sleep(BACKFILL_POLL_INTERVAL);
+    }
+
+    let db_path = live.lock().unwrap().db_path().to_path_buf();
+    let manager = SessionManager::with_source(db_path, SessionSource::Backfill);
+    for orphan in orphans {
+        manager.adopt_session(orphan, last_alive);
+    }
+
+    let result = match last_alive {
+        Some(start) if cfg!(target_os = "macos") && start < now => {
+            let window = BackfillWindow { start, end: now };
+            replay_log_show(&manager, window, &mut |_| {}).map(|(_, sessions)| sessions)
+        }
+        _ => Ok(0),
+    };
+
+    let settled = settle_recovery(&manager, live, now);
+    BACKFILL_RUNNING.store(false, Ordering::SeqCst);
+
+    settled?;
+    result
+}
+
+/// Hands the sessions `manager` still has running at `now` over to `live`,
+/// then closes the orphans it adopted that nothing stopped.
+fn settle_recovery(
+    manager: &SessionManager,
+    live: &Mutex<SessionManager>,
+    now: DateTime<Utc>,
+) -> Result<()> {
+    manager.hand_over_sessions(&live.lock().unwrap(), now)?;
+    manager.end_all_active_sessions_at(now, EndReason::ReplayEnd)
+}
+
+// ---------------------------------------------------------------------------
+// Reading the unified log
+// ---------------------------------------------------------------------------
+
+/// Streams `log show` for `window` through the parser into `manager`.
+/// Returns the number of lines read and sessions inserted.
+fn replay_log_show(
+    manager: &SessionManager,
+    window: BackfillWindow,
+    progress: &mut impl FnMut(BackfillProgress),
+) -> Result<(usize, usize)> {
+    let subsystem = platform_macos::cached_subsystem(manager.db_path());
+    eprintln!(
+        "[webcam-tracker] Replaying {} log from {} to {}",
+        subsystem, window.start, window.end
+    );
+
+    let mut child = Command::new("log")
+        .args(log_show_args(subsystem, window))
+        .stdout(Stdio::piped())
+        .stderr(Stdio::null())
+        .spawn()
+        .context("Failed to spawn log show process")?;
+    let stdout = child.stdout.take().context("Failed to get stdout")?;
+
+    let counts = backfill_lines(BufReader::new(stdout), subsystem, manager, window, progress);
+
+    let status = child.wait().context("Failed to wait for log show")?;
+    let counts = counts?;
+    if !status.success() {
+        anyhow::bail!("log show exited with {}", status);
+    }
+
+    Ok(counts)
+}
+
+/// Arguments for `log show` covering `window` with the subsystem's predicate.
+fn log_show_args(subsystem: Subsystem, window: BackfillWindow) -> Vec<String> {
+    let format = |t: DateTime<Utc>| {
+        t.with_timezone(&Local)
+            .format("%Y-%m-%d %H:%M:%S")
+            .to_string()
+    };
+
+    vec![
+        "show".to_string(),
+        "--start".to_string(),
+        format(window.start),
+        "--end".to_string(),
+        format(window.end),
+        "--style".to_string(),
+        "ndjson".to_string(),
+        "--predicate".to_string(),
+        platform_macos::predicate_for(&subsystem).to_string(),
+        "--info".to_string(),
+    ]
+}
+
+/// Feeds `log show` output through `parse_log_line` and applies the events
+/// that fall inside `window` to `manager` at their logged timestamps.
+/// Every parsed event is journaled. Starts of sessions that are already
+/// recorded are skipped. Sessions still open at the end are left running
+/// for the caller to close or hand over.
+///
+/// Returns the number of lines read and sessions started.
+pub fn backfill_lines<R: BufRead>(
+    reader: R,
+    subsystem: Subsystem,
+    manager: &SessionManager,
+    window: BackfillWindow,
+    progress: &mut impl FnMut(BackfillProgress),
+) -> Result<(usize, usize)> {
+    let mut state = ParserState::default();
+    let mut lines = 0;
+    let mut sessions = 0;
+    let mut covered = window.start;
+    let recorded = storage::get_sessions_between(manager.db_path(), window.start, window.end)?;
+    let journal = Journal::new(manager.db_path().to_path_buf());
+    let origin = subsystem.to_string();
+
+    for line in reader.lines() {
+        let line = line.context("Failed to read log show output")?;
+        lines += 1;
+
+        for event in platform_macos::parse_log_line(&line, &subsystem, &mut state) {
+            journal.record(&origin, Some(&line), &event);
+            let timestamp = match &event {
+                CameraEvent::Started { timestamp, .. } | CameraEvent::Stopped { timestamp, .. } => {
+                    *timestamp
+                }
+                CameraEvent::Confirmed { .. } => covered,
+            };
+            if !window.contains(timestamp) {
+                continue;
+            }
+
+            covered = covered.max(timestamp);
+            if let CameraEvent::Started {
+                app_name, sensor, ..
+            } = &event
+            {
+                // Compare the reported name; recorded names have aliases applied
+                let already_recorded = recorded.iter().any(|s| {
+                    s.raw_app_name.as_deref().unwrap_or(&s.app_name) == app_name
+                        && s.sensor == *sensor
+                        && s.start_time == timestamp
+                });
+                if already_recorded {
+                    continue;
+                }
+                sessions += 1;
+            }
+            apply_event(manager, event)?;
+        }
+
+        if lines % PROGRESS_EVERY_LINES == 0 {
+            progress(BackfillProgress::Running {
+                lines,
+                sessions,
+                fraction: window.fraction(covered),
+            });
+        }
+    }
+
+    Ok((lines, sessions))
+}
+
+// ---------------------------------------------------------------------------
+// Tests
+// ---------------------------------------------------------------------------
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::camera::platform_macos::predicate_for;
+    use crate::session::SessionStatus;
+    use crate::storage::scratch_db;
+    use std::io::Cursor;
+
+    const ZOOM_CAPTURE_NDJSON: &str = include_str!(concat!(
+        env!("CARGO_MANIFEST_DIR"),
+        "/tests/fixtures/cameracapture_zoom.ndjson"
+    ));
+
+    fn utc(s: &str) -> DateTime<Utc> {
+        DateTime::parse_from_rfc3339(s).unwrap().with_timezone(&Utc)
+    }
+
+    #[test]
+    fn test_backfill_marks_sessions_and_uses_log_timestamps() {
+        let db = scratch_db("backfill-marks");
+        let manager = SessionManager::with_source(db.clone(), SessionSource::Backfill);
+        let window = BackfillWindow {
+            start: utc("2025-06-10T00:00:00Z"),
+            end: utc("2025-06-11T00:00:00Z"),
+        };
+
+        let (lines, sessions) = backfill_lines(
+            Cursor::new(ZOOM_CAPTURE_NDJSON),
+            Subsystem::CameraCapture,
+            &manager,
+            window,
+            &mut |_| {},
+        )
+        .unwrap();
+        assert_eq!(lines, 6);
+        assert_eq!(sessions, 2);
+
+        let mut stored = storage::get_all_sessions(&db).unwrap();
+        stored.sort_by_key(|s| s.start_time);
+        assert!(stored.iter().all(|s| s.source == SessionSource::Backfill));
+        assert_eq!(stored[0].app_name, "Zoom");
+        assert_eq!(stored[0].start_time, utc("2025-06-10T21:23:01.123456Z"));
+        assert_eq!(stored[0].duration_secs, Some(120));
+
+        // The evidence behind the session was journaled along the way
+        let evidence = storage::get_session_events(&db, &stored[0]).unwrap();
+        assert_eq!(evidence.len(), 2);
+        assert!(evidence.iter().all(|e| e.subsystem == "cameracapture"));
+
+        // Covering the window again adds nothing, though "zoom.us" is now "Zoom"
+        let (_, again) = backfill_lines(
+            Cursor::new(ZOOM_CAPTURE_NDJSON),
+            Subsystem::CameraCapture,
+            &manager,
+            window,
+            &mut |_| {},
+        )
+        .unwrap();
+        assert_eq!(again, 0);
+        assert_eq!(storage::get_all_sessions(&db).unwrap().len(), 2);
+    }
+
+    #[test]
+    fn test_backfill_leaves_sessions_open_at_window_end() {
+        let db = scratch_db("backfill-window");
+        let manager = SessionManager::with_source(db.clone(), SessionSource::Backfill);
+        // Ends between the FaceTime start (21:24:10) and stop (21:30:10)
+        let window = BackfillWindow {
+            start: utc("2025-06-10T00:00:00Z"),
+            end: utc("2025-06-10T21:26:10Z"),
+        };
+
+        backfill_lines(
+            Cursor::new(ZOOM_CAPTURE_NDJSON),
+            Subsystem::CameraCapture,
+            &manager,
+            window,
+            &mut |_| {},
+        )
+        .unwrap();
+
+        let facetime = storage::get_all_sessions(&db)
+            .unwrap()
+            .into_iter()
+            .find(|s| s.app_name == "FaceTime")
+            .unwrap();
+        assert_eq!(facetime.status, SessionStatus::Running);
+
+        // As a backfill then closes it
+        manager
+            .end_all_active_sessions_at(window.end, EndReason::ReplayEnd)
+            .unwrap();
+        let facetime = storage::get_session(&db, facetime.id.unwrap()).unwrap().unwrap();
+        assert_eq!(facetime.status, SessionStatus::Completed);
+        assert_eq!(facetime.end_time, Some(window.end));
+        assert_eq!(facetime.duration_secs, Some(120));
+        assert_eq!(facetime.end_reason, Some(EndReason::ReplayEnd));
+    }
+
+    #[test]
+    fn test_window_stops_at_oldest_session() {
+        let db = scratch_db("backfill-oldest");
+        let now = utc("2025-06-12T00:00:00Z");
+
+        let window = backfill_window(&db, 7, now).unwrap().unwrap();
+        assert_eq!(window.start, utc("2025-06-05T00:00:00Z"));
+        assert_eq!(window.end, now);
+
+        let manager = SessionManager::new(db.clone());
+        manager
+            .start_session(
+                "FaceTime".into(),
+                None,
+                None,
+                crate::camera::Sensor::Camera,
+                utc("2025-06-10T09:00:00Z"),
+            )
+            .unwrap();
+        let window = backfill_window(&db, 7, now).unwrap().unwrap();
+        assert_eq!(window.end, utc("2025-06-10T09:00:00Z"));
+
+        assert_eq!(backfill_window(&db, 1, now).unwrap(), None);
+    }
+
+    #[test]
+    fn test_recovery_closes_orphans_at_logged_stop() {
+        let db = scratch_db("backfill-recovery");
+
+        // Recorded live before the tracker went away mid-session
+        let live = Mutex::new(SessionManager::new(db.clone()));
+        live.lock().unwrap().start_session(
+            "FaceTime".into(),
+            None,
+            Some(1234),
+            crate::camera::Sensor::Camera,
+            utc("2025-06-10T21:24:10Z"),
+        )
+        .unwrap();
+
+        live.lock().unwrap().start_session(
+            "Photo Booth".into(),
+            None,
+            None,
+            crate::camera::Sensor::Camera,
+            utc("2025-06-10T21:10:00Z"),
+        )
+        .unwrap();
+        let last_alive = utc("2025-06-10T21:20:00Z");
+
+        let manager = SessionManager::with_source(db.clone(), SessionSource::Backfill);
+        for orphan in live.lock().unwrap().get_active_sessions().unwrap() {
+            manager.adopt_session(&orphan, Some(last_alive));
+        }
+        let window = BackfillWindow {
+            start: last_alive,
+            end: utc("2025-06-11T00:00:00Z"),
+        };
+        let (_, sessions) = backfill_lines(
+            Cursor::new(ZOOM_CAPTURE_NDJSON),
+            Subsystem::CameraCapture,
+            &manager,
+            window,
+            &mut |_| {},
+        )
+        .unwrap();
+        settle_recovery(&manager, &live, window.end).unwrap();
+
+        // Only zoom.us is new; FaceTime's start was already recorded
+        assert_eq!(sessions, 1);
+        let stored = storage::get_all_sessions(&db).unwrap();
+        assert_eq!(stored.len(), 3);
+
+        let facetime = stored.iter().find(|s| s.app_name == "FaceTime").unwrap();
+        assert_eq!(facetime.source, SessionSource::Live);
+        assert_eq!(facetime.end_time, Some(utc("2025-06-10T21:30:10Z")));
+        assert_eq!(facetime.end_reason, Some(EndReason::Stopped));
+
+        // Never stopped in the log, so closed when the tracker was last alive
+        let photo_booth = stored.iter().find(|s| s.app_name == "Photo Booth").unwrap();
+        assert_eq!(photo_booth.end_time, Some(last_alive));
+        assert_eq!(photo_booth.end_reason, Some(EndReason::CrashRecovered));
+
+        let zoom = stored.iter().find(|s| s.app_name == "Zoom").unwrap();
+        assert_eq!(zoom.source, SessionSource::Backfill);
+        assert_eq!(zoom.status, SessionStatus::Completed);
+    }
+
+    #[test]
+    fn test_recovery_hands_running_sessions_to_live_manager() {
+        let db = scratch_db("backfill-handover");
+        let live = Mutex::new(SessionManager::new(db.clone()));
+        let manager = SessionManager::with_source(db.clone(), SessionSource::Backfill);
+        // The tracker comes back while FaceTime (21:24:10 to 21:30:10) is running
+        let window = BackfillWindow {
+            start: utc("2025-06-10T21:00:00Z"),
+            end: utc("2025-06-10T21:26:10Z"),
+        };
+
+        backfill_lines(
+            Cursor::new(ZOOM_CAPTURE_NDJSON),
+            Subsystem::CameraCapture,
+            &manager,
+            window,
+            &mut |_| {},
+        )
+        .unwrap();
+        settle_recovery(&manager, &live, window.end).unwrap();
+
+        let facetime = storage::get_all_sessions(&db)
+            .unwrap()
+            .into_iter()
+            .find(|s| s.app_name == "FaceTime")
+            .unwrap();
+        assert_eq!(facetime.status, SessionStatus::Running);
+        assert!(!manager.has_active_sessions());
+
+        // The live stream's stop ends it where it really stopped
+        let ended = live
+            .lock()
+            .unwrap()
+            .end_session_for_app(
+                "FaceTime",
+                None,
+                None,
+                crate::camera::Sensor::Camera,
+                utc("2025-06-10T21:30:10Z"),
+            )
+            .unwrap();
+        assert_eq!(ended, facetime.id.into_iter().collect::<Vec<_>>());
+        let facetime = storage::get_session(&db, facetime.id.unwrap()).unwrap().unwrap();
+        assert_eq!(facetime.end_reason, Some(EndReason::Stopped));
+        assert_eq!(facetime.duration_secs, Some(360));
+    }
+
+    #[test]
+    fn test_log_show_args_cover_window() {
+        let window = BackfillWindow {
+            start: utc("2025-06-10T00:00:00Z"),
+            end: utc("2025-06-11T00:00:00Z"),
+        };
+        let args = log_show_args(Subsystem::Cmio, window);
+        assert_eq!(args[0], "show");
+        assert_eq!(args[1], "--start");
+        assert_eq!(args[3], "--end");
+        assert!(args.contains(&"ndjson".to_string()));
+        assert!(args.contains(&predicate_for(&Subsystem::Cmio).to_string()));
+    }
+
+    #[test]
+    fn test_window_fraction() {
+        let window = BackfillWindow {
+            start: utc("2025-06-10T00:00:00Z"),
+            end: utc("2025-06-10T10:00:00Z"),
+        };
+        assert_eq!(window.fraction(utc("2025-06-10T05:00:00Z")), 0.5);
+        assert_eq!(window.fraction(utc("2025-06-11T00:00:00Z")), 1.0);
+    }
+}
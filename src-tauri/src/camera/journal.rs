@@ -0,0 +1,185 @@
+use crate::camera::monitor::{CameraEvent, Sensor};
+use crate::storage;
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use std::path::PathBuf;
+
+// ---------------------------------------------------------------------------
+// Raw event journal
+// ---------------------------------------------------------------------------
+
+/// Whether a journaled event started or stopped sensor use.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum EventKind {
+    Started,
+    Stopped,
+}
+
+impl std::fmt::Display for EventKind {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            EventKind::Started => write!(f, "started"),
+            EventKind::Stopped => write!(f, "stopped"),
+        }
+    }
+}
+
+/// One parsed `CameraEvent` as stored in the `camera_events` table, before
+/// any reconciling or session building.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct JournaledEvent {
+    pub id: Option<i64>,
+    pub kind: EventKind,
+    pub app_name: String,
+    pub bundle_id: Option<String>,
+    pub pid: Option<u32>,
+    pub sensor: Sensor,
+    /// Subsystem (or other source) the event was parsed from.
+    pub subsystem: String,
+    /// When the event happened, according to the log entry.
+    pub log_timestamp: DateTime<Utc>,
+    /// When we parsed it.
+    pub received_at: DateTime<Utc>,
+    /// Hash of the raw line the event was parsed from, when there was one.
+    pub line_hash: Option<String>,
+}
+
+impl JournaledEvent {
+    /// The journal entry for `event`, or `None` for events that are not
+    /// parsed from a source (confirmations).
+    pub fn from_event(event: &CameraEvent, subsystem: &str, line: Option<&str>) -> Option<Self> {
+        let (kind, app_name, bundle_id, pid, sensor, timestamp) = match event {
+            CameraEvent::Started {
+                app_name,
+                bundle_id,
+                pid,
+                sensor,
+                timestamp,
+            } => (
+                EventKind::Started,
+                app_name,
+                bundle_id,
+                pid,
+                sensor,
+                timestamp,
+            ),
+            CameraEvent::Stopped {
+                app_name,
+                bundle_id,
+                pid,
+                sensor,
+                timestamp,
+            } => (
+                EventKind::Stopped,
+                app_name,
+                bundle_id,
+                pid,
+                sensor,
+                timestamp,
+            ),
+            CameraEvent::Confirmed { .. } => return None,
+        };
+
+        Some(Self {
+            id: None,
+            kind,
+            app_name: app_name.clone(),
+            bundle_id: bundle_id.clone(),
+            pid: *pid,
+            sensor: *sensor,
+            subsystem: subsystem.to_string(),
+            log_timestamp: *timestamp,
+            received_at: Utc::now(),
+            line_hash: line.map(line_hash),
+        })
+    }
+
+    /// The `CameraEvent` this entry was journaled from.
+    pub fn to_event(&self) -> CameraEvent {
+        let app_name = self.app_name.clone();
+        let bundle_id = self.bundle_id.clone();
+        match self.kind {
+            EventKind::Started => CameraEvent::Started {
+                app_name,
+                bundle_id,
+                pid: self.pid,
+                sensor: self.sensor,
+                timestamp: self.log_timestamp,
+            },
+            EventKind::Stopped => CameraEvent::Stopped {
+                app_name,
+                bundle_id,
+                pid: self.pid,
+                sensor: self.sensor,
+                timestamp: self.log_timestamp,
+            },
+        }
+    }
+}
+
+/// Appends every parsed event to the `camera_events` table of a database.
+///
+/// Writing is best effort: a failed insert is logged and never holds up the
+/// event stream.
+#[derive(Debug, Clone)]
+pub struct Journal {
+    db_path: PathBuf,
+}
+
+impl Journal {
+    pub fn new(db_path: PathBuf) -> Self {
+        Self { db_path }
+    }
+
+    /// Records `event`, parsed from `line` of `subsystem` when it came from a
+    /// log line.
+    pub fn record(&self, subsystem: &str, line: Option<&str>, event: &CameraEvent) {
+        if let Some(entry) = JournaledEvent::from_event(event, subsystem, line) {
+            if let Err(e) = storage::insert_camera_event(&self.db_path, &entry) {
+                eprintln!("[webcam-tracker] Failed to journal camera event: {:#}", e);
+            }
+        }
+    }
+}
+
+/// Stable 64-bit FNV-1a hash of a raw log line, as 16 hex digits. Used to
+/// trace an event back to its line and to skip lines journaled twice.
+pub fn line_hash(line: &str) -> String {
+    const OFFSET_BASIS: u64 = 0xcbf2_9ce4_8422_2325;
+    const PRIME: u64 = 0x0000_0100_0000_01b3;
+
+    let hash = line.trim_end().bytes().fold(OFFSET_BASIS, |hash, byte| {
+        (hash ^ byte as u64).wrapping_mul(PRIME)
+    });
+    format!("{:016x}", hash)
+}
+
+// ---------------------------------------------------------------------------
+// Tests
+// ---------------------------------------------------------------------------
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_line_hash_is_stable() {
+        assert_eq!(line_hash(""), "cbf29ce484222325");
+        assert_eq!(line_hash("a"), "af63dc4c8601ec8c");
+        // Trailing newlines and carriage returns do not change the hash
+        assert_eq!(line_hash("a\r\n"), line_hash("a"));
+    }
+
+    #[test]
+    fn test_confirmations_are_not_journaled() {
+        let confirmed = CameraEvent::Confirmed {
+            app_name: "FaceTime".to_string(),
+            bundle_id: None,
+            pid: None,
+            sensor: Sensor::Camera,
+            source: "cmio".to_string(),
+        };
+        assert_eq!(JournaledEvent::from_event(&confirmed, "cmio", None), None);
+    }
+}
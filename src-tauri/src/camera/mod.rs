@@ -1,5 +1,13 @@
+pub mod backfill;
+pub mod journal;
 pub mod monitor;
+pub mod platform_linux;
 pub mod platform_macos;
+pub mod power;
+pub mod reconcile;
+pub mod replay;
+pub mod source;
 
-pub use monitor::{CameraEvent, CameraMonitor};
+pub use monitor::{CameraEvent, CameraMonitor, Sensor};
+pub use source::{select_source, SourceHealth};
 
@@ -1,92 +1,124 @@
-use crate::camera::platform_macos::{self, ParserState};
+use crate::camera::platform_macos::AttributionSnapshot;
+use crate::camera::source::{CameraSource, SourceHealth, StatusReporter};
 use anyhow::{Context, Result};
 use chrono::Utc;
 use serde::{Deserialize, Serialize};
-use std::process::{Child, Command, Stdio};
 use std::sync::mpsc;
-use std::thread;
+
+/// The privacy-sensitive device an event refers to.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Sensor {
+    Camera,
+    Microphone,
+}
+
+impl std::fmt::Display for Sensor {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Sensor::Camera => write!(f, "camera"),
+            Sensor::Microphone => write!(f, "microphone"),
+        }
+    }
+}
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub enum CameraEvent {
     Started {
         app_name: String,
+        /// Bundle identifier, when the source reports one (ControlCenter).
+        bundle_id: Option<String>,
+        /// PID of the process using the sensor, when the source knows it.
+        pid: Option<u32>,
+        sensor: Sensor,
         timestamp: chrono::DateTime<Utc>,
     },
     Stopped {
         app_name: String,
+        /// Bundle identifier, when the source reports one (ControlCenter).
+        bundle_id: Option<String>,
+        /// PID of the process using the sensor, when the source knows it.
+        pid: Option<u32>,
+        sensor: Sensor,
         timestamp: chrono::DateTime<Utc>,
     },
+    /// Another origin reported an app that already has a session, identified
+    /// by the same fields as the `Started` event that opened it.
+    Confirmed {
+        app_name: String,
+        bundle_id: Option<String>,
+        pid: Option<u32>,
+        sensor: Sensor,
+        /// The origin that confirmed it, e.g. a log subsystem name.
+        source: String,
+    },
 }
 
+/// Owns the active `CameraSource` and tracks whether it has been started.
 pub struct CameraMonitor {
-    child: Option<Child>,
+    source: Option<Box<dyn CameraSource>>,
     is_running: bool,
+    status_tx: Option<mpsc::Sender<SourceHealth>>,
 }
 
 impl CameraMonitor {
     pub fn new() -> Self {
         Self {
-            child: None,
+            source: None,
             is_running: false,
+            status_tx: None,
         }
     }
 
-    pub fn is_running(&self) -> bool {
-        self.is_running
-    }
-
-    pub fn start(&mut self) -> Result<mpsc::Receiver<CameraEvent>> {
+    /// Returns a receiver for every health transition of the sources this
+    /// monitor starts from now on.
+    pub fn watch_status(&mut self) -> mpsc::Receiver<SourceHealth> {
         let (tx, rx) = mpsc::channel();
+        self.status_tx = Some(tx);
+        rx
+    }
 
-        // Auto-detect which macOS logging subsystem reports camera events.
-        // This mirrors the detection logic in webcam_log.sh — it checks the
-        // last 5 minutes of logs for each subsystem in priority order:
-        //   1. controlcenter  (macOS Sonoma 14+)
-        //   2. SkyLight       (some Ventura builds)
-        //   3. cameracapture  (older macOS)
-        //   4. cmio           (CoreMediaIO fallback)
-        let subsystem = platform_macos::detect_subsystem();
-        eprintln!("[webcam-tracker] Detected camera subsystem: {}", subsystem);
+    /// Replaces the event source, stopping the previous one if it was running.
+    pub fn set_source(&mut self, source: Box<dyn CameraSource>) {
+        self.stop();
+        self.source = Some(source);
+    }
 
-        let predicate = platform_macos::predicate_for(&subsystem);
+    pub fn source_name(&self) -> Option<&'static str> {
+        self.source.as_ref().map(|s| s.name())
+    }
 
-        let mut child = Command::new("log")
-            .args(["stream", "--style", "syslog", "--predicate", predicate, "--info"])
-            .stdout(Stdio::piped())
-            .stderr(Stdio::null())
-            .spawn()
-            .context("Failed to spawn log stream process")?;
+    pub fn is_running(&self) -> bool {
+        self.is_running
+    }
 
-        let stdout = child.stdout.take().context("Failed to get stdout")?;
-        self.child = Some(child);
+    pub fn health(&self) -> SourceHealth {
+        match &self.source {
+            Some(source) => source.health(),
+            None => SourceHealth::Stopped,
+        }
+    }
 
-        // Spawn thread to parse log output
-        let tx_clone = tx.clone();
-        thread::spawn(move || {
-            use std::io::{BufRead, BufReader};
-            let reader = BufReader::new(stdout);
-            let mut state = ParserState::default();
+    /// The running source's latest attribution set, if it reports one.
+    pub fn attributions(&self) -> Option<AttributionSnapshot> {
+        match &self.source {
+            Some(source) if self.is_running => source.attributions(),
+            _ => None,
+        }
+    }
 
-            for line in reader.lines() {
-                if let Ok(line) = line {
-                    let events = platform_macos::parse_log_line(&line, &subsystem, &mut state);
-                    for event in events {
-                        if tx_clone.send(event).is_err() {
-                            return;
-                        }
-                    }
-                }
-            }
-        });
+    pub fn start(&mut self) -> Result<mpsc::Receiver<CameraEvent>> {
+        let source = self.source.as_mut().context("No camera source configured")?;
+        eprintln!("[webcam-tracker] Starting camera source: {}", source.name());
 
+        let rx = source.start(StatusReporter::new(self.status_tx.clone()))?;
         self.is_running = true;
         Ok(rx)
     }
 
     pub fn stop(&mut self) {
-        if let Some(mut child) = self.child.take() {
-            let _ = child.kill();
-            let _ = child.wait();
+        if let Some(source) = self.source.as_mut() {
+            source.stop();
         }
         self.is_running = false;
     }
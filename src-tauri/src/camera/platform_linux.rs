@@ -0,0 +1,435 @@
+use crate::camera::journal::Journal;
+use crate::camera::monitor::{CameraEvent, Sensor};
+use crate::camera::source::{CameraSource, SourceHealth, StatusReporter};
+use anyhow::Result;
+use chrono::Utc;
+use std::collections::HashSet;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{mpsc, Arc};
+use std::thread;
+use std::time::Duration;
+
+/// Default procfs mount point.
+pub const DEFAULT_PROCFS_ROOT: &str = "/proc";
+
+/// How often `/proc` is rescanned for open video device handles.
+pub const DEFAULT_POLL_INTERVAL: Duration = Duration::from_secs(1);
+
+/// Prefix of the V4L2 device nodes we consider to be cameras.
+const VIDEO_DEVICE_PREFIX: &str = "/dev/video";
+
+// ---------------------------------------------------------------------------
+// Device handle scanning
+// ---------------------------------------------------------------------------
+
+/// A single process holding a single `/dev/video*` device open.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct DeviceHandle {
+    pub pid: u32,
+    pub process_name: String,
+    pub device: PathBuf,
+}
+
+/// Walks `<root>/*/fd` looking for file descriptors that resolve to a video
+/// device, and diffs successive scans into `CameraEvent`s.
+///
+/// The procfs root is configurable so the scanner can be pointed at a fixture
+/// directory tree in tests.
+#[derive(Debug)]
+pub struct ProcfsScanner {
+    root: PathBuf,
+    open_handles: HashSet<DeviceHandle>,
+}
+
+impl ProcfsScanner {
+    pub fn new(root: impl Into<PathBuf>) -> Self {
+        Self {
+            root: root.into(),
+            open_handles: HashSet::new(),
+        }
+    }
+
+    /// Returns every (process, device) pair currently open under the root.
+    ///
+    /// Processes that disappear mid-scan or whose `fd` directory we are not
+    /// allowed to read are silently skipped.
+    pub fn scan(&self) -> HashSet<DeviceHandle> {
+        let mut handles = HashSet::new();
+
+        let entries = match fs::read_dir(&self.root) {
+            Ok(entries) => entries,
+            Err(_) => return handles,
+        };
+
+        for entry in entries.flatten() {
+            let pid = match entry.file_name().to_str().and_then(|s| s.parse::<u32>().ok()) {
+                Some(pid) => pid,
+                None => continue,
+            };
+
+            let devices = open_video_devices(&entry.path().join("fd"));
+            if devices.is_empty() {
+                continue;
+            }
+
+            let process_name = read_process_name(&entry.path());
+            for device in devices {
+                handles.insert(DeviceHandle {
+                    pid,
+                    process_name: process_name.clone(),
+                    device,
+                });
+            }
+        }
+
+        handles
+    }
+
+    /// Rescans procfs and returns the events implied by the difference from
+    /// the previous scan.
+    ///
+    /// A process is reported as `Started` when it opens its first video
+    /// device and as `Stopped` once it no longer holds any, so an app that
+    /// opens several devices still produces a single session.
+    pub fn poll(&mut self) -> Vec<CameraEvent> {
+        let current = self.scan();
+        let now = Utc::now();
+        let mut events = Vec::new();
+
+        let previous = processes(&self.open_handles);
+        let current_processes = processes(&current);
+
+        for (pid, name) in &current_processes {
+            if !previous.iter().any(|(p, _)| p == pid) {
+                events.push(CameraEvent::Started {
+                    app_name: name.clone(),
+                    bundle_id: None,
+                    pid: Some(*pid),
+                    sensor: Sensor::Camera,
+                    timestamp: now,
+                });
+            }
+        }
+
+        for (pid, name) in &previous {
+            if !current_processes.iter().any(|(p, _)| p == pid) {
+                events.push(CameraEvent::Stopped {
+                    app_name: name.clone(),
+                    bundle_id: None,
+                    pid: Some(*pid),
+                    sensor: Sensor::Camera,
+                    timestamp: now,
+                });
+            }
+        }
+
+        self.open_handles = current;
+        events
+    }
+}
+
+/// Returns the `/dev/video*` targets of every symlink in a `fd` directory.
+fn open_video_devices(fd_dir: &Path) -> Vec<PathBuf> {
+    let entries = match fs::read_dir(fd_dir) {
+        Ok(entries) => entries,
+        Err(_) => return vec![],
+    };
+
+    let mut devices: Vec<PathBuf> = entries
+        .flatten()
+        .filter_map(|fd| fs::read_link(fd.path()).ok())
+        .filter(|target| target.to_string_lossy().starts_with(VIDEO_DEVICE_PREFIX))
+        .collect();
+
+    devices.sort();
+    devices.dedup();
+    devices
+}
+
+/// Reads the short process name from `<pid>/comm`, falling back to the
+/// basename of `<pid>/exe`.
+fn read_process_name(pid_dir: &Path) -> String {
+    if let Ok(comm) = fs::read_to_string(pid_dir.join("comm")) {
+        let name = comm.trim();
+        if !name.is_empty() {
+            return name.to_string();
+        }
+    }
+
+    fs::read_link(pid_dir.join("exe"))
+        .ok()
+        .and_then(|exe| exe.file_name().map(|n| n.to_string_lossy().into_owned()))
+        .unwrap_or_else(|| "Unknown".to_string())
+}
+
+/// Collapses device handles into the distinct (pid, process name) pairs
+/// holding at least one device, ordered by pid.
+fn processes(handles: &HashSet<DeviceHandle>) -> Vec<(u32, String)> {
+    let mut processes: Vec<(u32, String)> = handles
+        .iter()
+        .map(|h| (h.pid, h.process_name.clone()))
+        .collect();
+    processes.sort();
+    processes.dedup_by_key(|(pid, _)| *pid);
+    processes
+}
+
+// ---------------------------------------------------------------------------
+// Polling thread
+// ---------------------------------------------------------------------------
+
+/// Spawns a thread that polls procfs every `interval` and forwards events to
+/// `tx` (journaling them first, when given a journal) until `stop` is set or
+/// the receiver is dropped.
+pub fn spawn_poller(
+    root: PathBuf,
+    interval: Duration,
+    tx: mpsc::Sender<CameraEvent>,
+    stop: Arc<AtomicBool>,
+    journal: Option<Journal>,
+) -> thread::JoinHandle<()> {
+    thread::spawn(move || {
+        let mut scanner = ProcfsScanner::new(root);
+
+        while !stop.load(Ordering::Relaxed) {
+            for event in scanner.poll() {
+                if let Some(journal) = &journal {
+                    journal.record("procfs", None, &event);
+                }
+                if tx.send(event).is_err() {
+                    return;
+                }
+            }
+            thread::sleep(interval);
+        }
+    })
+}
+
+// ---------------------------------------------------------------------------
+// procfs camera source
+// ---------------------------------------------------------------------------
+
+/// Polls a procfs tree for open video devices on a background thread.
+pub struct ProcfsSource {
+    root: PathBuf,
+    interval: Duration,
+    stop: Option<Arc<AtomicBool>>,
+    poller: Option<thread::JoinHandle<()>>,
+    status: StatusReporter,
+    /// Where every detected event is journaled, if anywhere.
+    journal: Option<Journal>,
+}
+
+impl ProcfsSource {
+    pub fn new(root: impl Into<PathBuf>, interval: Duration) -> Self {
+        Self {
+            root: root.into(),
+            interval,
+            stop: None,
+            poller: None,
+            status: StatusReporter::default(),
+            journal: None,
+        }
+    }
+
+    /// Journals every detected event to `journal`.
+    pub fn with_journal(mut self, journal: Journal) -> Self {
+        self.journal = Some(journal);
+        self
+    }
+}
+
+impl Default for ProcfsSource {
+    fn default() -> Self {
+        Self::new(DEFAULT_PROCFS_ROOT, DEFAULT_POLL_INTERVAL)
+    }
+}
+
+impl CameraSource for ProcfsSource {
+    fn name(&self) -> &'static str {
+        "procfs"
+    }
+
+    fn start(&mut self, status: StatusReporter) -> Result<mpsc::Receiver<CameraEvent>> {
+        let (tx, rx) = mpsc::channel();
+        let stop = Arc::new(AtomicBool::new(false));
+
+        eprintln!(
+            "[webcam-tracker] Polling {} for open video devices",
+            self.root.display()
+        );
+        self.poller = Some(spawn_poller(
+            self.root.clone(),
+            self.interval,
+            tx,
+            stop.clone(),
+            self.journal.clone(),
+        ));
+        self.stop = Some(stop);
+        status.report(SourceHealth::Running);
+        self.status = status;
+
+        Ok(rx)
+    }
+
+    fn stop(&mut self) {
+        if let Some(stop) = self.stop.take() {
+            stop.store(true, Ordering::Relaxed);
+        }
+        self.poller = None;
+        self.status.report(SourceHealth::Stopped);
+    }
+
+    fn health(&self) -> SourceHealth {
+        match &self.poller {
+            None => SourceHealth::Stopped,
+            Some(poller) if poller.is_finished() => {
+                SourceHealth::Failed("procfs poller exited".to_string())
+            }
+            Some(_) if !self.root.is_dir() => {
+                SourceHealth::Failed(format!("{} is not readable", self.root.display()))
+            }
+            Some(_) => SourceHealth::Running,
+        }
+    }
+}
+
+// ---------------------------------------------------------------------------
+// Tests
+// ---------------------------------------------------------------------------
+
+#[cfg(all(test, unix))]
+mod tests {
+    use super::*;
+    use std::os::unix::fs::symlink;
+
+    /// Builds a throwaway procfs-like tree under the system temp directory.
+    struct FixtureProc {
+        root: PathBuf,
+    }
+
+    impl FixtureProc {
+        fn new(name: &str) -> Self {
+            let root = std::env::temp_dir().join(format!(
+                "webcam-tracker-procfs-{}-{}",
+                name,
+                std::process::id()
+            ));
+            let _ = fs::remove_dir_all(&root);
+            fs::create_dir_all(&root).unwrap();
+            Self { root }
+        }
+
+        fn add_process(&self, pid: u32, comm: &str) {
+            let dir = self.root.join(pid.to_string());
+            fs::create_dir_all(dir.join("fd")).unwrap();
+            fs::write(dir.join("comm"), format!("{}\n", comm)).unwrap();
+        }
+
+        fn open_fd(&self, pid: u32, fd: u32, target: &str) {
+            let link = self.root.join(pid.to_string()).join("fd").join(fd.to_string());
+            symlink(target, link).unwrap();
+        }
+
+        fn close_fd(&self, pid: u32, fd: u32) {
+            let link = self.root.join(pid.to_string()).join("fd").join(fd.to_string());
+            fs::remove_file(link).unwrap();
+        }
+    }
+
+    impl Drop for FixtureProc {
+        fn drop(&mut self) {
+            let _ = fs::remove_dir_all(&self.root);
+        }
+    }
+
+    #[test]
+    fn test_scan_finds_video_handles_only() {
+        let proc = FixtureProc::new("scan");
+        proc.add_process(100, "zoom");
+        proc.open_fd(100, 3, "/dev/video0");
+        proc.open_fd(100, 4, "/dev/null");
+        proc.add_process(200, "bash");
+        proc.open_fd(200, 0, "/dev/pts/0");
+        fs::create_dir_all(proc.root.join("self")).unwrap();
+
+        let handles = ProcfsScanner::new(&proc.root).scan();
+        assert_eq!(handles.len(), 1);
+        let handle = handles.iter().next().unwrap();
+        assert_eq!(handle.pid, 100);
+        assert_eq!(handle.process_name, "zoom");
+        assert_eq!(handle.device, PathBuf::from("/dev/video0"));
+    }
+
+    #[test]
+    fn test_poll_emits_started_then_stopped() {
+        let proc = FixtureProc::new("poll");
+        proc.add_process(100, "cheese");
+        let mut scanner = ProcfsScanner::new(&proc.root);
+        assert!(scanner.poll().is_empty());
+
+        proc.open_fd(100, 5, "/dev/video0");
+        let events = scanner.poll();
+        assert_eq!(events.len(), 1);
+        match &events[0] {
+            CameraEvent::Started { app_name, .. } => assert_eq!(app_name, "cheese"),
+            _ => panic!("Expected Started event"),
+        }
+
+        // No change between scans produces no events
+        assert!(scanner.poll().is_empty());
+
+        proc.close_fd(100, 5);
+        let events = scanner.poll();
+        assert_eq!(events.len(), 1);
+        match &events[0] {
+            CameraEvent::Stopped { app_name, .. } => assert_eq!(app_name, "cheese"),
+            _ => panic!("Expected Stopped event"),
+        }
+    }
+
+    #[test]
+    fn test_poll_second_device_does_not_restart() {
+        let proc = FixtureProc::new("second-device");
+        proc.add_process(100, "obs");
+        proc.open_fd(100, 3, "/dev/video0");
+        let mut scanner = ProcfsScanner::new(&proc.root);
+        assert_eq!(scanner.poll().len(), 1);
+
+        proc.open_fd(100, 4, "/dev/video2");
+        assert!(scanner.poll().is_empty());
+
+        proc.close_fd(100, 3);
+        assert!(scanner.poll().is_empty());
+
+        proc.close_fd(100, 4);
+        let events = scanner.poll();
+        assert_eq!(events.len(), 1);
+        assert!(matches!(events[0], CameraEvent::Stopped { .. }));
+    }
+
+    #[test]
+    fn test_poll_process_exit_stops() {
+        let proc = FixtureProc::new("exit");
+        proc.add_process(100, "firefox");
+        proc.open_fd(100, 3, "/dev/video0");
+        let mut scanner = ProcfsScanner::new(&proc.root);
+        assert_eq!(scanner.poll().len(), 1);
+
+        fs::remove_dir_all(proc.root.join("100")).unwrap();
+        let events = scanner.poll();
+        assert_eq!(events.len(), 1);
+        match &events[0] {
+            CameraEvent::Stopped { app_name, .. } => assert_eq!(app_name, "firefox"),
+            _ => panic!("Expected Stopped event"),
+        }
+    }
+
+    #[test]
+    fn test_missing_root_yields_nothing() {
+        let scanner = ProcfsScanner::new("/nonexistent/webcam-tracker-procfs");
+        assert!(scanner.scan().is_empty());
+    }
+}
@@ -1,7 +1,20 @@
-use crate::camera::monitor::CameraEvent;
-use chrono::Utc;
-use std::collections::HashSet;
-use std::process::Command;
+use crate::camera::journal::Journal;
+use crate::camera::monitor::{CameraEvent, Sensor};
+use crate::camera::reconcile::Reconciler;
+use crate::camera::source::{CameraSource, SourceHealth, StatusReporter};
+use crate::session::aliases::Aliases;
+use crate::storage;
+use anyhow::{Context, Result};
+use chrono::{DateTime, Local, NaiveDateTime, TimeZone, Utc};
+use serde::{Deserialize, Serialize};
+use std::collections::{HashMap, VecDeque};
+use std::io::{BufRead, BufReader};
+use std::path::{Path, PathBuf};
+use std::process::{Child, Command, ExitStatus, Stdio};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{mpsc, Arc, Mutex};
+use std::thread;
+use std::time::{Duration, Instant};
 
 // ---------------------------------------------------------------------------
 // Subsystem detection — mirrors webcam_log.sh auto-detection
@@ -9,10 +22,12 @@ use std::process::Command;
 
 /// The macOS logging subsystems that can report camera activity.
 /// Different macOS versions use different subsystems.
-#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "lowercase")]
 pub enum Subsystem {
     /// `com.apple.controlcenter` — macOS Sonoma (14+).
-    /// Emits `activeCameraAttributions` messages with `[cam] AppName (bundle.id)`.
+    /// Emits `activeCameraAttributions` messages with `[cam] AppName (bundle.id)`
+    /// and `activeMicrophoneAttributions` messages with `[mic] AppName (bundle.id)`.
     ControlCenter,
     /// `com.apple.SkyLight` — some macOS Ventura builds.
     /// Emits `camera status 0` / `camera status 1` messages.
@@ -36,48 +51,222 @@ impl std::fmt::Display for Subsystem {
     }
 }
 
+impl std::str::FromStr for Subsystem {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        match s.trim().to_ascii_lowercase().as_str() {
+            "controlcenter" => Ok(Subsystem::ControlCenter),
+            "skylight" => Ok(Subsystem::SkyLight),
+            "cameracapture" => Ok(Subsystem::CameraCapture),
+            "cmio" => Ok(Subsystem::Cmio),
+            other => anyhow::bail!("Unknown camera subsystem: {}", other),
+        }
+    }
+}
+
+impl Subsystem {
+    /// Every subsystem, in detection priority order.
+    pub const ALL: [Subsystem; 4] = [
+        Subsystem::ControlCenter,
+        Subsystem::SkyLight,
+        Subsystem::CameraCapture,
+        Subsystem::Cmio,
+    ];
+
+    /// The unified-log subsystem identifier, as it appears in predicates and
+    /// in the `subsystem` field of ndjson entries.
+    pub fn identifier(&self) -> &'static str {
+        match self {
+            Subsystem::ControlCenter => "com.apple.controlcenter",
+            Subsystem::SkyLight => "com.apple.SkyLight",
+            Subsystem::CameraCapture => "com.apple.cameracapture",
+            Subsystem::Cmio => "com.apple.cmio",
+        }
+    }
+}
+
+/// Which subsystems a `LogStreamSource` streams.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub enum SubsystemSelection {
+    /// Stream the single subsystem picked by `detect_subsystem`.
+    #[default]
+    Detect,
+    /// Stream all of these at once and reconcile their events.
+    Fixed(Vec<Subsystem>),
+}
+
+impl std::str::FromStr for SubsystemSelection {
+    type Err = anyhow::Error;
+
+    /// Accepts `auto`, `all`, or a comma-separated list of subsystem names.
+    fn from_str(s: &str) -> Result<Self> {
+        match s.trim().to_ascii_lowercase().as_str() {
+            "" | "auto" => return Ok(SubsystemSelection::Detect),
+            "all" => return Ok(SubsystemSelection::Fixed(Subsystem::ALL.to_vec())),
+            _ => {}
+        }
+
+        let mut subsystems = Vec::new();
+        for name in s.split(',') {
+            let subsystem: Subsystem = name.parse()?;
+            if !subsystems.contains(&subsystem) {
+                subsystems.push(subsystem);
+            }
+        }
+        Ok(SubsystemSelection::Fixed(subsystems))
+    }
+}
+
+/// What a single `log show --last 5m` probe found for one subsystem.
+#[derive(Debug, Clone, Serialize)]
+pub struct ProbeEvidence {
+    pub subsystem: Subsystem,
+    /// Number of recent log lines containing the subsystem's camera marker.
+    pub matches: usize,
+    /// The first matching line, if any.
+    pub sample: Option<String>,
+    /// Why the probe could not run, if it failed.
+    pub error: Option<String>,
+}
+
+/// The outcome of probing every subsystem.
+#[derive(Debug, Clone, Serialize)]
+pub struct Detection {
+    pub subsystem: Subsystem,
+    /// True when no probe matched and ControlCenter was assumed.
+    pub fallback: bool,
+    /// macOS build the detection was made on (`sw_vers -buildVersion`).
+    pub os_build: Option<String>,
+    /// Evidence from each probe, in priority order.
+    pub probes: Vec<ProbeEvidence>,
+}
+
+/// `meta` keys the detection result is cached under.
+const META_DETECTED_SUBSYSTEM: &str = "detected_subsystem";
+const META_DETECTED_OS_BUILD: &str = "detected_os_build";
+
 /// Auto-detects which logging subsystem reports camera events on this Mac.
 /// Checks the last 5 minutes of logs — the same strategy used in webcam_log.sh.
 pub fn detect_subsystem() -> Subsystem {
-    // 1. controlcenter (macOS Sonoma+)
-    if probe_logs(
-        r#"subsystem == "com.apple.controlcenter" AND eventMessage CONTAINS "activeCameraAttributions""#,
-        "activeCameraAttributions",
-    ) {
-        return Subsystem::ControlCenter;
-    }
+    choose_subsystem(&probe_all()).0
+}
 
-    // 2. SkyLight
-    if probe_logs(
-        r#"subsystem == "com.apple.SkyLight" AND eventMessage CONTAINS "camera status""#,
-        "camera status",
-    ) {
-        return Subsystem::SkyLight;
-    }
+/// Returns the subsystem detected on this OS build, probing only when nothing
+/// is cached in the database at `db_path` or the OS build has changed since.
+pub fn cached_subsystem(db_path: &Path) -> Subsystem {
+    let os_build = current_os_build();
 
-    // 3. cameracapture
-    if probe_logs(
-        r#"subsystem == "com.apple.cameracapture""#,
-        "startRunning",
-    ) {
-        return Subsystem::CameraCapture;
+    match cached_detection(db_path) {
+        Some((subsystem, cached_build)) if cached_build == os_build => subsystem,
+        _ => redetect(db_path, os_build).subsystem,
     }
+}
 
-    // 4. cmio
-    if probe_logs(
-        r#"subsystem == "com.apple.cmio""#,
-        "CMIODeviceStartStream",
-    ) {
-        return Subsystem::Cmio;
+/// The cached detection result and the OS build it was made on, if any.
+pub fn cached_detection(db_path: &Path) -> Option<(Subsystem, Option<String>)> {
+    let subsystem = storage::get_meta(db_path, META_DETECTED_SUBSYSTEM)
+        .ok()
+        .flatten()?
+        .parse()
+        .ok()?;
+    let os_build = storage::get_meta(db_path, META_DETECTED_OS_BUILD)
+        .ok()
+        .flatten()
+        .filter(|build| !build.is_empty());
+
+    Some((subsystem, os_build))
+}
+
+/// Probes every subsystem, caches the result in the database at `db_path`
+/// and returns it with the evidence from each probe.
+pub fn redetect(db_path: &Path, os_build: Option<String>) -> Detection {
+    let probes = probe_all();
+    let (subsystem, fallback) = choose_subsystem(&probes);
+    let detection = Detection {
+        subsystem,
+        fallback,
+        os_build,
+        probes,
+    };
+
+    let stored = storage::set_meta(
+        db_path,
+        META_DETECTED_SUBSYSTEM,
+        &detection.subsystem.to_string(),
+    )
+    .and_then(|_| {
+        storage::set_meta(
+            db_path,
+            META_DETECTED_OS_BUILD,
+            detection.os_build.as_deref().unwrap_or_default(),
+        )
+    });
+    if let Err(e) = stored {
+        eprintln!(
+            "[webcam-tracker] Failed to cache subsystem detection: {}",
+            e
+        );
     }
 
-    // Default fallback — controlcenter is the most common on modern macOS
-    Subsystem::ControlCenter
+    detection
+}
+
+/// Picks the highest-priority subsystem with any evidence, falling back to
+/// ControlCenter — the most common on modern macOS. The flag is true for the
+/// fallback.
+pub fn choose_subsystem(probes: &[ProbeEvidence]) -> (Subsystem, bool) {
+    Subsystem::ALL
+        .iter()
+        .find(|s| probes.iter().any(|p| p.subsystem == **s && p.matches > 0))
+        .map(|s| (*s, false))
+        .unwrap_or((Subsystem::ControlCenter, true))
 }
 
-/// Runs `log show --last 5m` with the given predicate and checks whether any
-/// output line contains `needle`.
-fn probe_logs(predicate: &str, needle: &str) -> bool {
+/// Runs the probe for every subsystem concurrently and returns the evidence
+/// in priority order.
+pub fn probe_all() -> Vec<ProbeEvidence> {
+    thread::scope(|scope| {
+        let probes: Vec<_> = Subsystem::ALL
+            .iter()
+            .map(|subsystem| scope.spawn(move || probe_logs(*subsystem)))
+            .collect();
+        probes
+            .into_iter()
+            .zip(Subsystem::ALL)
+            .map(|(probe, subsystem)| {
+                probe.join().unwrap_or_else(|_| ProbeEvidence {
+                    subsystem,
+                    matches: 0,
+                    sample: None,
+                    error: Some("probe panicked".to_string()),
+                })
+            })
+            .collect()
+    })
+}
+
+/// The `log show` predicate and the marker a subsystem's camera messages
+/// contain — the same checks as webcam_log.sh.
+fn probe_for(subsystem: Subsystem) -> (&'static str, &'static str) {
+    match subsystem {
+        Subsystem::ControlCenter => (
+            r#"subsystem == "com.apple.controlcenter" AND eventMessage CONTAINS "activeCameraAttributions""#,
+            "activeCameraAttributions",
+        ),
+        Subsystem::SkyLight => (
+            r#"subsystem == "com.apple.SkyLight" AND eventMessage CONTAINS "camera status""#,
+            "camera status",
+        ),
+        Subsystem::CameraCapture => (r#"subsystem == "com.apple.cameracapture""#, "startRunning"),
+        Subsystem::Cmio => (r#"subsystem == "com.apple.cmio""#, "CMIODeviceStartStream"),
+    }
+}
+
+/// Runs `log show --last 5m` with the subsystem's probe predicate and
+/// collects the output lines that contain its marker.
+fn probe_logs(subsystem: Subsystem) -> ProbeEvidence {
+    let (predicate, needle) = probe_for(subsystem);
     let output = Command::new("log")
         .args(["show", "--last", "5m", "--predicate", predicate, "--info"])
         .output();
@@ -85,12 +274,47 @@ fn probe_logs(predicate: &str, needle: &str) -> bool {
     match output {
         Ok(out) => {
             let stdout = String::from_utf8_lossy(&out.stdout);
-            stdout.contains(needle)
+            let (matches, sample) = probe_matches(&stdout, needle);
+            ProbeEvidence {
+                subsystem,
+                matches,
+                sample,
+                error: None,
+            }
         }
-        Err(_) => false,
+        Err(e) => ProbeEvidence {
+            subsystem,
+            matches: 0,
+            sample: None,
+            error: Some(e.to_string()),
+        },
     }
 }
 
+/// Counts the lines of `output` containing `needle` and returns the first.
+fn probe_matches(output: &str, needle: &str) -> (usize, Option<String>) {
+    let mut lines = output.lines().filter(|line| line.contains(needle));
+    let sample = lines.next().map(|line| line.trim().to_string());
+    let matches = sample.iter().count() + lines.count();
+    (matches, sample)
+}
+
+/// The macOS build (e.g. `23F79`), read from `sw_vers`.
+pub fn current_os_build() -> Option<String> {
+    let output = Command::new("sw_vers").output().ok()?;
+    parse_os_build(&String::from_utf8_lossy(&output.stdout))
+}
+
+/// Extracts `BuildVersion` from `sw_vers` output.
+fn parse_os_build(sw_vers: &str) -> Option<String> {
+    sw_vers
+        .lines()
+        .filter_map(|line| line.split_once(':'))
+        .find(|(key, _)| key.trim() == "BuildVersion")
+        .map(|(_, value)| value.trim().to_string())
+        .filter(|build| !build.is_empty())
+}
+
 // ---------------------------------------------------------------------------
 // Predicate strings for `log stream` — one per subsystem
 // ---------------------------------------------------------------------------
@@ -99,10 +323,12 @@ fn probe_logs(predicate: &str, needle: &str) -> bool {
 pub fn predicate_for(subsystem: &Subsystem) -> &'static str {
     match subsystem {
         Subsystem::ControlCenter => {
-            r#"(subsystem == "com.apple.controlcenter") AND (eventMessage CONTAINS "activeCameraAttributions")"#
+            r#"(subsystem == "com.apple.controlcenter") AND (eventMessage CONTAINS "activeCameraAttributions" OR eventMessage CONTAINS "activeMicrophoneAttributions")"#
         }
+        // WindowServer logs SkyLight's camera status, so AVCaptureSession
+        // lines are streamed too, to tell which app opened the camera
         Subsystem::SkyLight => {
-            r#"(subsystem == "com.apple.SkyLight") AND (eventMessage CONTAINS "camera status")"#
+            r#"((subsystem == "com.apple.SkyLight") AND (eventMessage CONTAINS "camera status")) OR ((subsystem == "com.apple.cameracapture") AND (eventMessage CONTAINS "startRunning]" OR eventMessage CONTAINS "stopRunning]"))"#
         }
         Subsystem::CameraCapture => {
             r#"(subsystem == "com.apple.cameracapture") AND (eventMessage CONTAINS "startRunning]" OR eventMessage CONTAINS "stopRunning]")"#
@@ -113,12 +339,413 @@ pub fn predicate_for(subsystem: &Subsystem) -> &'static str {
     }
 }
 
+/// Returns a `--predicate` matching any of `subsystems`.
+pub fn combined_predicate(subsystems: &[Subsystem]) -> String {
+    subsystems
+        .iter()
+        .map(|s| format!("({})", predicate_for(s)))
+        .collect::<Vec<_>>()
+        .join(" OR ")
+}
+
+// ---------------------------------------------------------------------------
+// `log stream` camera source
+// ---------------------------------------------------------------------------
+
+/// First delay before restarting a `log stream` child that died.
+const RESTART_BASE_DELAY: Duration = Duration::from_secs(1);
+/// Upper bound for the exponential restart delay.
+const RESTART_MAX_DELAY: Duration = Duration::from_secs(60);
+/// A child that stayed up at least this long is considered healthy, so its
+/// death restarts the backoff from `RESTART_BASE_DELAY`.
+const STABLE_RUN: Duration = Duration::from_secs(60);
+/// Number of trailing stderr lines kept to explain why a child died.
+const STDERR_TAIL_LINES: usize = 5;
+
+/// Streams the unified log through `log stream --style ndjson` and parses
+/// each line with `parse_log_line`.
+///
+/// By default it streams the one subsystem `detect_subsystem` picks, cached
+/// per OS build in the database when one is given. With
+/// `SubsystemSelection::Fixed` it streams several in a single predicate, tags
+/// each line with the subsystem it came from and merges their events through
+/// a `Reconciler`.
+///
+/// The child runs under a supervisor thread: when it exits or its pipe
+/// closes, the supervisor records its stderr and spawns a new child after an
+/// exponential backoff. When detecting, it looks the subsystem up again before
+/// each spawn, which only probes after an OS update. The event receiver stays
+/// connected across restarts.
+pub struct LogStreamSource {
+    subsystems: SubsystemSelection,
+    /// Database the detected subsystem is cached in, if any.
+    detection_cache: Option<PathBuf>,
+    /// Where every parsed event is journaled, if anywhere.
+    journal: Option<Journal>,
+    /// Alias rules the reconciler matches app names by.
+    aliases: Arc<Mutex<Aliases>>,
+    /// ControlCenter's latest attribution lists, while it is streamed.
+    attributions: SharedAttributions,
+    child: Arc<Mutex<Option<Child>>>,
+    stop: Arc<AtomicBool>,
+    status: StatusReporter,
+}
+
+impl LogStreamSource {
+    pub fn new(subsystems: SubsystemSelection, detection_cache: Option<PathBuf>) -> Self {
+        Self {
+            subsystems,
+            detection_cache,
+            journal: None,
+            aliases: Arc::default(),
+            attributions: SharedAttributions::default(),
+            child: Arc::new(Mutex::new(None)),
+            stop: Arc::new(AtomicBool::new(false)),
+            status: StatusReporter::default(),
+        }
+    }
+
+    /// Journals every parsed event, before reconciling, to `journal`.
+    pub fn with_journal(mut self, journal: Journal) -> Self {
+        self.journal = Some(journal);
+        self
+    }
+
+    /// Reconciles several subsystems by app names after `aliases`, so one
+    /// app reported under different names gets a single session.
+    pub fn with_aliases(mut self, aliases: Arc<Mutex<Aliases>>) -> Self {
+        self.aliases = aliases;
+        self
+    }
+}
+
+impl CameraSource for LogStreamSource {
+    fn name(&self) -> &'static str {
+        "logstream"
+    }
+
+    fn start(&mut self, status: StatusReporter) -> Result<mpsc::Receiver<CameraEvent>> {
+        let (tx, rx) = mpsc::channel();
+
+        // Each run gets its own stop flag and child slot, so a supervisor
+        // still winding down from an earlier run can't touch this one.
+        self.stop = Arc::new(AtomicBool::new(false));
+        self.child = Arc::new(Mutex::new(None));
+        self.status = status.clone();
+
+        let subsystems = self.subsystems.clone();
+        let detection_cache = self.detection_cache.clone();
+        let mut stream = LogStreamState::new(vec![], self.journal.clone(), self.aliases.clone());
+        stream.attributions = self.attributions.clone();
+        let child = self.child.clone();
+        let stop = self.stop.clone();
+        thread::spawn(move || {
+            supervise(subsystems, detection_cache, stream, tx, status, stop, child)
+        });
+
+        Ok(rx)
+    }
+
+    fn stop(&mut self) {
+        self.stop.store(true, Ordering::Relaxed);
+        if let Some(mut child) = self.child.lock().unwrap().take() {
+            let _ = child.kill();
+            let _ = child.wait();
+        }
+        self.status.report(SourceHealth::Stopped);
+    }
+
+    fn health(&self) -> SourceHealth {
+        self.status.current()
+    }
+
+    fn attributions(&self) -> Option<AttributionSnapshot> {
+        self.attributions.lock().unwrap().clone()
+    }
+}
+
+/// ControlCenter attribution lists shared between the stream thread and the
+/// source.
+type SharedAttributions = Arc<Mutex<Option<AttributionSnapshot>>>;
+
+/// Keeps a `log stream` child running until `stop` is set or the event
+/// receiver is dropped, restarting it with exponential backoff whenever it
+/// dies.
+///
+/// `stop` and `child` belong to this run alone; health is only reported
+/// while `stop` is unset.
+fn supervise(
+    selection: SubsystemSelection,
+    detection_cache: Option<PathBuf>,
+    mut stream: LogStreamState,
+    tx: mpsc::Sender<CameraEvent>,
+    status: StatusReporter,
+    stop: Arc<AtomicBool>,
+    child: Arc<Mutex<Option<Child>>>,
+) {
+    let mut attempt = 0;
+
+    while !stop.load(Ordering::Relaxed) {
+        let subsystems = match &selection {
+            SubsystemSelection::Detect => {
+                // Auto-detect which macOS logging subsystem reports camera
+                // events. This mirrors the detection logic in webcam_log.sh —
+                // it checks the last 5 minutes of logs for each subsystem in
+                // priority order:
+                //   1. controlcenter  (macOS Sonoma 14+)
+                //   2. SkyLight       (some Ventura builds)
+                //   3. cameracapture  (older macOS)
+                //   4. cmio           (CoreMediaIO fallback)
+                // The answer is cached per OS build, so only the first start
+                // after an update (or `redetect_subsystem`) pays for probing.
+                let subsystem = match &detection_cache {
+                    Some(db_path) => cached_subsystem(db_path),
+                    None => detect_subsystem(),
+                };
+                eprintln!("[webcam-tracker] Detected camera subsystem: {}", subsystem);
+                vec![subsystem]
+            }
+            SubsystemSelection::Fixed(subsystems) => subsystems.clone(),
+        };
+
+        // Diffing state only carries over while the subsystems stay the same
+        if stream.subsystems != subsystems {
+            stream.reset(subsystems);
+        }
+
+        let started_at = Instant::now();
+        let reason = match run_log_stream(&mut stream, &tx, &status, &stop, &child) {
+            Ok(Some(reason)) => reason,
+            Ok(None) => break,
+            Err(e) => format!("{:#}", e),
+        };
+
+        if stop.load(Ordering::Relaxed) {
+            break;
+        }
+
+        if started_at.elapsed() >= STABLE_RUN {
+            attempt = 0;
+        }
+        attempt += 1;
+        let delay = restart_delay(attempt);
+
+        eprintln!(
+            "[webcam-tracker] {}; restarting in {}s (attempt {})",
+            reason,
+            delay.as_secs(),
+            attempt
+        );
+        status.report(SourceHealth::Restarting {
+            attempt,
+            retry_in_secs: delay.as_secs(),
+            reason,
+        });
+
+        let deadline = Instant::now() + delay;
+        while !stop.load(Ordering::Relaxed) && Instant::now() < deadline {
+            thread::sleep(Duration::from_millis(100));
+        }
+    }
+
+    if let Some(mut child) = child.lock().unwrap().take() {
+        let _ = child.kill();
+        let _ = child.wait();
+    }
+    // Once stopped, `stop` has reported for us and a newer run may already
+    // own the status.
+    if !stop.load(Ordering::Relaxed) {
+        status.report(SourceHealth::Stopped);
+    }
+}
+
+/// Spawns one `log stream` child and forwards its events until its stdout
+/// closes.
+///
+/// Returns why the child died, or `None` if the source was stopped or the
+/// event receiver was dropped and nothing should be restarted.
+fn run_log_stream(
+    stream: &mut LogStreamState,
+    tx: &mpsc::Sender<CameraEvent>,
+    status: &StatusReporter,
+    stop: &AtomicBool,
+    child: &Mutex<Option<Child>>,
+) -> Result<Option<String>> {
+    let (stdout, stderr) = {
+        // Spawn under the lock so `stop` either sees the child or stops us
+        // from spawning it.
+        let mut slot = child.lock().unwrap();
+        if stop.load(Ordering::Relaxed) {
+            return Ok(None);
+        }
+
+        let mut spawned = Command::new("log")
+            .args([
+                "stream",
+                "--style",
+                "ndjson",
+                "--predicate",
+                &combined_predicate(&stream.subsystems),
+                "--info",
+            ])
+            .stdout(Stdio::piped())
+            .stderr(Stdio::piped())
+            .spawn()
+            .context("Failed to spawn log stream process")?;
+
+        let stdout = spawned.stdout.take().context("Failed to get stdout")?;
+        let stderr = spawned.stderr.take().context("Failed to get stderr")?;
+        *slot = Some(spawned);
+        status.report(SourceHealth::Running);
+        (stdout, stderr)
+    };
+
+    let stderr_reader =
+        thread::spawn(move || stderr_tail(BufReader::new(stderr), STDERR_TAIL_LINES));
+
+    for line in BufReader::new(stdout).lines().map_while(Result::ok) {
+        for event in stream.route_line(&line) {
+            if tx.send(event).is_err() {
+                return Ok(None);
+            }
+        }
+    }
+
+    let exit_status = child
+        .lock()
+        .unwrap()
+        .take()
+        .and_then(|mut exited| exited.wait().ok());
+    let stderr = stderr_reader.join().unwrap_or_default();
+
+    Ok(Some(exit_reason(exit_status, &stderr)))
+}
+
+/// Parser and reconciler state for one set of streamed subsystems, kept
+/// across restarts of the `log stream` child.
+struct LogStreamState {
+    subsystems: Vec<Subsystem>,
+    parser: ParserState,
+    reconciler: Reconciler,
+    aliases: Arc<Mutex<Aliases>>,
+    journal: Option<Journal>,
+    /// Where ControlCenter's latest attribution lists are published.
+    attributions: SharedAttributions,
+}
+
+impl LogStreamState {
+    fn new(
+        subsystems: Vec<Subsystem>,
+        journal: Option<Journal>,
+        aliases: Arc<Mutex<Aliases>>,
+    ) -> Self {
+        Self {
+            subsystems,
+            parser: ParserState::default(),
+            reconciler: Reconciler::with_aliases(aliases.clone()),
+            aliases,
+            journal,
+            attributions: SharedAttributions::default(),
+        }
+    }
+
+    /// Switches to streaming `subsystems`, dropping the diffing state of
+    /// the previous ones.
+    fn reset(&mut self, subsystems: Vec<Subsystem>) {
+        self.subsystems = subsystems;
+        self.parser = ParserState::default();
+        self.reconciler = Reconciler::with_aliases(self.aliases.clone());
+        *self.attributions.lock().unwrap() = None;
+    }
+
+    /// Parses one line of `log stream` output into the events to forward,
+    /// then publishes ControlCenter's attribution lists if it has any.
+    fn route_line(&mut self, line: &str) -> Vec<CameraEvent> {
+        let events = self.parse_line(line);
+        if let Some(snapshot) = self.parser.attributions() {
+            *self.attributions.lock().unwrap() = Some(snapshot);
+        }
+        events
+    }
+
+    /// Every parsed event is journaled; lines from several subsystems then
+    /// go through the reconciler.
+    fn parse_line(&mut self, line: &str) -> Vec<CameraEvent> {
+        match self.subsystems.as_slice() {
+            [subsystem] => {
+                let events = parse_log_line(line, subsystem, &mut self.parser);
+                if let Some(journal) = &self.journal {
+                    for event in &events {
+                        journal.record(&subsystem.to_string(), Some(line), event);
+                    }
+                }
+                events
+            }
+            subsystems => {
+                let mut events = Vec::new();
+                for (origin, event) in parse_tagged_line(line, subsystems, &mut self.parser) {
+                    let origin = origin.to_string();
+                    if let Some(journal) = &self.journal {
+                        journal.record(&origin, Some(line), &event);
+                    }
+                    events.extend(self.reconciler.apply(&origin, event));
+                }
+                events
+            }
+        }
+    }
+}
+
+/// Delay before restart number `attempt` (1-based): doubles from
+/// `RESTART_BASE_DELAY` up to `RESTART_MAX_DELAY`.
+fn restart_delay(attempt: u32) -> Duration {
+    let factor = 1u32
+        .checked_shl(attempt.saturating_sub(1))
+        .unwrap_or(u32::MAX);
+    RESTART_BASE_DELAY
+        .saturating_mul(factor)
+        .min(RESTART_MAX_DELAY)
+}
+
+/// Reads `reader` to the end and returns its last `max_lines` non-empty
+/// lines joined with `; `.
+fn stderr_tail<R: BufRead>(reader: R, max_lines: usize) -> String {
+    let mut tail = VecDeque::with_capacity(max_lines);
+    for line in reader.lines().map_while(Result::ok) {
+        let line = line.trim();
+        if line.is_empty() {
+            continue;
+        }
+        if tail.len() == max_lines {
+            tail.pop_front();
+        }
+        tail.push_back(line.to_string());
+    }
+    Vec::from(tail).join("; ")
+}
+
+/// Human-readable explanation of why a `log stream` child went away.
+fn exit_reason(exit_status: Option<ExitStatus>, stderr: &str) -> String {
+    let mut reason = match exit_status {
+        Some(exit_status) => format!("log stream exited ({})", exit_status),
+        None => "log stream exited".to_string(),
+    };
+    if !stderr.is_empty() {
+        reason.push_str(": ");
+        reason.push_str(stderr);
+    }
+    reason
+}
+
 // ---------------------------------------------------------------------------
 // Log line parsers — one per subsystem
 // ---------------------------------------------------------------------------
 
 /// Parses a single log line and returns zero or more `CameraEvent`s.
 ///
+/// Lines holding an ndjson entry are handed to `parse_log_entry`; anything
+/// else is treated as `--style syslog` text.
+///
 /// Most subsystems produce at most one event per line, but `ControlCenter` can
 /// produce several (one per app that started or stopped) because a single log
 /// line lists *all* currently active camera apps.
@@ -127,81 +754,480 @@ pub fn parse_log_line(
     subsystem: &Subsystem,
     state: &mut ParserState,
 ) -> Vec<CameraEvent> {
+    let trimmed = line.trim_start();
+    if trimmed.starts_with('{') {
+        if let Ok(entry) = serde_json::from_str::<LogEntry>(trimmed) {
+            return parse_log_entry(&entry, subsystem, state);
+        }
+    }
+
     // Skip common noise lines
     if line.contains("Filtering the log") || line.contains("backtrace") {
         return vec![];
     }
 
+    let (process, pid) = extract_process(line);
+    let record = LogRecord {
+        message: line,
+        process,
+        pid,
+        category: None,
+        timestamp: event_timestamp(line),
+    };
+    dispatch(&record, subsystem, state)
+}
+
+/// Parses a line from a stream covering several subsystems, returning each
+/// event tagged with the subsystem the line came from.
+///
+/// The origin is the `subsystem` field of ndjson entries, or the subsystem
+/// identifier printed on syslog lines. Lines from none of `subsystems` are
+/// ignored.
+pub fn parse_tagged_line(
+    line: &str,
+    subsystems: &[Subsystem],
+    state: &mut ParserState,
+) -> Vec<(Subsystem, CameraEvent)> {
+    let origin = match line_origin(line, subsystems) {
+        Some(origin) => origin,
+        None => return vec![],
+    };
+
+    parse_log_line(line, &origin, state)
+        .into_iter()
+        .map(|event| (origin, event))
+        .collect()
+}
+
+/// Works out which of `subsystems` logged `line`.
+fn line_origin(line: &str, subsystems: &[Subsystem]) -> Option<Subsystem> {
+    let trimmed = line.trim_start();
+    if trimmed.starts_with('{') {
+        if let Ok(entry) = serde_json::from_str::<LogEntry>(trimmed) {
+            let identifier = entry.subsystem?;
+            return subsystems
+                .iter()
+                .find(|s| s.identifier().eq_ignore_ascii_case(&identifier))
+                .copied();
+        }
+    }
+
+    let line = line.to_ascii_lowercase();
+    subsystems
+        .iter()
+        .find(|s| line.contains(&s.identifier().to_ascii_lowercase()))
+        .copied()
+}
+
+/// Parses a single `--style ndjson` entry and returns zero or more
+/// `CameraEvent`s.
+///
+/// Entries from a different subsystem than the one being tracked are ignored,
+/// except that AVCaptureSession entries still attribute SkyLight events.
+pub fn parse_log_entry(
+    entry: &LogEntry,
+    subsystem: &Subsystem,
+    state: &mut ParserState,
+) -> Vec<CameraEvent> {
+    let foreign = entry
+        .subsystem
+        .as_deref()
+        .filter(|s| !s.eq_ignore_ascii_case(subsystem.identifier()));
+    if let Some(foreign) = foreign {
+        let attributes = *subsystem == Subsystem::SkyLight
+            && foreign.eq_ignore_ascii_case(Subsystem::CameraCapture.identifier());
+        if !attributes {
+            return vec![];
+        }
+    }
+
+    let process = entry
+        .process_image_path
+        .as_deref()
+        .and_then(|path| path.rsplit('/').next())
+        .filter(|name| !name.is_empty())
+        .unwrap_or("Unknown")
+        .to_string();
+    let timestamp = entry
+        .timestamp
+        .as_deref()
+        .and_then(parse_line_timestamp)
+        .unwrap_or_else(Utc::now);
+
+    let record = LogRecord {
+        message: &entry.event_message,
+        process,
+        pid: entry.process_id,
+        category: entry.category.as_deref(),
+        timestamp,
+    };
+    if foreign.is_some() {
+        state.note_capture_events(&parse_cameracapture(&record));
+        return vec![];
+    }
+    dispatch(&record, subsystem, state)
+}
+
+/// Applies the parser for `subsystem` to a normalized record.
+fn dispatch(
+    record: &LogRecord,
+    subsystem: &Subsystem,
+    state: &mut ParserState,
+) -> Vec<CameraEvent> {
     match subsystem {
-        Subsystem::ControlCenter => parse_controlcenter(line, state),
-        Subsystem::SkyLight => parse_skylight(line, state),
-        Subsystem::CameraCapture => parse_cameracapture(line),
-        Subsystem::Cmio => parse_cmio(line),
+        Subsystem::ControlCenter => parse_controlcenter(record, state),
+        Subsystem::SkyLight => parse_skylight(record, state),
+        Subsystem::CameraCapture => {
+            let events = parse_cameracapture(record);
+            state.note_capture_events(&events);
+            events
+        }
+        Subsystem::Cmio => {
+            let events = parse_cmio(record);
+            state.note_capture_events(&events);
+            events
+        }
     }
 }
 
+// ---------------------------------------------------------------------------
+// Log entries
+// ---------------------------------------------------------------------------
+
+/// One entry of `log stream --style ndjson` output.
+///
+/// Only the fields the parsers use are kept; the rest are ignored.
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct LogEntry {
+    #[serde(default)]
+    pub event_message: String,
+    pub process_image_path: Option<String>,
+    #[serde(rename = "processID")]
+    pub process_id: Option<u32>,
+    pub subsystem: Option<String>,
+    pub category: Option<String>,
+    /// `YYYY-MM-DD HH:MM:SS.ffffff±HHMM`, the same format as syslog lines.
+    pub timestamp: Option<String>,
+}
+
+/// The parts of a log entry the per-subsystem parsers look at, whichever
+/// `--style` it was read in.
+struct LogRecord<'a> {
+    /// The whole line for syslog, `eventMessage` for ndjson.
+    message: &'a str,
+    process: String,
+    pid: Option<u32>,
+    /// Only known for ndjson; syslog lines print the subsystem alone.
+    category: Option<&'a str>,
+    timestamp: DateTime<Utc>,
+}
+
 // ---------------------------------------------------------------------------
 // Parser state (needed for stateful subsystems like ControlCenter & SkyLight)
 // ---------------------------------------------------------------------------
 
+/// An app listed in a ControlCenter attribution message.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Attribution {
+    pub app_name: String,
+    pub bundle_id: Option<String>,
+}
+
+impl Attribution {
+    /// Stable identity of the app: its bundle ID when known, since display
+    /// names change between versions and locales.
+    pub fn identity(&self) -> &str {
+        self.bundle_id.as_deref().unwrap_or(&self.app_name)
+    }
+}
+
 /// Mutable state carried across log lines for subsystems that require diffing
 /// (e.g. ControlCenter lists *all* active apps on every line).
 #[derive(Debug, Default)]
 pub struct ParserState {
-    /// Currently known set of active camera apps (for ControlCenter).
-    pub active_apps: HashSet<String>,
+    /// Currently known active camera apps, keyed by `Attribution::identity`
+    /// (for ControlCenter).
+    pub active_apps: HashMap<String, Attribution>,
+    /// Currently known active microphone apps, keyed by
+    /// `Attribution::identity` (for ControlCenter).
+    pub active_mic_apps: HashMap<String, Attribution>,
     /// Last known camera state for SkyLight ("open" / "closed").
     pub skylight_state: Option<bool>,
+    /// Apps that capture subsystems (cameracapture, cmio) report as
+    /// currently using the camera, oldest first.
+    pub capture_starts: Vec<CaptureStart>,
+    /// The app the open SkyLight camera status was attributed to.
+    pub skylight_app: Option<CaptureStart>,
+    /// When ControlCenter last listed the camera apps (for ControlCenter).
+    pub camera_listed_at: Option<DateTime<Utc>>,
+    /// When ControlCenter last listed the microphone apps (for
+    /// ControlCenter).
+    pub microphone_listed_at: Option<DateTime<Utc>>,
+}
+
+/// The apps ControlCenter most recently listed as using one sensor.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct AttributionList {
+    pub listed_at: DateTime<Utc>,
+    pub apps: Vec<Attribution>,
+}
+
+/// ControlCenter's latest attribution lists, for checking that running
+/// sessions are still attributed.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct AttributionSnapshot {
+    pub camera: Option<AttributionList>,
+    pub microphone: Option<AttributionList>,
+}
+
+impl AttributionSnapshot {
+    /// Whether a list for `sensor` published at or after `since` leaves out
+    /// the app. Apps match by bundle ID when both sides have one, and by
+    /// case-insensitive name otherwise.
+    pub fn omits(
+        &self,
+        app_name: &str,
+        bundle_id: Option<&str>,
+        sensor: Sensor,
+        since: DateTime<Utc>,
+    ) -> bool {
+        self.list_since(sensor, since)
+            .is_some_and(|list| !list.includes(app_name, bundle_id))
+    }
+
+    /// Whether a list for `sensor` published at or after `since` includes
+    /// the app, matched as in `omits`.
+    pub fn lists(
+        &self,
+        app_name: &str,
+        bundle_id: Option<&str>,
+        sensor: Sensor,
+        since: DateTime<Utc>,
+    ) -> bool {
+        self.list_since(sensor, since)
+            .is_some_and(|list| list.includes(app_name, bundle_id))
+    }
+
+    fn list_since(&self, sensor: Sensor, since: DateTime<Utc>) -> Option<&AttributionList> {
+        let list = match sensor {
+            Sensor::Camera => &self.camera,
+            Sensor::Microphone => &self.microphone,
+        };
+        list.as_ref().filter(|list| list.listed_at >= since)
+    }
+}
+
+impl AttributionList {
+    fn includes(&self, app_name: &str, bundle_id: Option<&str>) -> bool {
+        self.apps
+            .iter()
+            .any(|app| match (bundle_id, &app.bundle_id) {
+                (Some(wanted), Some(listed)) => wanted == listed,
+                _ => app.app_name.eq_ignore_ascii_case(app_name),
+            })
+    }
+}
+
+/// An app a capture subsystem reported starting the camera, used to name
+/// the app behind a SkyLight camera status.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CaptureStart {
+    pub app_name: String,
+    pub bundle_id: Option<String>,
+    pub pid: Option<u32>,
+    pub timestamp: DateTime<Utc>,
+}
+
+impl CaptureStart {
+    fn unattributed(timestamp: DateTime<Utc>) -> Self {
+        Self {
+            app_name: UNATTRIBUTED_APP.to_string(),
+            bundle_id: None,
+            pid: None,
+            timestamp,
+        }
+    }
+}
+
+impl ParserState {
+    /// ControlCenter's latest attribution lists, if it has published any.
+    pub fn attributions(&self) -> Option<AttributionSnapshot> {
+        let list = |listed_at: Option<DateTime<Utc>>, apps: &HashMap<String, Attribution>| {
+            listed_at.map(|listed_at| AttributionList {
+                listed_at,
+                apps: apps.values().cloned().collect(),
+            })
+        };
+        let snapshot = AttributionSnapshot {
+            camera: list(self.camera_listed_at, &self.active_apps),
+            microphone: list(self.microphone_listed_at, &self.active_mic_apps),
+        };
+        (snapshot != AttributionSnapshot::default()).then_some(snapshot)
+    }
+
+    /// Tracks which apps capture subsystems report using the camera.
+    fn note_capture_events(&mut self, events: &[CameraEvent]) {
+        for event in events {
+            match event {
+                CameraEvent::Started {
+                    app_name,
+                    bundle_id,
+                    pid,
+                    sensor: Sensor::Camera,
+                    timestamp,
+                } => {
+                    if self.capture_starts.len() == MAX_CAPTURE_STARTS {
+                        // Stops can go missing; forget the oldest start
+                        self.capture_starts.remove(0);
+                    }
+                    self.capture_starts.push(CaptureStart {
+                        app_name: app_name.clone(),
+                        bundle_id: bundle_id.clone(),
+                        pid: *pid,
+                        timestamp: *timestamp,
+                    });
+                }
+                CameraEvent::Stopped {
+                    app_name,
+                    pid,
+                    sensor: Sensor::Camera,
+                    ..
+                } => {
+                    if let Some(i) = self
+                        .capture_starts
+                        .iter()
+                        .rposition(|s| s.app_name == *app_name && s.pid == *pid)
+                    {
+                        self.capture_starts.remove(i);
+                    }
+                }
+                _ => {}
+            }
+        }
+    }
+
+    /// The app that most recently started capturing within
+    /// `SKYLIGHT_ATTRIBUTION_WINDOW` before `timestamp`.
+    fn attribute_camera_open(&self, timestamp: DateTime<Utc>) -> Option<CaptureStart> {
+        self.capture_starts
+            .iter()
+            .rev()
+            .find(|s| {
+                s.timestamp <= timestamp && timestamp - s.timestamp <= SKYLIGHT_ATTRIBUTION_WINDOW
+            })
+            .cloned()
+    }
 }
 
 // ---------------------------------------------------------------------------
 // ControlCenter parser (macOS Sonoma+)
 // ---------------------------------------------------------------------------
 
-/// Parses `activeCameraAttributions` lines.
+/// Marker preceding each app in an `activeCameraAttributions` list.
+const CAMERA_MARKER: &str = "[cam] ";
+/// Marker preceding each app in an `activeMicrophoneAttributions` list.
+const MICROPHONE_MARKER: &str = "[mic] ";
+
+/// Parses `activeCameraAttributions` and `activeMicrophoneAttributions` lines.
 ///
-/// Example log line:
+/// Example log lines:
 ///   `... StatusBarServer[PID]: ... activeCameraAttributions: [cam] FaceTime (com.apple.FaceTime) ...`
+///   `... StatusBarServer[PID]: ... activeMicrophoneAttributions: [mic] FaceTime (com.apple.FaceTime) ...`
 ///
-/// Each line lists all apps currently using the camera. We diff against the
-/// previous set to emit Started/Stopped events.
-fn parse_controlcenter(line: &str, state: &mut ParserState) -> Vec<CameraEvent> {
-    if !line.contains("activeCameraAttributions") {
+/// Each line lists all apps currently using that sensor. We diff against the
+/// previous set for the same sensor to emit Started/Stopped events.
+fn parse_controlcenter(record: &LogRecord, state: &mut ParserState) -> Vec<CameraEvent> {
+    let line = record.message;
+    let has_camera = line.contains("activeCameraAttributions");
+    let has_microphone = line.contains("activeMicrophoneAttributions");
+    if !has_camera && !has_microphone {
         return vec![];
     }
 
-    let now = Utc::now();
+    let timestamp = record.timestamp;
     let mut events = Vec::new();
 
-    // Extract app names from "[cam] AppName (bundle.id)" patterns
-    let current_apps: HashSet<String> = extract_cam_apps(line);
+    if has_camera {
+        // Extract app names from "[cam] AppName (bundle.id)" patterns
+        let current_apps = extract_attributed_apps(line, CAMERA_MARKER);
+        diff_attributions(
+            &mut state.active_apps,
+            current_apps,
+            Sensor::Camera,
+            timestamp,
+            &mut events,
+        );
+        state.camera_listed_at = Some(timestamp);
+    }
+
+    if has_microphone {
+        // Extract app names from "[mic] AppName (bundle.id)" patterns
+        let current_apps = extract_attributed_apps(line, MICROPHONE_MARKER);
+        diff_attributions(
+            &mut state.active_mic_apps,
+            current_apps,
+            Sensor::Microphone,
+            timestamp,
+            &mut events,
+        );
+        state.microphone_listed_at = Some(timestamp);
+    }
+
+    events
+}
 
+/// Emits Started/Stopped events for the difference between the previously
+/// attributed apps and `current_apps`, then replaces the previous set.
+///
+/// Apps are compared by identity, so an app whose display name changes while
+/// it keeps the camera does not produce a Stopped/Started pair.
+fn diff_attributions(
+    previous_apps: &mut HashMap<String, Attribution>,
+    current_apps: HashMap<String, Attribution>,
+    sensor: Sensor,
+    timestamp: DateTime<Utc>,
+    events: &mut Vec<CameraEvent>,
+) {
     // Newly started apps = in current but not in previous
-    for app in current_apps.difference(&state.active_apps) {
-        events.push(CameraEvent::Started {
-            app_name: app.clone(),
-            timestamp: now,
-        });
+    for (identity, app) in &current_apps {
+        if !previous_apps.contains_key(identity) {
+            events.push(CameraEvent::Started {
+                app_name: app.app_name.clone(),
+                bundle_id: app.bundle_id.clone(),
+                // The PID on the line belongs to ControlCenter, not the app
+                pid: None,
+                sensor,
+                timestamp,
+            });
+        }
     }
 
     // Newly stopped apps = in previous but not in current
-    for app in state.active_apps.difference(&current_apps) {
-        events.push(CameraEvent::Stopped {
-            app_name: app.clone(),
-            timestamp: now,
-        });
+    for (identity, app) in previous_apps.iter() {
+        if !current_apps.contains_key(identity) {
+            events.push(CameraEvent::Stopped {
+                app_name: app.app_name.clone(),
+                bundle_id: app.bundle_id.clone(),
+                // The PID on the line belongs to ControlCenter, not the app
+                pid: None,
+                sensor,
+                timestamp,
+            });
+        }
     }
 
     // Update state
-    state.active_apps = current_apps;
-
-    events
+    *previous_apps = current_apps;
 }
 
-/// Extracts app names from `[cam] AppName (bundle.id)` patterns in a log line.
-fn extract_cam_apps(line: &str) -> HashSet<String> {
-    let mut apps = HashSet::new();
-    let marker = "[cam] ";
+/// Extracts apps from `<marker>AppName (bundle.id)` patterns in a log line,
+/// e.g. `[cam] FaceTime (com.apple.FaceTime)`, keyed by identity.
+fn extract_attributed_apps(line: &str, marker: &str) -> HashMap<String, Attribution> {
+    let mut apps = HashMap::new();
+    let mut insert = |app: Attribution| {
+        apps.insert(app.identity().to_string(), app);
+    };
 
     let mut search_from = 0;
     while let Some(start) = line[search_from..].find(marker) {
@@ -209,15 +1235,27 @@ fn extract_cam_apps(line: &str) -> HashSet<String> {
         // The app name ends at the next " (" which precedes the bundle ID
         if let Some(paren_offset) = line[name_start..].find(" (") {
             let name = line[name_start..name_start + paren_offset].trim();
+            let bundle_start = name_start + paren_offset + 2;
+            let bundle_id = line[bundle_start..]
+                .find(')')
+                .map(|end| line[bundle_start..bundle_start + end].trim())
+                .filter(|id| !id.is_empty())
+                .map(|id| id.to_string());
             if !name.is_empty() {
-                apps.insert(name.to_string());
+                insert(Attribution {
+                    app_name: name.to_string(),
+                    bundle_id,
+                });
             }
             search_from = name_start + paren_offset + 1;
         } else {
             // No parenthesized bundle ID — take the rest of the token
             let name = line[name_start..].split_whitespace().next().unwrap_or("");
             if !name.is_empty() {
-                apps.insert(name.to_string());
+                insert(Attribution {
+                    app_name: name.to_string(),
+                    bundle_id: None,
+                });
             }
             break;
         }
@@ -230,31 +1268,59 @@ fn extract_cam_apps(line: &str) -> HashSet<String> {
 // SkyLight parser
 // ---------------------------------------------------------------------------
 
+/// Name given to SkyLight sessions no capture subsystem could attribute.
+pub const UNATTRIBUTED_APP: &str = "Unattributed";
+/// How long before a SkyLight camera-open a capture start may be logged for
+/// the start to name the app. Attribution is settled when the status line is
+/// parsed, so starts logged after it never name it.
+const SKYLIGHT_ATTRIBUTION_WINDOW: chrono::Duration = chrono::Duration::seconds(5);
+/// Upper bound on the capture starts kept for attribution.
+const MAX_CAPTURE_STARTS: usize = 16;
+
 /// Parses `camera status 0` / `camera status 1` lines.
 /// Stateful: only emits an event on transitions (open→closed, closed→open).
-fn parse_skylight(line: &str, state: &mut ParserState) -> Vec<CameraEvent> {
+///
+/// These lines are logged by WindowServer, so the app is taken from a
+/// capture start logged shortly before instead, or reported as
+/// `UNATTRIBUTED_APP`. The stop reuses whatever the start was attributed to.
+/// AVCaptureSession lines in the same stream only feed that attribution.
+fn parse_skylight(record: &LogRecord, state: &mut ParserState) -> Vec<CameraEvent> {
+    let line = record.message;
     if !line.contains("camera status") {
+        state.note_capture_events(&parse_cameracapture(record));
         return vec![];
     }
 
-    let now = Utc::now();
-
     if line.contains("camera status 0") {
         // Camera closed
         if state.skylight_state != Some(false) {
             state.skylight_state = Some(false);
+            let app = state
+                .skylight_app
+                .take()
+                .unwrap_or_else(|| CaptureStart::unattributed(record.timestamp));
             return vec![CameraEvent::Stopped {
-                app_name: extract_app_name(line),
-                timestamp: now,
+                app_name: app.app_name,
+                bundle_id: app.bundle_id,
+                pid: app.pid,
+                sensor: Sensor::Camera,
+                timestamp: record.timestamp,
             }];
         }
     } else if line.contains("camera status 1") {
         // Camera opened
         if state.skylight_state != Some(true) {
             state.skylight_state = Some(true);
+            let app = state
+                .attribute_camera_open(record.timestamp)
+                .unwrap_or_else(|| CaptureStart::unattributed(record.timestamp));
+            state.skylight_app = Some(app.clone());
             return vec![CameraEvent::Started {
-                app_name: extract_app_name(line),
-                timestamp: now,
+                app_name: app.app_name,
+                bundle_id: app.bundle_id,
+                pid: app.pid,
+                sensor: Sensor::Camera,
+                timestamp: record.timestamp,
             }];
         }
     }
@@ -266,8 +1332,18 @@ fn parse_skylight(line: &str, state: &mut ParserState) -> Vec<CameraEvent> {
 // CameraCapture parser (original implementation)
 // ---------------------------------------------------------------------------
 
+/// The cameracapture category AVCaptureSession logs its start/stop under.
+const CAPTURE_CATEGORY: &str = "capture";
+
 /// Parses `startRunning]` / `stopRunning]` from AVCaptureSession.
-fn parse_cameracapture(line: &str) -> Vec<CameraEvent> {
+///
+/// Entries from other cameracapture categories are skipped when the
+/// category is known (ndjson); syslog lines are parsed as before.
+fn parse_cameracapture(record: &LogRecord) -> Vec<CameraEvent> {
+    if record.category.is_some_and(|c| c != CAPTURE_CATEGORY) {
+        return vec![];
+    }
+    let line = record.message;
     if line.contains("<private>") {
         return vec![];
     }
@@ -279,18 +1355,25 @@ fn parse_cameracapture(line: &str) -> Vec<CameraEvent> {
         return vec![];
     }
 
-    let app_name = extract_app_name(line);
-    let now = Utc::now();
+    let app_name = record.process.clone();
+    let pid = record.pid;
+    let timestamp = record.timestamp;
 
     if is_start {
         vec![CameraEvent::Started {
             app_name,
-            timestamp: now,
+            bundle_id: None,
+            pid,
+            sensor: Sensor::Camera,
+            timestamp,
         }]
     } else {
         vec![CameraEvent::Stopped {
             app_name,
-            timestamp: now,
+            bundle_id: None,
+            pid,
+            sensor: Sensor::Camera,
+            timestamp,
         }]
     }
 }
@@ -301,7 +1384,8 @@ fn parse_cameracapture(line: &str) -> Vec<CameraEvent> {
 
 /// Parses CoreMediaIO lines — `CMIODeviceStartStream` / `CMIODeviceStopStream`
 /// or `startRunning` / `stopRunning`.
-fn parse_cmio(line: &str) -> Vec<CameraEvent> {
+fn parse_cmio(record: &LogRecord) -> Vec<CameraEvent> {
+    let line = record.message;
     let is_start = line.contains("startRunning") || line.contains("CMIODeviceStartStream");
     let is_stop = line.contains("stopRunning") || line.contains("CMIODeviceStopStream");
 
@@ -309,18 +1393,25 @@ fn parse_cmio(line: &str) -> Vec<CameraEvent> {
         return vec![];
     }
 
-    let app_name = extract_app_name(line);
-    let now = Utc::now();
+    let app_name = record.process.clone();
+    let pid = record.pid;
+    let timestamp = record.timestamp;
 
     if is_stop {
         vec![CameraEvent::Stopped {
             app_name,
-            timestamp: now,
+            bundle_id: None,
+            pid,
+            sensor: Sensor::Camera,
+            timestamp,
         }]
     } else {
         vec![CameraEvent::Started {
             app_name,
-            timestamp: now,
+            bundle_id: None,
+            pid,
+            sensor: Sensor::Camera,
+            timestamp,
         }]
     }
 }
@@ -329,22 +1420,51 @@ fn parse_cmio(line: &str) -> Vec<CameraEvent> {
 // Shared helpers
 // ---------------------------------------------------------------------------
 
-/// Extracts the application name from a syslog-format log line.
+/// Parses the leading `YYYY-MM-DD HH:MM:SS[.ffffff][±HHMM]` timestamp of a
+/// syslog-format log line.
+///
+/// Lines without a timezone offset are interpreted in the local timezone,
+/// which is what `log stream` prints in that case.
+pub fn parse_line_timestamp(line: &str) -> Option<DateTime<Utc>> {
+    let mut tokens = line.split_whitespace();
+    let date = tokens.next()?;
+    let time = tokens.next()?;
+    let stamp = format!("{} {}", date, time);
+
+    if let Ok(ts) = DateTime::parse_from_str(&stamp, "%Y-%m-%d %H:%M:%S%.f%z") {
+        return Some(ts.with_timezone(&Utc));
+    }
+
+    let naive = NaiveDateTime::parse_from_str(&stamp, "%Y-%m-%d %H:%M:%S%.f").ok()?;
+    Local
+        .from_local_datetime(&naive)
+        .earliest()
+        .map(|ts| ts.with_timezone(&Utc))
+}
+
+/// The time an event on `line` happened: the line's own timestamp, or the
+/// current time if the line does not carry one.
+fn event_timestamp(line: &str) -> DateTime<Utc> {
+    parse_line_timestamp(line).unwrap_or_else(Utc::now)
+}
+
+/// Extracts the application name and PID from a syslog-format log line.
 ///
-/// Looks for the first token matching `Name[digits]:` and strips the
-/// `[PID]:` suffix to get the process/app name.
-fn extract_app_name(line: &str) -> String {
+/// Looks for the first token matching `Name[digits]:` and splits it into the
+/// process/app name and the PID between the brackets.
+fn extract_process(line: &str) -> (String, Option<u32>) {
     for token in line.split_whitespace() {
         if let Some(bracket_pos) = token.find('[') {
             if token.ends_with("]:") {
                 let name = &token[..bracket_pos];
                 if !name.is_empty() {
-                    return name.to_string();
+                    let pid = token[bracket_pos + 1..token.len() - 2].parse().ok();
+                    return (name.to_string(), pid);
                 }
             }
         }
     }
-    "Unknown".to_string()
+    ("Unknown".to_string(), None)
 }
 
 // ---------------------------------------------------------------------------
@@ -354,6 +1474,19 @@ fn extract_app_name(line: &str) -> String {
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::camera::replay::apply_event;
+    use crate::session::SessionManager;
+
+    /// Records `app_name (bundle_id)` as already active in a ControlCenter set.
+    fn remember(apps: &mut HashMap<String, Attribution>, app_name: &str, bundle_id: &str) {
+        apps.insert(
+            bundle_id.to_string(),
+            Attribution {
+                app_name: app_name.to_string(),
+                bundle_id: Some(bundle_id.to_string()),
+            },
+        );
+    }
 
     // --- Detection / Predicate tests ---
 
@@ -439,6 +1572,17 @@ mod tests {
         }
     }
 
+    #[test]
+    fn test_cameracapture_captures_pid() {
+        let line = "2025-06-10 14:23:01.123456-0700  0x1a2b  Default  0x0  456  0  zoom.us[5678]: (com.apple.cameracapture) [AVCaptureSession startRunning]: called";
+        let mut state = ParserState::default();
+        let events = parse_log_line(line, &Subsystem::CameraCapture, &mut state);
+        match &events[0] {
+            CameraEvent::Started { pid, .. } => assert_eq!(*pid, Some(5678)),
+            _ => panic!("Expected Started event"),
+        }
+    }
+
     // --- ControlCenter parser tests ---
 
     #[test]
@@ -456,7 +1600,7 @@ mod tests {
     #[test]
     fn test_controlcenter_app_stop() {
         let mut state = ParserState::default();
-        state.active_apps.insert("FaceTime".to_string());
+        remember(&mut state.active_apps, "FaceTime", "com.apple.FaceTime");
 
         // Empty attributions line = camera turned off
         let line = "2025-06-10 14:25:01 StatusBarServer[123]: activeCameraAttributions: (none)";
@@ -471,8 +1615,8 @@ mod tests {
     #[test]
     fn test_controlcenter_two_apps_one_stops() {
         let mut state = ParserState::default();
-        state.active_apps.insert("FaceTime".to_string());
-        state.active_apps.insert("zoom.us".to_string());
+        remember(&mut state.active_apps, "FaceTime", "com.apple.FaceTime");
+        remember(&mut state.active_apps, "zoom.us", "us.zoom.xos");
 
         // Only FaceTime remains
         let line = "2025-06-10 14:25:01 StatusBarServer[123]: activeCameraAttributions: [cam] FaceTime (com.apple.FaceTime)";
@@ -487,13 +1631,30 @@ mod tests {
     #[test]
     fn test_controlcenter_no_change_no_events() {
         let mut state = ParserState::default();
-        state.active_apps.insert("FaceTime".to_string());
+        remember(&mut state.active_apps, "FaceTime", "com.apple.FaceTime");
 
         let line = "2025-06-10 14:25:01 StatusBarServer[123]: activeCameraAttributions: [cam] FaceTime (com.apple.FaceTime)";
         let events = parse_log_line(line, &Subsystem::ControlCenter, &mut state);
         assert!(events.is_empty());
     }
 
+    #[test]
+    fn test_controlcenter_attributions_published_per_sensor() {
+        let mut state = ParserState::default();
+        assert_eq!(state.attributions(), None);
+
+        let line = "2025-06-10 14:23:01+0000 StatusBarServer[123]: activeCameraAttributions: [cam] FaceTime (com.apple.FaceTime)";
+        parse_log_line(line, &Subsystem::ControlCenter, &mut state);
+        let snapshot = state.attributions().unwrap();
+        assert!(snapshot.microphone.is_none());
+
+        let listed_at = snapshot.camera.as_ref().unwrap().listed_at;
+        assert!(!snapshot.omits("facetime", None, Sensor::Camera, listed_at));
+        assert!(snapshot.omits("zoom.us", None, Sensor::Camera, listed_at));
+        // Nothing is known yet about the microphone
+        assert!(!snapshot.omits("zoom.us", None, Sensor::Microphone, listed_at));
+    }
+
     // --- SkyLight parser tests ---
 
     #[test]
@@ -534,6 +1695,55 @@ mod tests {
         assert!(events.is_empty());
     }
 
+    #[test]
+    fn test_skylight_attributed_to_capturing_app() {
+        let mut state = ParserState::default();
+        let capture = r#"{"eventMessage":"[AVCaptureSession startRunning]: called","subsystem":"com.apple.cameracapture","processImagePath":"/Applications/zoom.us.app/Contents/MacOS/zoom.us","processID":5678,"timestamp":"2025-06-10 14:23:00.500000+0000"}"#;
+        assert!(parse_log_line(capture, &Subsystem::SkyLight, &mut state).is_empty());
+
+        let opened = r#"{"eventMessage":"camera status 1","subsystem":"com.apple.SkyLight","processImagePath":"/System/Library/PrivateFrameworks/SkyLight.framework/Resources/WindowServer","processID":99,"timestamp":"2025-06-10 14:23:01.000000+0000"}"#;
+        let events = parse_log_line(opened, &Subsystem::SkyLight, &mut state);
+        match &events[..] {
+            [CameraEvent::Started { app_name, pid, .. }] => {
+                assert_eq!(app_name, "zoom.us");
+                assert_eq!(*pid, Some(5678));
+            }
+            _ => panic!("Expected Started event"),
+        }
+
+        // The stop is attributed like the start, even after zoom's own stop
+        let capture_stop =
+            "2025-06-10 14:30:00.000000+0000 zoom.us[5678]: [AVCaptureSession stopRunning]: called";
+        parse_log_line(capture_stop, &Subsystem::SkyLight, &mut state);
+        let closed = "2025-06-10 14:30:01.000000+0000 WindowServer[99]: camera status 0";
+        let events = parse_log_line(closed, &Subsystem::SkyLight, &mut state);
+        match &events[..] {
+            [CameraEvent::Stopped { app_name, .. }] => assert_eq!(app_name, "zoom.us"),
+            _ => panic!("Expected Stopped event"),
+        }
+    }
+
+    #[test]
+    fn test_skylight_without_capture_start_is_unattributed() {
+        let mut state = ParserState::default();
+        // A capture start long before the camera opened does not count, nor
+        // does one stamped after it
+        let capture = "2025-06-10 14:00:00.000000+0000 FaceTime[1234]: [AVCaptureSession startRunning]: called";
+        parse_log_line(capture, &Subsystem::SkyLight, &mut state);
+        let capture = "2025-06-10 14:23:03.000000+0000 zoom.us[5678]: [AVCaptureSession startRunning]: called";
+        parse_log_line(capture, &Subsystem::SkyLight, &mut state);
+
+        let opened = "2025-06-10 14:23:01.000000+0000 WindowServer[99]: camera status 1";
+        let events = parse_log_line(opened, &Subsystem::SkyLight, &mut state);
+        match &events[..] {
+            [CameraEvent::Started { app_name, pid, .. }] => {
+                assert_eq!(app_name, UNATTRIBUTED_APP);
+                assert_eq!(*pid, None);
+            }
+            _ => panic!("Expected Started event"),
+        }
+    }
+
     // --- CMIO parser tests ---
 
     #[test]
@@ -572,21 +1782,426 @@ mod tests {
         }
     }
 
-    // --- extract_cam_apps helper tests ---
+    #[test]
+    fn test_controlcenter_microphone_start() {
+        let line = "2025-06-10 14:23:01 StatusBarServer[123]: activeMicrophoneAttributions: [mic] zoom.us (us.zoom.xos)";
+        let mut state = ParserState::default();
+        let events = parse_log_line(line, &Subsystem::ControlCenter, &mut state);
+        assert_eq!(events.len(), 1);
+        match &events[0] {
+            CameraEvent::Started {
+                app_name, sensor, ..
+            } => {
+                assert_eq!(app_name, "zoom.us");
+                assert_eq!(*sensor, Sensor::Microphone);
+            }
+            _ => panic!("Expected Started event"),
+        }
+        assert!(state.active_apps.is_empty());
+    }
+
+    #[test]
+    fn test_controlcenter_microphone_line_leaves_camera_apps_alone() {
+        let mut state = ParserState::default();
+        remember(&mut state.active_apps, "FaceTime", "com.apple.FaceTime");
+        remember(&mut state.active_mic_apps, "FaceTime", "com.apple.FaceTime");
+
+        // Mic turned off, camera untouched
+        let line = "2025-06-10 14:25:01 StatusBarServer[123]: activeMicrophoneAttributions: (none)";
+        let events = parse_log_line(line, &Subsystem::ControlCenter, &mut state);
+        assert_eq!(events.len(), 1);
+        match &events[0] {
+            CameraEvent::Stopped {
+                app_name, sensor, ..
+            } => {
+                assert_eq!(app_name, "FaceTime");
+                assert_eq!(*sensor, Sensor::Microphone);
+            }
+            _ => panic!("Expected Stopped event"),
+        }
+        assert!(state.active_apps.contains_key("com.apple.FaceTime"));
+    }
+
+    // --- extract_attributed_apps helper tests ---
 
     #[test]
     fn test_extract_cam_apps_multiple() {
         let line = "activeCameraAttributions: [cam] FaceTime (com.apple.FaceTime), [cam] zoom.us (us.zoom.xos)";
-        let apps = extract_cam_apps(line);
-        assert!(apps.contains("FaceTime"));
-        assert!(apps.contains("zoom.us"));
+        let apps = extract_attributed_apps(line, CAMERA_MARKER);
+        assert_eq!(apps["com.apple.FaceTime"].app_name, "FaceTime");
+        assert_eq!(apps["us.zoom.xos"].app_name, "zoom.us");
         assert_eq!(apps.len(), 2);
     }
 
     #[test]
     fn test_extract_cam_apps_empty() {
         let line = "activeCameraAttributions: (none)";
-        let apps = extract_cam_apps(line);
+        let apps = extract_attributed_apps(line, CAMERA_MARKER);
         assert!(apps.is_empty());
     }
+
+    #[test]
+    fn test_extract_cam_apps_without_bundle_id() {
+        let line = "activeCameraAttributions: [cam] SomeTool";
+        let apps = extract_attributed_apps(line, CAMERA_MARKER);
+        assert_eq!(apps["SomeTool"].bundle_id, None);
+    }
+
+    #[test]
+    fn test_controlcenter_start_carries_bundle_id() {
+        let line = "2025-06-10 14:23:01 StatusBarServer[123]: activeCameraAttributions: [cam] zoom.us (us.zoom.xos)";
+        let mut state = ParserState::default();
+        let events = parse_log_line(line, &Subsystem::ControlCenter, &mut state);
+        match &events[0] {
+            CameraEvent::Started { bundle_id, pid, .. } => {
+                assert_eq!(bundle_id.as_deref(), Some("us.zoom.xos"));
+                assert_eq!(*pid, None);
+            }
+            _ => panic!("Expected Started event"),
+        }
+    }
+
+    #[test]
+    fn test_controlcenter_renamed_app_keeps_session() {
+        let mut state = ParserState::default();
+        remember(&mut state.active_apps, "zoom.us", "us.zoom.xos");
+
+        // Same bundle, localized display name
+        let line = "2025-06-10 14:25:01 StatusBarServer[123]: activeCameraAttributions: [cam] Zoom (us.zoom.xos)";
+        let events = parse_log_line(line, &Subsystem::ControlCenter, &mut state);
+        assert!(events.is_empty());
+    }
+
+    #[test]
+    fn test_extract_mic_apps_ignores_cam_entries() {
+        let line = "[cam] FaceTime (com.apple.FaceTime), [mic] zoom.us (us.zoom.xos)";
+        let apps = extract_attributed_apps(line, MICROPHONE_MARKER);
+        assert_eq!(apps.len(), 1);
+        assert!(apps.contains_key("us.zoom.xos"));
+    }
+
+    // --- Timestamp parsing tests ---
+
+    #[test]
+    fn test_parse_line_timestamp_with_offset() {
+        let line = "2025-06-10 14:23:01.123456-0700  0x1a2b  Default  0x0  123  0  FaceTime[1234]: (com.apple.cameracapture) [AVCaptureSession startRunning]: called";
+        let ts = parse_line_timestamp(line).unwrap();
+        assert_eq!(ts.to_rfc3339(), "2025-06-10T21:23:01.123456+00:00");
+    }
+
+    #[test]
+    fn test_parse_line_timestamp_rejects_non_timestamp() {
+        assert!(parse_line_timestamp("Filtering the log data using ...").is_none());
+        assert!(parse_line_timestamp("").is_none());
+    }
+
+    #[test]
+    fn test_subsystem_round_trips_through_display() {
+        for sub in [
+            Subsystem::ControlCenter,
+            Subsystem::SkyLight,
+            Subsystem::CameraCapture,
+            Subsystem::Cmio,
+        ] {
+            assert_eq!(sub.to_string().parse::<Subsystem>().unwrap(), sub);
+        }
+    }
+
+    #[test]
+    fn test_events_carry_line_timestamp() {
+        let line = "2025-06-10 14:25:01.654321-0700  0x1a2b  Default  0x0  123  0  FaceTime[1234]: (com.apple.cameracapture) [AVCaptureSession stopRunning]: called";
+        let mut state = ParserState::default();
+        let events = parse_log_line(line, &Subsystem::CameraCapture, &mut state);
+        match &events[0] {
+            CameraEvent::Stopped { timestamp, .. } => {
+                assert_eq!(timestamp.to_rfc3339(), "2025-06-10T21:25:01.654321+00:00")
+            }
+            _ => panic!("Expected Stopped event"),
+        }
+    }
+
+    #[test]
+    fn test_controlcenter_events_carry_line_timestamp() {
+        let line = "2025-06-10 14:23:01.000000+0200 StatusBarServer[123]: activeCameraAttributions: [cam] FaceTime (com.apple.FaceTime)";
+        let mut state = ParserState::default();
+        let events = parse_log_line(line, &Subsystem::ControlCenter, &mut state);
+        match &events[0] {
+            CameraEvent::Started { timestamp, .. } => {
+                assert_eq!(timestamp.to_rfc3339(), "2025-06-10T12:23:01+00:00")
+            }
+            _ => panic!("Expected Started event"),
+        }
+    }
+
+    // --- ndjson entry tests ---
+
+    #[test]
+    fn test_ndjson_cameracapture_start() {
+        let line = r#"{"eventMessage":"[AVCaptureSession startRunning]: called","subsystem":"com.apple.cameracapture","category":"capture","processImagePath":"/Applications/zoom.us.app/Contents/MacOS/zoom.us","processID":5678,"timestamp":"2025-06-10 14:23:01.123456-0700","backtrace":{"frames":[]}}"#;
+        let entry: LogEntry = serde_json::from_str(line).unwrap();
+        assert_eq!(entry.category.as_deref(), Some("capture"));
+
+        let mut state = ParserState::default();
+        let events = parse_log_line(line, &Subsystem::CameraCapture, &mut state);
+        assert_eq!(events.len(), 1);
+        match &events[0] {
+            CameraEvent::Started {
+                app_name,
+                pid,
+                timestamp,
+                ..
+            } => {
+                assert_eq!(app_name, "zoom.us");
+                assert_eq!(*pid, Some(5678));
+                assert_eq!(timestamp.to_rfc3339(), "2025-06-10T21:23:01.123456+00:00");
+            }
+            _ => panic!("Expected Started event"),
+        }
+    }
+
+    #[test]
+    fn test_ndjson_cameracapture_other_category_ignored() {
+        let line = r#"{"eventMessage":"[AVCaptureSession startRunning]: called","subsystem":"com.apple.cameracapture","category":"device","processImagePath":"/Applications/zoom.us.app/Contents/MacOS/zoom.us","processID":5678}"#;
+        let mut state = ParserState::default();
+        let events = parse_log_line(line, &Subsystem::CameraCapture, &mut state);
+        assert!(events.is_empty());
+    }
+
+    #[test]
+    fn test_ndjson_controlcenter_attributions() {
+        let line = r#"{"eventMessage":"activeCameraAttributions: [cam] FaceTime (com.apple.FaceTime)","subsystem":"com.apple.controlcenter","processImagePath":"/System/Library/CoreServices/ControlCenter.app/Contents/MacOS/ControlCenter","processID":321,"timestamp":"2025-06-10 14:23:01.000000+0200"}"#;
+        let mut state = ParserState::default();
+        let events = parse_log_line(line, &Subsystem::ControlCenter, &mut state);
+        assert_eq!(events.len(), 1);
+        match &events[0] {
+            CameraEvent::Started {
+                app_name,
+                bundle_id,
+                pid,
+                ..
+            } => {
+                assert_eq!(app_name, "FaceTime");
+                assert_eq!(bundle_id.as_deref(), Some("com.apple.FaceTime"));
+                assert_eq!(*pid, None);
+            }
+            _ => panic!("Expected Started event"),
+        }
+    }
+
+    #[test]
+    fn test_ndjson_other_subsystem_ignored() {
+        let line = r#"{"eventMessage":"camera status 1","subsystem":"com.apple.cmio","processImagePath":"/usr/sbin/WindowServer","processID":99}"#;
+        let mut state = ParserState::default();
+        let events = parse_log_line(line, &Subsystem::SkyLight, &mut state);
+        assert!(events.is_empty());
+        assert_eq!(state.skylight_state, None);
+    }
+
+    #[test]
+    fn test_malformed_json_falls_back_to_syslog() {
+        let line = "{ truncated FaceTime[1234]: [AVCaptureSession startRunning]: called";
+        let mut state = ParserState::default();
+        let events = parse_log_line(line, &Subsystem::CameraCapture, &mut state);
+        assert_eq!(events.len(), 1);
+        match &events[0] {
+            CameraEvent::Started { app_name, pid, .. } => {
+                assert_eq!(app_name, "FaceTime");
+                assert_eq!(*pid, Some(1234));
+            }
+            _ => panic!("Expected Started event"),
+        }
+    }
+
+    // --- Supervisor helper tests ---
+
+    #[test]
+    fn test_restart_delay_doubles_up_to_cap() {
+        let delays: Vec<u64> = (1..=8).map(|n| restart_delay(n).as_secs()).collect();
+        assert_eq!(delays, vec![1, 2, 4, 8, 16, 32, 60, 60]);
+        assert_eq!(restart_delay(u32::MAX), RESTART_MAX_DELAY);
+    }
+
+    #[test]
+    fn test_stderr_tail_keeps_last_lines() {
+        let stderr = "first\n\nsecond\nthird\n";
+        assert_eq!(stderr_tail(stderr.as_bytes(), 2), "second; third");
+        assert_eq!(stderr_tail("".as_bytes(), 2), "");
+    }
+
+    #[test]
+    fn test_exit_reason_includes_stderr() {
+        assert_eq!(exit_reason(None, ""), "log stream exited");
+        assert_eq!(
+            exit_reason(None, "log: Cannot run while sandboxed"),
+            "log stream exited: log: Cannot run while sandboxed"
+        );
+    }
+
+    // --- Multi-subsystem tests ---
+
+    #[test]
+    fn test_subsystem_selection_parsing() {
+        assert_eq!(
+            "auto".parse::<SubsystemSelection>().unwrap(),
+            SubsystemSelection::Detect
+        );
+        assert_eq!(
+            "all".parse::<SubsystemSelection>().unwrap(),
+            SubsystemSelection::Fixed(Subsystem::ALL.to_vec())
+        );
+        assert_eq!(
+            "cmio, cameracapture,cmio"
+                .parse::<SubsystemSelection>()
+                .unwrap(),
+            SubsystemSelection::Fixed(vec![Subsystem::Cmio, Subsystem::CameraCapture])
+        );
+        assert!("cmio,bogus".parse::<SubsystemSelection>().is_err());
+    }
+
+    #[test]
+    fn test_combined_predicate_ors_each_subsystem() {
+        let predicate = combined_predicate(&[Subsystem::CameraCapture, Subsystem::Cmio]);
+        assert!(predicate.contains(predicate_for(&Subsystem::CameraCapture)));
+        assert!(predicate.contains(") OR ("));
+        assert!(predicate.contains(predicate_for(&Subsystem::Cmio)));
+    }
+
+    #[test]
+    fn test_tagged_line_uses_entry_subsystem() {
+        let line = r#"{"eventMessage":"CMIODeviceStartStream","subsystem":"com.apple.cmio","processImagePath":"/Applications/zoom.us.app/Contents/MacOS/zoom.us","processID":5678}"#;
+        let mut state = ParserState::default();
+        let subsystems = [Subsystem::CameraCapture, Subsystem::Cmio];
+        let events = parse_tagged_line(line, &subsystems, &mut state);
+        assert_eq!(events.len(), 1);
+        assert_eq!(events[0].0, Subsystem::Cmio);
+
+        // Untagged lines are not guessed at
+        let untagged = r#"{"eventMessage":"CMIODeviceStartStream","processID":5678}"#;
+        assert!(parse_tagged_line(untagged, &subsystems, &mut state).is_empty());
+    }
+
+    #[test]
+    fn test_stream_state_reconciles_subsystems() {
+        let mut stream = LogStreamState::new(
+            vec![Subsystem::CameraCapture, Subsystem::Cmio],
+            None,
+            Arc::default(),
+        );
+        let capture_start = "2025-06-10 14:23:01.000000-0700  zoom.us[5678]: (com.apple.cameracapture) [AVCaptureSession startRunning]: called";
+        let cmio_start = "2025-06-10 14:23:01.100000-0700  zoom.us[5678]: (com.apple.cmio) CMIODeviceStartStream";
+        let cmio_stop =
+            "2025-06-10 14:25:00.000000-0700  zoom.us[5678]: (com.apple.cmio) CMIODeviceStopStream";
+        let capture_stop = "2025-06-10 14:25:01.000000-0700  zoom.us[5678]: (com.apple.cameracapture) [AVCaptureSession stopRunning]: called";
+
+        let events = stream.route_line(capture_start);
+        assert!(matches!(events[0], CameraEvent::Started { .. }));
+
+        let events = stream.route_line(cmio_start);
+        assert_eq!(events.len(), 1);
+        match &events[0] {
+            CameraEvent::Confirmed { source, .. } => assert_eq!(source, "cmio"),
+            _ => panic!("Expected Confirmed event"),
+        }
+
+        assert!(stream.route_line(cmio_stop).is_empty());
+        let events = stream.route_line(capture_stop);
+        match &events[..] {
+            [CameraEvent::Stopped { timestamp, .. }] => {
+                assert_eq!(timestamp.to_rfc3339(), "2025-06-10T21:25:01+00:00")
+            }
+            _ => panic!("Expected a single Stopped event"),
+        }
+    }
+
+    #[test]
+    fn test_stream_state_merges_one_app_reported_under_two_names() {
+        let path = storage::scratch_db("stream-aliases");
+        let manager = SessionManager::new(path.clone());
+        let mut stream = LogStreamState::new(
+            vec![Subsystem::ControlCenter, Subsystem::CameraCapture],
+            None,
+            manager.aliases(),
+        );
+        let lines = [
+            r#"{"eventMessage":"activeCameraAttributions: [cam] Zoom (us.zoom.xos)","subsystem":"com.apple.controlcenter","processImagePath":"/System/Library/CoreServices/ControlCenter.app/Contents/MacOS/ControlCenter","processID":321,"timestamp":"2025-06-10 14:23:01.000000-0700"}"#,
+            r#"{"eventMessage":"[AVCaptureSession startRunning]: called","subsystem":"com.apple.cameracapture","category":"capture","processImagePath":"/Applications/zoom.us.app/Contents/MacOS/zoom.us","processID":5678,"timestamp":"2025-06-10 14:23:01.200000-0700"}"#,
+            r#"{"eventMessage":"[AVCaptureSession stopRunning]: called","subsystem":"com.apple.cameracapture","category":"capture","processImagePath":"/Applications/zoom.us.app/Contents/MacOS/zoom.us","processID":5678,"timestamp":"2025-06-10 14:25:00.000000-0700"}"#,
+            r#"{"eventMessage":"activeCameraAttributions: (none)","subsystem":"com.apple.controlcenter","processImagePath":"/System/Library/CoreServices/ControlCenter.app/Contents/MacOS/ControlCenter","processID":321,"timestamp":"2025-06-10 14:25:01.000000-0700"}"#,
+        ];
+        for line in lines {
+            for event in stream.route_line(line) {
+                apply_event(&manager, event).unwrap();
+            }
+        }
+
+        let sessions = manager.get_all_sessions().unwrap();
+        assert_eq!(sessions.len(), 1);
+        assert_eq!(sessions[0].app_name, "Zoom");
+        assert_eq!(sessions[0].confirmed_by, vec!["controlcenter", "cameracapture"]);
+        assert_eq!(
+            sessions[0].end_time.map(|t| t.to_rfc3339()).as_deref(),
+            Some("2025-06-10T21:25:01+00:00")
+        );
+    }
+
+    // --- Detection tests ---
+
+    fn evidence(subsystem: Subsystem, matches: usize) -> ProbeEvidence {
+        ProbeEvidence {
+            subsystem,
+            matches,
+            sample: None,
+            error: None,
+        }
+    }
+
+    #[test]
+    fn test_choose_subsystem_prefers_priority_order() {
+        let probes = vec![
+            evidence(Subsystem::ControlCenter, 0),
+            evidence(Subsystem::SkyLight, 0),
+            evidence(Subsystem::CameraCapture, 3),
+            evidence(Subsystem::Cmio, 7),
+        ];
+        assert_eq!(choose_subsystem(&probes), (Subsystem::CameraCapture, false));
+    }
+
+    #[test]
+    fn test_choose_subsystem_falls_back_to_controlcenter() {
+        let probes: Vec<_> = Subsystem::ALL.iter().map(|s| evidence(*s, 0)).collect();
+        assert_eq!(choose_subsystem(&probes), (Subsystem::ControlCenter, true));
+    }
+
+    #[test]
+    fn test_probe_matches_counts_lines() {
+        let output = "Filtering the log data\nfoo startRunning a\nbar\nbaz startRunning b\n";
+        let (matches, sample) = probe_matches(output, "startRunning");
+        assert_eq!(matches, 2);
+        assert_eq!(sample.as_deref(), Some("foo startRunning a"));
+        assert_eq!(probe_matches(output, "CMIODeviceStartStream"), (0, None));
+    }
+
+    #[test]
+    fn test_parse_os_build() {
+        let output = "ProductName:\t\tmacOS\nProductVersion:\t\t14.5\nBuildVersion:\t\t23F79\n";
+        assert_eq!(parse_os_build(output).as_deref(), Some("23F79"));
+        assert_eq!(parse_os_build(""), None);
+    }
+
+    #[test]
+    fn test_cached_detection_reads_meta() {
+        let path = storage::scratch_db("detection");
+
+        assert_eq!(cached_detection(&path), None);
+
+        storage::set_meta(&path, META_DETECTED_SUBSYSTEM, "cmio").unwrap();
+        storage::set_meta(&path, META_DETECTED_OS_BUILD, "").unwrap();
+        assert_eq!(cached_detection(&path), Some((Subsystem::Cmio, None)));
+
+        storage::set_meta(&path, META_DETECTED_OS_BUILD, "23F79").unwrap();
+        assert_eq!(
+            cached_detection(&path),
+            Some((Subsystem::Cmio, Some("23F79".to_string())))
+        );
+    }
 }
@@ -0,0 +1,242 @@
+use chrono::{DateTime, Utc};
+use std::process::Command;
+use std::time::Instant;
+
+// ---------------------------------------------------------------------------
+// Sleep/wake and screen lock
+// ---------------------------------------------------------------------------
+
+/// A wall-clock gap this much longer than the time the process was awake
+/// for means the machine slept in between.
+const SLEEP_GAP: chrono::Duration = chrono::Duration::seconds(20);
+
+/// A change in the machine's power or lock state.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PowerEvent {
+    /// The machine slept from `slept_at` until `woke_at`.
+    Slept {
+        slept_at: DateTime<Utc>,
+        woke_at: DateTime<Utc>,
+    },
+    ScreenLocked,
+    ScreenUnlocked,
+}
+
+/// One `Sleep` or `Wake` entry of `pmset -g log`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PmsetEntry {
+    Sleep(DateTime<Utc>),
+    Wake(DateTime<Utc>),
+}
+
+/// Watches for sleeps and screen lock changes by polling.
+///
+/// A sleep shows up as the wall clock having moved further than the
+/// monotonic clock, which stops while the machine sleeps. Its exact times
+/// are then looked up in `pmset -g log`. The screen lock state is read from
+/// `ioreg`. Either command failing (e.g. off macOS) only loses precision:
+/// the sleep is then assumed to span the unexplained part of the gap.
+#[derive(Debug)]
+pub struct PowerWatcher {
+    last_wall: DateTime<Utc>,
+    last_tick: Instant,
+    screen_locked: bool,
+}
+
+impl PowerWatcher {
+    pub fn new() -> Self {
+        Self {
+            last_wall: Utc::now(),
+            last_tick: Instant::now(),
+            screen_locked: false,
+        }
+    }
+
+    /// Reports the sleeps since the last poll and a change of the screen
+    /// lock state, if any.
+    pub fn poll(&mut self) -> Vec<PowerEvent> {
+        let now = Utc::now();
+        let tick = Instant::now();
+        let awake = chrono::Duration::from_std(tick - self.last_tick).unwrap_or_default();
+        let mut events = Vec::new();
+
+        if let Some((slept_at, woke_at)) = detect_sleep(self.last_wall, now, awake) {
+            let logged = pmset_log()
+                .map(|log| parse_sleeps(&log, self.last_wall, now))
+                .unwrap_or_default();
+            if logged.is_empty() {
+                events.push(PowerEvent::Slept { slept_at, woke_at });
+            }
+            events.extend(
+                logged
+                    .into_iter()
+                    .map(|(slept_at, woke_at)| PowerEvent::Slept { slept_at, woke_at }),
+            );
+        }
+        self.last_wall = now;
+        self.last_tick = tick;
+
+        if let Some(locked) = screen_locked() {
+            if locked != self.screen_locked {
+                self.screen_locked = locked;
+                events.push(if locked {
+                    PowerEvent::ScreenLocked
+                } else {
+                    PowerEvent::ScreenUnlocked
+                });
+            }
+        }
+
+        events
+    }
+}
+
+impl Default for PowerWatcher {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// The sleep implied by `now` being further from `last_wall` than the
+/// `awake` time the monotonic clock counted, assuming the machine was awake
+/// first and asleep for the rest.
+pub fn detect_sleep(
+    last_wall: DateTime<Utc>,
+    now: DateTime<Utc>,
+    awake: chrono::Duration,
+) -> Option<(DateTime<Utc>, DateTime<Utc>)> {
+    let asleep = (now - last_wall) - awake;
+    (asleep > SLEEP_GAP).then(|| (last_wall + awake, now))
+}
+
+/// Parses a `Sleep` or `Wake` line of `pmset -g log`, e.g.
+/// `2025-06-10 14:23:01 +0200 Sleep  \tEntering Sleep state due to 'Clamshell Sleep'`.
+/// `DarkWake` and maintenance entries are not user wakes and are ignored.
+pub fn parse_pmset_line(line: &str) -> Option<PmsetEntry> {
+    let mut tokens = line.split_whitespace();
+    let date = tokens.next()?;
+    let time = tokens.next()?;
+    let offset = tokens.next()?;
+    let kind = tokens.next()?;
+
+    let at = DateTime::parse_from_str(
+        &format!("{} {} {}", date, time, offset),
+        "%Y-%m-%d %H:%M:%S %z",
+    )
+    .ok()?
+    .with_timezone(&Utc);
+
+    match kind {
+        "Sleep" => Some(PmsetEntry::Sleep(at)),
+        "Wake" => Some(PmsetEntry::Wake(at)),
+        _ => None,
+    }
+}
+
+/// The sleeps in `pmset -g log` output that ended between `after` and
+/// `until`, as (sleep, wake) pairs.
+pub fn parse_sleeps(
+    log: &str,
+    after: DateTime<Utc>,
+    until: DateTime<Utc>,
+) -> Vec<(DateTime<Utc>, DateTime<Utc>)> {
+    let mut sleeps = Vec::new();
+    let mut asleep_since = None;
+
+    for entry in log.lines().filter_map(parse_pmset_line) {
+        match entry {
+            PmsetEntry::Sleep(at) => asleep_since = asleep_since.or(Some(at)),
+            PmsetEntry::Wake(at) => {
+                if let Some(slept_at) = asleep_since.take() {
+                    if at > after && at <= until {
+                        sleeps.push((slept_at, at));
+                    }
+                }
+            }
+        }
+    }
+
+    sleeps
+}
+
+/// Whether `ioreg -n Root -d1` output shows the console session's screen as
+/// locked.
+pub fn parse_screen_locked(ioreg: &str) -> bool {
+    ioreg.contains("\"CGSSessionScreenIsLocked\"=Yes")
+}
+
+fn pmset_log() -> Option<String> {
+    let output = Command::new("pmset").args(["-g", "log"]).output().ok()?;
+    output
+        .status
+        .success()
+        .then(|| String::from_utf8_lossy(&output.stdout).into_owned())
+}
+
+fn screen_locked() -> Option<bool> {
+    let output = Command::new("ioreg")
+        .args(["-n", "Root", "-d1"])
+        .output()
+        .ok()?;
+    output
+        .status
+        .success()
+        .then(|| parse_screen_locked(&String::from_utf8_lossy(&output.stdout)))
+}
+
+// ---------------------------------------------------------------------------
+// Tests
+// ---------------------------------------------------------------------------
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::{Duration, TimeZone};
+
+    #[test]
+    fn test_detect_sleep_from_clock_gap() {
+        let last = Utc.with_ymd_and_hms(2025, 6, 10, 14, 0, 0).unwrap();
+
+        // Awake the whole time
+        let now = last + Duration::seconds(10);
+        assert_eq!(detect_sleep(last, now, Duration::seconds(10)), None);
+
+        let now = last + Duration::hours(2);
+        assert_eq!(
+            detect_sleep(last, now, Duration::seconds(10)),
+            Some((last + Duration::seconds(10), now))
+        );
+    }
+
+    #[test]
+    fn test_parse_sleeps_pairs_sleep_with_wake() {
+        let log = "\
+2025-06-10 13:00:00 +0000 Sleep               \tEntering Sleep state due to 'Idle Sleep'
+2025-06-10 13:30:00 +0000 Wake                \tWake from Deep Idle [CDNVA] : due to UserActivity
+2025-06-10 14:05:00 +0000 Sleep               \tEntering Sleep state due to 'Clamshell Sleep'
+2025-06-10 14:20:00 +0000 DarkWake            \tDarkWake from Deep Idle [CDN] : due to RTC/Maintenance
+2025-06-10 14:21:00 +0000 Sleep               \tEntering Sleep state due to 'Maintenance Sleep'
+2025-06-10 16:40:00 +0200 Wake                \tWake from Deep Idle [CDNVA] : due to UserActivity
+garbage line
+";
+        let after = Utc.with_ymd_and_hms(2025, 6, 10, 14, 0, 0).unwrap();
+        let until = Utc.with_ymd_and_hms(2025, 6, 10, 15, 0, 0).unwrap();
+
+        assert_eq!(
+            parse_sleeps(log, after, until),
+            vec![(
+                Utc.with_ymd_and_hms(2025, 6, 10, 14, 5, 0).unwrap(),
+                Utc.with_ymd_and_hms(2025, 6, 10, 14, 40, 0).unwrap(),
+            )]
+        );
+    }
+
+    #[test]
+    fn test_parse_screen_locked() {
+        let locked = r#"    | |   "IOConsoleUsers" = ({"kCGSSessionOnConsoleKey"=Yes,"CGSSessionScreenIsLocked"=Yes,"kCGSSessionUserNameKey"="me"})"#;
+        assert!(parse_screen_locked(locked));
+        assert!(!parse_screen_locked(
+            r#"    | |   "IOConsoleUsers" = ({"kCGSSessionOnConsoleKey"=Yes})"#
+        ));
+    }
+}
@@ -0,0 +1,409 @@
+use crate::camera::monitor::{CameraEvent, Sensor};
+use crate::session::aliases::Aliases;
+use std::collections::{BTreeMap, BTreeSet};
+use std::sync::{Arc, Mutex};
+
+// ---------------------------------------------------------------------------
+// Reconciling events from several origins
+// ---------------------------------------------------------------------------
+
+/// An app that at least one origin currently reports as using a sensor.
+#[derive(Debug)]
+struct ActiveApp {
+    /// Identity of the session that was started, reused for every event we
+    /// emit about it so the session manager always finds the same session.
+    app_name: String,
+    bundle_id: Option<String>,
+    pid: Option<u32>,
+    sensor: Sensor,
+    /// `app_name` after alias rules, which events are matched on.
+    normalized: String,
+    /// Origins that currently report the app as active, with how many of
+    /// their starts are not yet balanced by a stop.
+    claims: BTreeMap<String, u32>,
+    /// Every origin that has reported the app as active during the session.
+    confirmed_by: BTreeSet<String>,
+}
+
+impl ActiveApp {
+    /// Whether an event about the app normalized to `normalized` refers to
+    /// this app: by bundle ID when both sides know it, otherwise by PID when
+    /// both know it, otherwise by case-insensitive normalized name.
+    fn is(&self, normalized: &str, bundle_id: Option<&str>, pid: Option<u32>) -> bool {
+        if let (Some(ours), Some(theirs)) = (self.bundle_id.as_deref(), bundle_id) {
+            return ours == theirs;
+        }
+        if let (Some(ours), Some(theirs)) = (self.pid, pid) {
+            return ours == theirs;
+        }
+        self.normalized.to_lowercase() == normalized.to_lowercase()
+    }
+}
+
+/// Merges `CameraEvent`s from several origins (e.g. log subsystems) that can
+/// report the same app into a single authoritative stream.
+///
+/// Apps are matched across origins by sensor and by the strongest identity
+/// both events carry: bundle ID, then PID, then case-insensitive name after
+/// alias rules, since not every origin knows the bundle ID or PID and origins
+/// may report one app under different names (ControlCenter's `Zoom` is
+/// cameracapture's `zoom.us`). The first `Started` for an app
+/// starts its session; later ones from other origins only confirm it. The
+/// session stops once every origin that reported it has reported it stopped,
+/// so a stop from one origin does not cut short a session another origin
+/// still sees. Stops for apps no origin reported as started are dropped.
+///
+/// An origin that starts an app it already reports opened a nested capture
+/// session: the start and its matching stop are forwarded as-is, so the
+/// session manager can count them.
+///
+/// Each origin that reports an app adds a `Confirmed` event for the session.
+#[derive(Debug, Default)]
+pub struct Reconciler {
+    active: Vec<ActiveApp>,
+    /// Rules naming apps, shared with the session manager the events go to.
+    aliases: Arc<Mutex<Aliases>>,
+}
+
+impl Reconciler {
+    /// A reconciler that matches names as reported, without alias rules.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// A reconciler that matches names after `aliases`, e.g. the rules of
+    /// the `SessionManager` the events go to (`SessionManager::aliases`).
+    pub fn with_aliases(aliases: Arc<Mutex<Aliases>>) -> Self {
+        Self {
+            active: Vec::new(),
+            aliases,
+        }
+    }
+
+    fn normalize(&self, app_name: &str, bundle_id: Option<&str>) -> String {
+        self.aliases.lock().unwrap().normalize(app_name, bundle_id)
+    }
+
+    /// Index of the active app an event refers to, if any.
+    fn find(
+        &self,
+        app_name: &str,
+        bundle_id: Option<&str>,
+        pid: Option<u32>,
+        sensor: Sensor,
+    ) -> Option<usize> {
+        let normalized = self.normalize(app_name, bundle_id);
+        self.active
+            .iter()
+            .position(|app| app.sensor == sensor && app.is(&normalized, bundle_id, pid))
+    }
+
+    /// Forgets an app's claims, after its session was ended by something other
+    /// than a stop. The next start from any origin starts a new session.
+    pub fn forget(
+        &mut self,
+        app_name: &str,
+        bundle_id: Option<&str>,
+        pid: Option<u32>,
+        sensor: Sensor,
+    ) {
+        if let Some(index) = self.find(app_name, bundle_id, pid, sensor) {
+            self.active.remove(index);
+        }
+    }
+
+    /// Applies one event reported by `origin` and returns the events to
+    /// forward, if any.
+    pub fn apply(&mut self, origin: &str, event: CameraEvent) -> Vec<CameraEvent> {
+        match event {
+            CameraEvent::Started {
+                app_name,
+                bundle_id,
+                pid,
+                sensor,
+                timestamp,
+            } => {
+                let mut events = Vec::new();
+
+                let index = match self.find(&app_name, bundle_id.as_deref(), pid, sensor) {
+                    Some(index) => index,
+                    None => {
+                        events.push(CameraEvent::Started {
+                            app_name: app_name.clone(),
+                            bundle_id: bundle_id.clone(),
+                            pid,
+                            sensor,
+                            timestamp,
+                        });
+                        let normalized = self.normalize(&app_name, bundle_id.as_deref());
+                        self.active.push(ActiveApp {
+                            app_name,
+                            bundle_id,
+                            pid,
+                            sensor,
+                            normalized,
+                            claims: BTreeMap::new(),
+                            confirmed_by: BTreeSet::new(),
+                        });
+                        self.active.len() - 1
+                    }
+                };
+                let app = &mut self.active[index];
+
+                let count = app.claims.entry(origin.to_string()).or_insert(0);
+                *count += 1;
+                if *count > 1 {
+                    events.push(CameraEvent::Started {
+                        app_name: app.app_name.clone(),
+                        bundle_id: app.bundle_id.clone(),
+                        pid: app.pid,
+                        sensor,
+                        timestamp,
+                    });
+                }
+                if app.confirmed_by.insert(origin.to_string()) {
+                    events.push(CameraEvent::Confirmed {
+                        app_name: app.app_name.clone(),
+                        bundle_id: app.bundle_id.clone(),
+                        pid: app.pid,
+                        sensor,
+                        source: origin.to_string(),
+                    });
+                }
+
+                events
+            }
+            CameraEvent::Stopped {
+                app_name,
+                bundle_id,
+                pid,
+                sensor,
+                timestamp,
+            } => {
+                let index = match self.find(&app_name, bundle_id.as_deref(), pid, sensor) {
+                    Some(index) => index,
+                    None => return vec![],
+                };
+                let app = &mut self.active[index];
+
+                let nested = match app.claims.get_mut(origin) {
+                    Some(count) if *count > 1 => {
+                        *count -= 1;
+                        true
+                    }
+                    _ => {
+                        app.claims.remove(origin);
+                        false
+                    }
+                };
+                if nested {
+                    return vec![CameraEvent::Stopped {
+                        app_name: app.app_name.clone(),
+                        bundle_id: app.bundle_id.clone(),
+                        pid: app.pid,
+                        sensor,
+                        timestamp,
+                    }];
+                }
+                if !app.claims.is_empty() {
+                    // Another origin still sees the app using the sensor
+                    return vec![];
+                }
+
+                let app = self.active.remove(index);
+                vec![CameraEvent::Stopped {
+                    app_name: app.app_name,
+                    bundle_id: app.bundle_id,
+                    pid: app.pid,
+                    sensor,
+                    timestamp,
+                }]
+            }
+            // Confirmations are produced here, never consumed
+            CameraEvent::Confirmed { .. } => vec![],
+        }
+    }
+}
+
+// ---------------------------------------------------------------------------
+// Tests
+// ---------------------------------------------------------------------------
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::Utc;
+
+    fn started(app_name: &str) -> CameraEvent {
+        CameraEvent::Started {
+            app_name: app_name.to_string(),
+            bundle_id: None,
+            pid: None,
+            sensor: Sensor::Camera,
+            timestamp: Utc::now(),
+        }
+    }
+
+    fn stopped(app_name: &str) -> CameraEvent {
+        CameraEvent::Stopped {
+            app_name: app_name.to_string(),
+            bundle_id: None,
+            pid: None,
+            sensor: Sensor::Camera,
+            timestamp: Utc::now(),
+        }
+    }
+
+    fn sources(events: &[CameraEvent]) -> Vec<&str> {
+        events
+            .iter()
+            .filter_map(|e| match e {
+                CameraEvent::Confirmed { source, .. } => Some(source.as_str()),
+                _ => None,
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_duplicate_start_only_confirms() {
+        let mut reconciler = Reconciler::new();
+
+        let first = reconciler.apply("cameracapture", started("zoom.us"));
+        assert!(matches!(first[0], CameraEvent::Started { .. }));
+        assert_eq!(sources(&first), vec!["cameracapture"]);
+
+        let second = reconciler.apply("controlcenter", started("Zoom.us"));
+        assert_eq!(second.len(), 1);
+        assert_eq!(sources(&second), vec!["controlcenter"]);
+    }
+
+    #[test]
+    fn test_nested_starts_from_one_origin_are_forwarded() {
+        let mut reconciler = Reconciler::new();
+        reconciler.apply("cameracapture", started("zoom.us"));
+
+        // A second capture session from the same origin is counted
+        let nested = reconciler.apply("cameracapture", started("zoom.us"));
+        assert_eq!(nested.len(), 1);
+        assert!(matches!(nested[0], CameraEvent::Started { .. }));
+
+        let inner = reconciler.apply("cameracapture", stopped("zoom.us"));
+        assert!(matches!(inner[..], [CameraEvent::Stopped { .. }]));
+        let outer = reconciler.apply("cameracapture", stopped("zoom.us"));
+        assert!(matches!(outer[..], [CameraEvent::Stopped { .. }]));
+        assert!(reconciler
+            .apply("cameracapture", stopped("zoom.us"))
+            .is_empty());
+    }
+
+    #[test]
+    fn test_bundle_id_outranks_name() {
+        let with_bundle = |app_name: &str, bundle_id: &str| CameraEvent::Started {
+            app_name: app_name.to_string(),
+            bundle_id: Some(bundle_id.to_string()),
+            pid: None,
+            sensor: Sensor::Camera,
+            timestamp: Utc::now(),
+        };
+        let mut reconciler = Reconciler::new();
+        reconciler.apply("cameracapture", with_bundle("zoom.us", "us.zoom.xos"));
+
+        // Different names for one bundle are the same app
+        let same = reconciler.apply("controlcenter", with_bundle("Zoom", "us.zoom.xos"));
+        assert_eq!(same.len(), 1);
+        assert_eq!(sources(&same), vec!["controlcenter"]);
+
+        // One name for different bundles is not
+        let other = reconciler.apply("controlcenter", with_bundle("zoom.us", "com.example.zoom"));
+        assert!(matches!(other[0], CameraEvent::Started { .. }));
+    }
+
+    #[test]
+    fn test_names_match_after_aliases() {
+        let aliases = Arc::new(Mutex::new(Aliases::new(vec![])));
+        let mut reconciler = Reconciler::with_aliases(aliases);
+        reconciler.apply(
+            "controlcenter",
+            CameraEvent::Started {
+                app_name: "Zoom".to_string(),
+                bundle_id: Some("us.zoom.xos".to_string()),
+                pid: None,
+                sensor: Sensor::Camera,
+                timestamp: Utc::now(),
+            },
+        );
+
+        let capture = CameraEvent::Started {
+            app_name: "zoom.us".to_string(),
+            bundle_id: None,
+            pid: Some(5678),
+            sensor: Sensor::Camera,
+            timestamp: Utc::now(),
+        };
+        let events = reconciler.apply("cameracapture", capture.clone());
+        assert_eq!(events.len(), 1);
+        assert_eq!(sources(&events), vec!["cameracapture"]);
+
+        // Without the rules the names differ
+        let mut reconciler = Reconciler::new();
+        reconciler.apply("controlcenter", started("Zoom"));
+        let events = reconciler.apply("cameracapture", capture);
+        assert!(matches!(events[0], CameraEvent::Started { .. }));
+    }
+
+    #[test]
+    fn test_stop_waits_for_every_claiming_origin() {
+        let mut reconciler = Reconciler::new();
+        reconciler.apply("cameracapture", started("FaceTime"));
+        reconciler.apply("cmio", started("FaceTime"));
+
+        assert!(reconciler.apply("cmio", stopped("FaceTime")).is_empty());
+
+        let events = reconciler.apply("cameracapture", stopped("FaceTime"));
+        assert_eq!(events.len(), 1);
+        assert!(matches!(events[0], CameraEvent::Stopped { .. }));
+    }
+
+    #[test]
+    fn test_stop_without_start_is_dropped() {
+        let mut reconciler = Reconciler::new();
+        assert!(reconciler.apply("cmio", stopped("FaceTime")).is_empty());
+
+        reconciler.apply("cameracapture", started("FaceTime"));
+        reconciler.apply("cameracapture", stopped("FaceTime"));
+        assert!(reconciler.apply("cmio", stopped("FaceTime")).is_empty());
+    }
+
+    #[test]
+    fn test_events_keep_identity_of_first_start() {
+        let mut reconciler = Reconciler::new();
+        reconciler.apply(
+            "cameracapture",
+            CameraEvent::Started {
+                app_name: "FaceTime".to_string(),
+                bundle_id: None,
+                pid: Some(1234),
+                sensor: Sensor::Camera,
+                timestamp: Utc::now(),
+            },
+        );
+
+        let events = reconciler.apply(
+            "controlcenter",
+            CameraEvent::Started {
+                app_name: "FaceTime".to_string(),
+                bundle_id: Some("com.apple.FaceTime".to_string()),
+                pid: None,
+                sensor: Sensor::Camera,
+                timestamp: Utc::now(),
+            },
+        );
+        match &events[0] {
+            CameraEvent::Confirmed { bundle_id, pid, .. } => {
+                assert_eq!(*bundle_id, None);
+                assert_eq!(*pid, Some(1234));
+            }
+            _ => panic!("Expected Confirmed event"),
+        }
+    }
+}
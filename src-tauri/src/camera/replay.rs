@@ -0,0 +1,628 @@
+use crate::camera::journal::EventKind;
+use crate::camera::monitor::CameraEvent;
+use crate::camera::platform_macos::{self, ParserState, Subsystem};
+use crate::camera::reconcile::Reconciler;
+use crate::session::policy::AppPolicy;
+use crate::session::{EndReason, Session, SessionManager, SessionSource};
+use crate::storage;
+use anyhow::{Context, Result};
+use chrono::{DateTime, Utc};
+use serde::Serialize;
+use std::collections::HashSet;
+use std::fs::File;
+use std::io::{BufRead, BufReader};
+use std::path::Path;
+
+// ---------------------------------------------------------------------------
+// Replaying recorded `log stream` captures
+// ---------------------------------------------------------------------------
+
+/// Replays a saved `log stream` capture (`--style ndjson` or `--style syslog`)
+/// into a scratch database and returns the sessions it produced.
+///
+/// Every line goes through `parse_log_line` with the given subsystem, and the
+/// resulting events are applied through `SessionManager` at the timestamp
+/// printed on the line, so the same capture always yields the same sessions.
+/// Any existing database at `db_path` is replaced.
+pub fn replay_capture(capture: &Path, subsystem: Subsystem, db_path: &Path) -> Result<Vec<Session>> {
+    let file = File::open(capture)
+        .with_context(|| format!("Failed to open capture {}", capture.display()))?;
+
+    if db_path.exists() {
+        std::fs::remove_file(db_path).context("Failed to remove previous scratch database")?;
+    }
+    storage::init_db(db_path)?;
+
+    let manager = SessionManager::new(db_path.to_path_buf());
+    replay_lines(BufReader::new(file), subsystem, &manager)?;
+
+    manager.get_all_sessions()
+}
+
+/// Feeds every line of `reader` through the parser and applies the resulting
+/// events to `manager` at the timestamps the parser read off each line.
+/// Returns the number of events applied.
+pub fn replay_lines<R: BufRead>(
+    reader: R,
+    subsystem: Subsystem,
+    manager: &SessionManager,
+) -> Result<usize> {
+    let mut state = ParserState::default();
+    let mut applied = 0;
+
+    for line in reader.lines() {
+        let line = line.context("Failed to read capture")?;
+
+        for event in platform_macos::parse_log_line(&line, &subsystem, &mut state) {
+            apply_event(manager, event)?;
+            applied += 1;
+        }
+    }
+
+    Ok(applied)
+}
+
+/// Applies one parsed event to `manager` at the event's own timestamp.
+/// Starts of apps whose policy is `AppPolicy::Ignore` are dropped, as they
+/// are when tracking live.
+pub fn apply_event(manager: &SessionManager, event: CameraEvent) -> Result<()> {
+    match event {
+        CameraEvent::Started {
+            app_name,
+            bundle_id,
+            pid,
+            sensor,
+            timestamp,
+        } => {
+            if manager.app_policy(&app_name, bundle_id.as_deref())? != AppPolicy::Ignore {
+                manager.start_session(app_name, bundle_id, pid, sensor, timestamp)?;
+            }
+        }
+        CameraEvent::Stopped {
+            app_name,
+            bundle_id,
+            pid,
+            sensor,
+            timestamp,
+        } => {
+            manager.end_session_for_app(&app_name, bundle_id.as_deref(), pid, sensor, timestamp)?;
+        }
+        CameraEvent::Confirmed {
+            app_name,
+            bundle_id,
+            pid,
+            sensor,
+            source,
+        } => {
+            manager.confirm_session(&app_name, bundle_id.as_deref(), pid, sensor, &source)?;
+        }
+    }
+
+    Ok(())
+}
+
+// ---------------------------------------------------------------------------
+// Rebuilding sessions from the event journal
+// ---------------------------------------------------------------------------
+
+/// What `rebuild_sessions` changed.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize)]
+pub struct RebuildSummary {
+    /// Sessions deleted from the range.
+    pub removed: usize,
+    /// Sessions recomputed from the journal.
+    pub rebuilt: usize,
+    /// Journaled events applied.
+    pub events: usize,
+}
+
+/// Journaled events read at a time while rebuilding.
+const JOURNAL_PAGE: usize = 1000;
+
+/// Recomputes the sessions that started within `[start, end)` from the
+/// `camera_events` journal, through the same `SessionManager` rules (and
+/// `Reconciler`, when the range has events from several subsystems) as live
+/// events. Rebuilt sessions are marked `SessionSource::Rebuilt`.
+///
+/// The range is narrowed so it never reaches back before the oldest journaled
+/// event (older sessions could not be recomputed) or forward into a session
+/// that is still running. Events after `end` are only used to stop sessions
+/// that started inside the range; any still open are closed at `end`.
+///
+/// Ends the journal does not record (pauses, shutdowns, sleep, crash
+/// recovery, liveness checks) are kept: a rebuilt session is ended wherever
+/// the old session it replaces ended for one of them. The new sessions are
+/// written before the old ones are deleted, so a failed rebuild leaves the
+/// range as it was.
+pub fn rebuild_sessions(
+    db_path: &Path,
+    start: DateTime<Utc>,
+    end: DateTime<Utc>,
+) -> Result<RebuildSummary> {
+    let start = match storage::earliest_camera_event(db_path)? {
+        Some(earliest) => start.max(earliest),
+        None => return Ok(RebuildSummary::default()),
+    };
+    let end = storage::get_active_sessions(db_path)?
+        .iter()
+        .map(|s| s.start_time)
+        .fold(end, DateTime::min);
+    if start >= end {
+        return Ok(RebuildSummary::default());
+    }
+
+    let previous = storage::get_sessions_between(db_path, start, end)?;
+    let previous_ids: HashSet<i64> = previous.iter().filter_map(|s| s.id).collect();
+    let boundaries = unjournaled_ends(db_path, &previous)?;
+
+    let manager = SessionManager::with_source(db_path.to_path_buf(), SessionSource::Rebuilt);
+    let rebuilt = apply_journal(&manager, db_path, start, end, &boundaries)
+        .and_then(|counts| {
+            manager.end_all_active_sessions_at(end, EndReason::ReplayEnd)?;
+            Ok(counts)
+        });
+    let (rebuilt, events) = match rebuilt {
+        Ok(counts) => counts,
+        Err(e) => {
+            // Drop whatever was rebuilt so far; the old sessions are untouched
+            let partial: Vec<i64> = storage::get_sessions_between(db_path, start, end)?
+                .iter()
+                .filter(|s| s.source == SessionSource::Rebuilt)
+                .filter_map(|s| s.id)
+                .filter(|id| !previous_ids.contains(id))
+                .collect();
+            storage::delete_sessions(db_path, &partial)?;
+            return Err(e);
+        }
+    };
+
+    let previous_ids: Vec<i64> = previous_ids.into_iter().collect();
+    let removed = storage::delete_sessions(db_path, &previous_ids)?;
+
+    eprintln!(
+        "[webcam-tracker] Rebuilt {} sessions ({} removed) from {} journaled events between {} and {}",
+        rebuilt, removed, events, start, end
+    );
+
+    Ok(RebuildSummary {
+        removed,
+        rebuilt,
+        events,
+    })
+}
+
+/// An end of an old session that the journal does not record.
+struct Boundary<'a> {
+    session: &'a Session,
+    ended_at: DateTime<Utc>,
+    /// For a sleep end, when the session resumed on wake.
+    resumed_at: Option<DateTime<Utc>>,
+}
+
+/// The ends among `sessions` a rebuild could not recompute from the journal,
+/// in the order they happened.
+fn unjournaled_ends<'a>(db_path: &Path, sessions: &'a [Session]) -> Result<Vec<Boundary<'a>>> {
+    let mut boundaries = Vec::new();
+    for session in sessions {
+        let (ended_at, end_reason) = match (session.end_time, session.end_reason) {
+            (Some(ended_at), Some(end_reason)) => (ended_at, end_reason),
+            _ => continue,
+        };
+        let resumed_at = match end_reason {
+            EndReason::Stopped | EndReason::Restarted | EndReason::ReplayEnd => continue,
+            EndReason::Sleep => resumed_after_sleep(db_path, sessions, session, ended_at)?,
+            _ => None,
+        };
+        boundaries.push(Boundary {
+            session,
+            ended_at,
+            resumed_at,
+        });
+    }
+    boundaries.sort_by_key(|boundary| boundary.ended_at);
+    Ok(boundaries)
+}
+
+/// When `session`, ended by sleep at `slept_at`, resumed on wake, if it
+/// did: the start of the next session of the same app instance among
+/// `sessions`, unless a journaled start began that one.
+fn resumed_after_sleep(
+    db_path: &Path,
+    sessions: &[Session],
+    session: &Session,
+    slept_at: DateTime<Utc>,
+) -> Result<Option<DateTime<Utc>>> {
+    let next = sessions
+        .iter()
+        .filter(|next| {
+            next.start_time >= slept_at
+                && next.app_name == session.app_name
+                && next.bundle_id == session.bundle_id
+                && next.pid == session.pid
+                && next.sensor == session.sensor
+        })
+        .min_by_key(|next| next.start_time);
+    let next = match next {
+        Some(next) => next,
+        None => return Ok(None),
+    };
+
+    // A resumed session has no journaled start; one a start after wake
+    // began is rebuilt from that start
+    let started = storage::get_session_events(db_path, next)?
+        .iter()
+        .any(|event| event.kind == EventKind::Started && event.log_timestamp == next.start_time);
+    Ok((!started).then_some(next.start_time))
+}
+
+/// Applies the journal from `start` on (in log order) to `manager`, ending
+/// sessions at `boundaries` as it passes them. Starts at or after `end` are
+/// ignored, and the walk stops once nothing started before `end` is still
+/// open. Returns the number of sessions started and events applied.
+fn apply_journal(
+    manager: &SessionManager,
+    db_path: &Path,
+    start: DateTime<Utc>,
+    end: DateTime<Utc>,
+    boundaries: &[Boundary],
+) -> Result<(usize, usize)> {
+    let subsystems = storage::camera_event_subsystems_between(db_path, start, end)?;
+    let mut reconciler =
+        (subsystems.len() > 1).then(|| Reconciler::with_aliases(manager.aliases()));
+    let mut boundaries = boundaries.iter().peekable();
+
+    let mut started = 0;
+    let mut applied = 0;
+    let (mut since, mut after_id) = (start, -1);
+    'pages: loop {
+        let page = storage::get_camera_events_page(db_path, since, after_id, JOURNAL_PAGE)?;
+        for entry in &page {
+            while let Some(boundary) = boundaries.next_if(|b| b.ended_at <= entry.log_timestamp) {
+                end_at_boundary(manager, reconciler.as_mut(), boundary)?;
+            }
+
+            let after_end = entry.log_timestamp >= end;
+            if after_end && !manager.has_active_sessions() {
+                break 'pages;
+            }
+
+            let events = match reconciler.as_mut() {
+                Some(reconciler) => reconciler.apply(&entry.subsystem, entry.to_event()),
+                None => vec![entry.to_event()],
+            };
+            for event in events {
+                if let CameraEvent::Started { .. } = event {
+                    if after_end {
+                        continue;
+                    }
+                    started += 1;
+                }
+                apply_event(manager, event)?;
+            }
+            applied += 1;
+        }
+
+        match page.last() {
+            Some(last) if page.len() == JOURNAL_PAGE => {
+                since = last.log_timestamp;
+                after_id = last.id.unwrap_or(after_id);
+            }
+            _ => break,
+        }
+    }
+
+    for boundary in boundaries {
+        end_at_boundary(manager, reconciler.as_mut(), boundary)?;
+    }
+
+    Ok((started, applied))
+}
+
+/// Ends the rebuilt session `boundary` applies to, if it is running.
+fn end_at_boundary(
+    manager: &SessionManager,
+    reconciler: Option<&mut Reconciler>,
+    boundary: &Boundary,
+) -> Result<()> {
+    let session = boundary.session;
+    if manager.end_session_like(session, boundary.resumed_at)?.is_none() {
+        return Ok(());
+    }
+    if let (Some(reconciler), None) = (reconciler, boundary.resumed_at) {
+        reconciler.forget(
+            session.raw_app_name.as_deref().unwrap_or(&session.app_name),
+            session.bundle_id.as_deref(),
+            session.pid,
+            session.sensor,
+        );
+    }
+
+    Ok(())
+}
+
+// ---------------------------------------------------------------------------
+// Tests
+// ---------------------------------------------------------------------------
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::camera::journal::{EventKind, JournaledEvent};
+    use crate::session::SessionStatus;
+    use crate::storage::scratch_db;
+    use std::io::Cursor;
+    use std::path::PathBuf;
+
+    const ZOOM_CAPTURE: &str = include_str!(concat!(
+        env!("CARGO_MANIFEST_DIR"),
+        "/tests/fixtures/cameracapture_zoom.log"
+    ));
+
+    const ZOOM_CAPTURE_NDJSON: &str = include_str!(concat!(
+        env!("CARGO_MANIFEST_DIR"),
+        "/tests/fixtures/cameracapture_zoom.ndjson"
+    ));
+
+    #[test]
+    fn test_replay_uses_capture_timestamps() {
+        let db = scratch_db("replay-timestamps");
+        let manager = SessionManager::new(db.to_path_buf());
+
+        let applied =
+            replay_lines(Cursor::new(ZOOM_CAPTURE), Subsystem::CameraCapture, &manager).unwrap();
+        assert_eq!(applied, 4);
+
+        let mut sessions = manager.get_all_sessions().unwrap();
+        sessions.sort_by_key(|s| s.start_time);
+        assert_eq!(sessions.len(), 2);
+
+        assert_eq!(sessions[0].app_name, "Zoom");
+        assert_eq!(sessions[0].start_time.to_rfc3339(), "2025-06-10T21:23:01.123456+00:00");
+        assert_eq!(sessions[0].duration_secs, Some(120));
+        assert_eq!(sessions[0].status, SessionStatus::Completed);
+
+        assert_eq!(sessions[1].app_name, "FaceTime");
+        assert_eq!(sessions[1].duration_secs, Some(360));
+    }
+
+    #[test]
+    fn test_replay_is_deterministic() {
+        let first = scratch_db("replay-first");
+        let second = scratch_db("replay-second");
+
+        for db in [&first, &second] {
+            let manager = SessionManager::new(db.to_path_buf());
+            replay_lines(Cursor::new(ZOOM_CAPTURE), Subsystem::CameraCapture, &manager).unwrap();
+        }
+
+        let summarize = |db: &PathBuf| -> Vec<(String, String, Option<i64>)> {
+            storage::get_all_sessions(db)
+                .unwrap()
+                .into_iter()
+                .map(|s| (s.app_name, s.start_time.to_rfc3339(), s.duration_secs))
+                .collect()
+        };
+        assert_eq!(summarize(&first), summarize(&second));
+    }
+
+    #[test]
+    fn test_ndjson_capture_matches_syslog_capture() {
+        let syslog = scratch_db("replay-syslog");
+        let ndjson = scratch_db("replay-ndjson");
+
+        for (db, capture) in [(&syslog, ZOOM_CAPTURE), (&ndjson, ZOOM_CAPTURE_NDJSON)] {
+            let manager = SessionManager::new(db.to_path_buf());
+            replay_lines(Cursor::new(capture), Subsystem::CameraCapture, &manager).unwrap();
+        }
+
+        let summarize = |db: &PathBuf| -> Vec<(String, Option<u32>, String, Option<i64>)> {
+            let mut sessions = storage::get_all_sessions(db).unwrap();
+            sessions.sort_by_key(|s| s.start_time);
+            sessions
+                .into_iter()
+                .map(|s| {
+                    (
+                        s.app_name,
+                        s.pid,
+                        s.start_time.to_rfc3339(),
+                        s.duration_secs,
+                    )
+                })
+                .collect()
+        };
+        assert_eq!(summarize(&syslog).len(), 2);
+        assert_eq!(summarize(&syslog), summarize(&ndjson));
+    }
+
+    fn utc(s: &str) -> DateTime<Utc> {
+        DateTime::parse_from_rfc3339(s).unwrap().with_timezone(&Utc)
+    }
+
+    #[test]
+    fn test_rebuild_recomputes_sessions_from_journal() {
+        let db = scratch_db("replay-rebuild");
+        let live = SessionManager::new(db.to_path_buf());
+        let sensor = crate::camera::Sensor::Camera;
+
+        // Recorded before anything was journaled, so never rebuilt
+        live.start_session(
+            "Photo Booth".into(),
+            None,
+            None,
+            sensor,
+            utc("2025-06-01T10:00:00Z"),
+        )
+        .unwrap();
+        live.end_session_for_app(
+            "Photo Booth",
+            None,
+            None,
+            sensor,
+            utc("2025-06-01T10:05:00Z"),
+        )
+        .unwrap();
+
+        // Journal the capture, then corrupt the history it produced
+        let window = crate::camera::backfill::BackfillWindow {
+            start: utc("2025-06-10T00:00:00Z"),
+            end: utc("2025-06-11T00:00:00Z"),
+        };
+        crate::camera::backfill::backfill_lines(
+            Cursor::new(ZOOM_CAPTURE_NDJSON),
+            Subsystem::CameraCapture,
+            &live,
+            window,
+            &mut |_| {},
+        )
+        .unwrap();
+        live.start_session(
+            "Bogus".into(),
+            None,
+            None,
+            sensor,
+            utc("2025-06-10T21:26:00Z"),
+        )
+        .unwrap();
+        live.end_session_for_app("Bogus", None, None, sensor, utc("2025-06-10T21:26:01Z"))
+            .unwrap();
+
+        let summary = rebuild_sessions(
+            &db,
+            utc("2025-05-01T00:00:00Z"),
+            utc("2025-07-01T00:00:00Z"),
+        )
+        .unwrap();
+        assert_eq!(
+            summary,
+            RebuildSummary {
+                removed: 3,
+                rebuilt: 2,
+                events: 4
+            }
+        );
+
+        let mut sessions = storage::get_all_sessions(&db).unwrap();
+        sessions.sort_by_key(|s| s.start_time);
+        let names: Vec<&str> = sessions.iter().map(|s| s.app_name.as_str()).collect();
+        assert_eq!(names, vec!["Photo Booth", "Zoom", "FaceTime"]);
+        assert_eq!(sessions[0].source, SessionSource::Live);
+        assert_eq!(sessions[1].source, SessionSource::Rebuilt);
+        assert_eq!(sessions[1].duration_secs, Some(120));
+        assert_eq!(sessions[2].duration_secs, Some(360));
+    }
+
+    #[test]
+    fn test_rebuild_reconciles_several_subsystems() {
+        let db = scratch_db("replay-rebuild-reconcile");
+        let t0 = utc("2025-06-10T09:00:00Z");
+        let event = |kind, subsystem: &str, secs| JournaledEvent {
+            id: None,
+            kind,
+            app_name: "FaceTime".to_string(),
+            bundle_id: None,
+            pid: None,
+            sensor: crate::camera::Sensor::Camera,
+            subsystem: subsystem.to_string(),
+            log_timestamp: t0 + chrono::Duration::seconds(secs),
+            received_at: Utc::now(),
+            line_hash: Some(format!("{}-{}", subsystem, secs)),
+        };
+        for entry in [
+            event(EventKind::Started, "cameracapture", 0),
+            event(EventKind::Started, "cmio", 1),
+            event(EventKind::Stopped, "cmio", 10),
+            event(EventKind::Stopped, "cameracapture", 20),
+        ] {
+            storage::insert_camera_event(&db, &entry).unwrap();
+        }
+
+        let summary = rebuild_sessions(&db, t0, t0 + chrono::Duration::hours(1)).unwrap();
+        assert_eq!(summary.rebuilt, 1);
+
+        let sessions = storage::get_all_sessions(&db).unwrap();
+        assert_eq!(sessions.len(), 1);
+        assert_eq!(sessions[0].duration_secs, Some(20));
+        assert_eq!(sessions[0].confirmed_by, vec!["cameracapture", "cmio"]);
+    }
+
+    #[test]
+    fn test_rebuild_keeps_ends_missing_from_journal() {
+        let db = scratch_db("replay-rebuild-paused");
+        let t0 = utc("2025-06-10T09:00:00Z");
+        let sensor = crate::camera::Sensor::Camera;
+        let event = |kind, secs| JournaledEvent {
+            id: None,
+            kind,
+            app_name: "FaceTime".to_string(),
+            bundle_id: None,
+            pid: None,
+            sensor,
+            subsystem: "cameracapture".to_string(),
+            log_timestamp: t0 + chrono::Duration::seconds(secs),
+            received_at: Utc::now(),
+            line_hash: Some(secs.to_string()),
+        };
+
+        // Tracking was paused five minutes in; the stop came long after
+        let live = SessionManager::new(db.to_path_buf());
+        storage::insert_camera_event(&db, &event(EventKind::Started, 0)).unwrap();
+        live.start_session("FaceTime".into(), None, None, sensor, t0).unwrap();
+        live.end_all_active_sessions_at(t0 + chrono::Duration::minutes(5), EndReason::Paused)
+            .unwrap();
+        storage::insert_camera_event(&db, &event(EventKind::Stopped, 1200)).unwrap();
+
+        let summary = rebuild_sessions(&db, t0, t0 + chrono::Duration::hours(1)).unwrap();
+        assert_eq!(summary.removed, 1);
+        assert_eq!(summary.rebuilt, 1);
+
+        let sessions = storage::get_all_sessions(&db).unwrap();
+        assert_eq!(sessions.len(), 1);
+        assert_eq!(sessions[0].source, SessionSource::Rebuilt);
+        assert_eq!(sessions[0].end_reason, Some(EndReason::Paused));
+        assert_eq!(sessions[0].duration_secs, Some(300));
+    }
+
+    #[test]
+    fn test_rebuild_does_not_resume_sleep_at_a_journaled_start() {
+        let db = scratch_db("replay-rebuild-sleep");
+        let t0 = utc("2025-06-10T09:00:00Z");
+        let at = |secs| t0 + chrono::Duration::seconds(secs);
+        let sensor = crate::camera::Sensor::Camera;
+        let event = |kind, secs| JournaledEvent {
+            id: None,
+            kind,
+            app_name: "FaceTime".to_string(),
+            bundle_id: None,
+            pid: None,
+            sensor,
+            subsystem: "cameracapture".to_string(),
+            log_timestamp: at(secs),
+            received_at: Utc::now(),
+            line_hash: Some(secs.to_string()),
+        };
+
+        // Slept five minutes in; nothing confirmed the session after wake,
+        // and FaceTime started the camera again later
+        let live = SessionManager::new(db.to_path_buf());
+        storage::insert_camera_event(&db, &event(EventKind::Started, 0)).unwrap();
+        live.start_session("FaceTime".into(), None, None, sensor, t0).unwrap();
+        live.split_at_sleep(at(300), at(900)).unwrap();
+        storage::insert_camera_event(&db, &event(EventKind::Started, 1000)).unwrap();
+        live.start_session("FaceTime".into(), None, None, sensor, at(1000)).unwrap();
+        storage::insert_camera_event(&db, &event(EventKind::Stopped, 1200)).unwrap();
+        live.end_session_for_app("FaceTime", None, None, sensor, at(1200)).unwrap();
+
+        let summary = rebuild_sessions(&db, t0, t0 + chrono::Duration::hours(1)).unwrap();
+        assert_eq!(summary.removed, 2);
+        assert_eq!(summary.rebuilt, 2);
+
+        let mut sessions = storage::get_all_sessions(&db).unwrap();
+        sessions.sort_by_key(|s| s.start_time);
+        assert_eq!(sessions.len(), 2);
+        assert_eq!(sessions[0].end_reason, Some(EndReason::Sleep));
+        assert_eq!(sessions[0].duration_secs, Some(300));
+        assert_eq!(sessions[1].start_time, at(1000));
+        assert_eq!(sessions[1].end_reason, Some(EndReason::Stopped));
+        assert_eq!(sessions[1].duration_secs, Some(200));
+    }
+}
@@ -0,0 +1,212 @@
+use crate::camera::journal::Journal;
+use crate::camera::monitor::CameraEvent;
+use crate::camera::platform_linux::ProcfsSource;
+use crate::camera::platform_macos::{AttributionSnapshot, LogStreamSource, SubsystemSelection};
+use crate::session::aliases::Aliases;
+use anyhow::Result;
+use serde::Serialize;
+use std::path::Path;
+use std::sync::{mpsc, Arc, Mutex};
+
+/// Environment variable that overrides the platform's default camera source.
+pub const SOURCE_ENV_VAR: &str = "WEBCAM_TRACKER_SOURCE";
+
+/// Environment variable choosing which log subsystems the `logstream` source
+/// streams: `auto` (the default), `all`, or a comma-separated list.
+pub const SUBSYSTEMS_ENV_VAR: &str = "WEBCAM_TRACKER_SUBSYSTEMS";
+
+/// Current condition of a `CameraSource`.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+#[serde(tag = "state", content = "detail", rename_all = "lowercase")]
+pub enum SourceHealth {
+    /// Not started, or stopped on request.
+    Stopped,
+    /// Started and still producing events.
+    Running,
+    /// The underlying process died and will be restarted after a delay.
+    Restarting {
+        attempt: u32,
+        retry_in_secs: u64,
+        reason: String,
+    },
+    /// Started, but the underlying process or scanner has died.
+    Failed(String),
+}
+
+/// Records a source's current `SourceHealth` and forwards every transition
+/// to the listener registered with `CameraMonitor::watch_status`, if any.
+#[derive(Debug, Clone)]
+pub struct StatusReporter {
+    current: Arc<Mutex<SourceHealth>>,
+    tx: Option<mpsc::Sender<SourceHealth>>,
+}
+
+impl StatusReporter {
+    pub fn new(tx: Option<mpsc::Sender<SourceHealth>>) -> Self {
+        Self {
+            current: Arc::new(Mutex::new(SourceHealth::Stopped)),
+            tx,
+        }
+    }
+
+    /// Updates the current health, notifying the listener only when it
+    /// actually changed.
+    pub fn report(&self, health: SourceHealth) {
+        let mut current = self.current.lock().unwrap();
+        if *current == health {
+            return;
+        }
+        *current = health.clone();
+        drop(current);
+
+        if let Some(tx) = &self.tx {
+            let _ = tx.send(health);
+        }
+    }
+
+    pub fn current(&self) -> SourceHealth {
+        self.current.lock().unwrap().clone()
+    }
+}
+
+impl Default for StatusReporter {
+    fn default() -> Self {
+        Self::new(None)
+    }
+}
+
+/// A backend that produces `CameraEvent`s.
+///
+/// `CameraMonitor` owns exactly one source and the session loop in `lib.rs`
+/// only ever sees the receiver returned by `start`, so new backends can be
+/// added without touching session handling.
+pub trait CameraSource: Send {
+    /// Short identifier used in logs and for `WEBCAM_TRACKER_SOURCE`.
+    fn name(&self) -> &'static str;
+
+    /// Starts producing events. The receiver disconnects once the source
+    /// stops or dies. Health transitions are reported through `status`.
+    fn start(&mut self, status: StatusReporter) -> Result<mpsc::Receiver<CameraEvent>>;
+
+    /// Stops producing events and releases any child processes or threads.
+    fn stop(&mut self);
+
+    fn health(&self) -> SourceHealth;
+
+    /// The apps the source currently sees attributed to each sensor, for
+    /// sources that report the full set rather than only transitions.
+    fn attributions(&self) -> Option<AttributionSnapshot> {
+        None
+    }
+}
+
+/// The camera sources that can be picked at runtime.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SourceKind {
+    /// `log stream` on the macOS unified log.
+    LogStream,
+    /// Polling `/proc/*/fd` for open `/dev/video*` handles (Linux).
+    Procfs,
+}
+
+impl SourceKind {
+    /// The natural source for the platform we were compiled for.
+    pub fn platform_default() -> Self {
+        if cfg!(target_os = "linux") {
+            SourceKind::Procfs
+        } else {
+            SourceKind::LogStream
+        }
+    }
+}
+
+impl std::str::FromStr for SourceKind {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        match s.trim().to_ascii_lowercase().as_str() {
+            "logstream" | "log-stream" => Ok(SourceKind::LogStream),
+            "procfs" => Ok(SourceKind::Procfs),
+            other => anyhow::bail!("Unknown camera source: {}", other),
+        }
+    }
+}
+
+/// Builds the source to monitor with, honouring `WEBCAM_TRACKER_SOURCE` and
+/// `WEBCAM_TRACKER_SUBSYSTEMS` when they are set and falling back to the
+/// platform defaults otherwise. `db_path` is where sources may cache state
+/// such as the detected log subsystem, and where they journal the events
+/// they parse. Sources that merge several origins match apps by the names
+/// `aliases` give them.
+pub fn select_source(db_path: &Path, aliases: Arc<Mutex<Aliases>>) -> Box<dyn CameraSource> {
+    let kind = match std::env::var(SOURCE_ENV_VAR) {
+        Ok(value) => value.parse().unwrap_or_else(|e| {
+            eprintln!("[webcam-tracker] {}; using platform default", e);
+            SourceKind::platform_default()
+        }),
+        Err(_) => SourceKind::platform_default(),
+    };
+
+    let subsystems = match std::env::var(SUBSYSTEMS_ENV_VAR) {
+        Ok(value) => value.parse().unwrap_or_else(|e| {
+            eprintln!("[webcam-tracker] {}; detecting the subsystem instead", e);
+            SubsystemSelection::Detect
+        }),
+        Err(_) => SubsystemSelection::Detect,
+    };
+
+    build_source(kind, subsystems, db_path, aliases)
+}
+
+pub fn build_source(
+    kind: SourceKind,
+    subsystems: SubsystemSelection,
+    db_path: &Path,
+    aliases: Arc<Mutex<Aliases>>,
+) -> Box<dyn CameraSource> {
+    let journal = Journal::new(db_path.to_path_buf());
+    match kind {
+        SourceKind::LogStream => Box::new(
+            LogStreamSource::new(subsystems, Some(db_path.to_path_buf()))
+                .with_journal(journal)
+                .with_aliases(aliases),
+        ),
+        SourceKind::Procfs => Box::new(ProcfsSource::default().with_journal(journal)),
+    }
+}
+
+// ---------------------------------------------------------------------------
+// Tests
+// ---------------------------------------------------------------------------
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_reporter_only_forwards_transitions() {
+        let (tx, rx) = mpsc::channel();
+        let reporter = StatusReporter::new(Some(tx));
+
+        reporter.report(SourceHealth::Running);
+        reporter.report(SourceHealth::Running);
+        reporter.report(SourceHealth::Stopped);
+
+        let received: Vec<SourceHealth> = rx.try_iter().collect();
+        assert_eq!(received, vec![SourceHealth::Running, SourceHealth::Stopped]);
+        assert_eq!(reporter.current(), SourceHealth::Stopped);
+    }
+
+    #[test]
+    fn test_restarting_serializes_with_detail() {
+        let health = SourceHealth::Restarting {
+            attempt: 2,
+            retry_in_secs: 4,
+            reason: "log stream exited".to_string(),
+        };
+        let json = serde_json::to_value(&health).unwrap();
+        assert_eq!(json["state"], "restarting");
+        assert_eq!(json["detail"]["attempt"], 2);
+        assert_eq!(json["detail"]["retry_in_secs"], 4);
+    }
+}
@@ -1,18 +1,39 @@
+use crate::camera::backfill;
+use crate::camera::journal::JournaledEvent;
+use crate::camera::replay::RebuildSummary;
+use crate::camera::platform_macos::{self, Detection};
+use crate::camera::CameraMonitor;
 use crate::export::export_sessions_to_csv;
+use crate::notifications::{self, NotifyRule, NotifyRuleEntry, QuietHours};
+use crate::session::aliases::{default_rules, AliasMatch, AliasRule};
+use crate::session::policy::{AppPolicy, AppPolicyEntry};
 use crate::session::{Session, SessionManager, SessionStatus};
+use crate::storage;
 use anyhow::Result;
+use chrono::{DateTime, Utc};
 use serde::{Deserialize, Serialize};
-use std::sync::Mutex;
-use tauri::{AppHandle, Manager, State};
+use std::sync::{Arc, Mutex};
+use tauri::{AppHandle, Emitter, State};
 
-#[derive(Debug, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct SessionDto {
     pub id: Option<i64>,
     pub app_name: String,
+    pub bundle_id: Option<String>,
+    pub pid: Option<u32>,
+    pub sensor: String,
+    pub confirmed_by: Vec<String>,
+    pub source: String,
     pub start_time: String,
     pub end_time: Option<String>,
     pub duration_secs: Option<i64>,
     pub status: String,
+    pub end_reason: Option<String>,
+    /// Whether the session was cut short by something other than the app
+    /// stopping (pause, shutdown, crash, ...), so its duration is not real.
+    pub truncated: bool,
+    pub merged_interruptions: u32,
+    pub screen_locked: bool,
 }
 
 impl From<Session> for SessionDto {
@@ -20,6 +41,11 @@ impl From<Session> for SessionDto {
         Self {
             id: session.id,
             app_name: session.app_name,
+            bundle_id: session.bundle_id,
+            pid: session.pid,
+            sensor: session.sensor.to_string(),
+            confirmed_by: session.confirmed_by,
+            source: session.source.to_string(),
             start_time: session.start_time.to_rfc3339(),
             end_time: session.end_time.map(|t| t.to_rfc3339()),
             duration_secs: session.duration_secs,
@@ -27,6 +53,41 @@ impl From<Session> for SessionDto {
                 SessionStatus::Running => "running".to_string(),
                 SessionStatus::Completed => "completed".to_string(),
             },
+            end_reason: session.end_reason.map(|r| r.to_string()),
+            truncated: session.end_reason.is_some_and(|r| r.is_truncated()),
+            merged_interruptions: session.merged_interruptions,
+            screen_locked: session.screen_locked,
+        }
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct CameraEventDto {
+    pub id: Option<i64>,
+    pub kind: String,
+    pub app_name: String,
+    pub bundle_id: Option<String>,
+    pub pid: Option<u32>,
+    pub sensor: String,
+    pub subsystem: String,
+    pub log_timestamp: String,
+    pub received_at: String,
+    pub line_hash: Option<String>,
+}
+
+impl From<JournaledEvent> for CameraEventDto {
+    fn from(event: JournaledEvent) -> Self {
+        Self {
+            id: event.id,
+            kind: event.kind.to_string(),
+            app_name: event.app_name,
+            bundle_id: event.bundle_id,
+            pid: event.pid,
+            sensor: event.sensor.to_string(),
+            subsystem: event.subsystem,
+            log_timestamp: event.log_timestamp.to_rfc3339(),
+            received_at: event.received_at.to_rfc3339(),
+            line_hash: event.line_hash,
         }
     }
 }
@@ -38,12 +99,208 @@ pub fn get_sessions(session_manager: State<'_, Mutex<SessionManager>>) -> Result
     Ok(sessions.into_iter().map(SessionDto::from).collect())
 }
 
+/// Returns the journaled camera events behind a session, oldest first.
+#[tauri::command]
+pub fn get_session_events(
+    session_manager: State<'_, Mutex<SessionManager>>,
+    session_id: i64,
+) -> Result<Vec<CameraEventDto>, String> {
+    let manager = session_manager.lock().map_err(|e| e.to_string())?;
+    let events = manager
+        .get_session_events(session_id)
+        .map_err(|e| e.to_string())?;
+    Ok(events.into_iter().map(CameraEventDto::from).collect())
+}
+
 #[tauri::command]
 pub fn get_tracking_status(session_manager: State<'_, Mutex<SessionManager>>) -> Result<bool, String> {
     let manager = session_manager.lock().map_err(|e| e.to_string())?;
     Ok(manager.is_tracking())
 }
 
+/// Seconds within which a stop and a start of the same app are merged into
+/// one session.
+#[tauri::command]
+pub fn get_grace_period(session_manager: State<'_, Mutex<SessionManager>>) -> Result<i64, String> {
+    let manager = session_manager.lock().map_err(|e| e.to_string())?;
+    Ok(manager.grace_period_secs())
+}
+
+#[tauri::command]
+pub fn set_grace_period(
+    session_manager: State<'_, Mutex<SessionManager>>,
+    secs: i64,
+) -> Result<(), String> {
+    let manager = session_manager.lock().map_err(|e| e.to_string())?;
+    manager.set_grace_period(secs).map_err(|e| e.to_string())
+}
+
+/// Returns the user's alias rules, in the order they are applied.
+#[tauri::command]
+pub fn get_alias_rules(
+    session_manager: State<'_, Mutex<SessionManager>>,
+) -> Result<Vec<AliasRule>, String> {
+    let manager = session_manager.lock().map_err(|e| e.to_string())?;
+    manager.alias_rules().map_err(|e| e.to_string())
+}
+
+/// Returns the built-in alias rules, applied after the user's.
+#[tauri::command]
+pub fn get_default_alias_rules() -> Result<Vec<AliasRule>, String> {
+    Ok(default_rules())
+}
+
+/// Adds an alias rule and renames recorded sessions to match.
+/// `kind` is one of `exact`, `prefix`, `regex` or `bundle_id`.
+#[tauri::command]
+pub fn add_alias_rule(
+    app: AppHandle,
+    session_manager: State<'_, Mutex<SessionManager>>,
+    kind: String,
+    pattern: String,
+    app_name: String,
+) -> Result<i64, String> {
+    let kind: AliasMatch = kind.parse().map_err(|e: anyhow::Error| e.to_string())?;
+    let rule = AliasRule::new(kind, &pattern, &app_name);
+    let manager = session_manager.lock().map_err(|e| e.to_string())?;
+    let id = manager.add_alias_rule(&rule).map_err(|e| format!("{:#}", e))?;
+    let _ = app.emit("session-updated", ());
+    Ok(id)
+}
+
+#[tauri::command]
+pub fn delete_alias_rule(
+    app: AppHandle,
+    session_manager: State<'_, Mutex<SessionManager>>,
+    id: i64,
+) -> Result<bool, String> {
+    let manager = session_manager.lock().map_err(|e| e.to_string())?;
+    let removed = manager.remove_alias_rule(id).map_err(|e| e.to_string())?;
+    if removed {
+        let _ = app.emit("session-updated", ());
+    }
+    Ok(removed)
+}
+
+/// Returns every app with a policy set.
+#[tauri::command]
+pub fn get_app_policies(
+    session_manager: State<'_, Mutex<SessionManager>>,
+) -> Result<Vec<AppPolicyEntry>, String> {
+    let manager = session_manager.lock().map_err(|e| e.to_string())?;
+    manager.app_policies().map_err(|e| e.to_string())
+}
+
+/// Sets the policy for the app recorded as `app_name`.
+/// `policy` is one of `allow`, `ignore` or `flag`.
+#[tauri::command]
+pub fn set_app_policy(
+    session_manager: State<'_, Mutex<SessionManager>>,
+    app_name: String,
+    policy: String,
+) -> Result<(), String> {
+    let policy: AppPolicy = policy.parse().map_err(|e: anyhow::Error| e.to_string())?;
+    let manager = session_manager.lock().map_err(|e| e.to_string())?;
+    manager
+        .set_app_policy(&app_name, policy)
+        .map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+pub fn clear_app_policy(
+    session_manager: State<'_, Mutex<SessionManager>>,
+    app_name: String,
+) -> Result<bool, String> {
+    let manager = session_manager.lock().map_err(|e| e.to_string())?;
+    manager.clear_app_policy(&app_name).map_err(|e| e.to_string())
+}
+
+/// Returns every per-app notification rule.
+#[tauri::command]
+pub fn get_notification_rules(
+    session_manager: State<'_, Mutex<SessionManager>>,
+) -> Result<Vec<NotifyRuleEntry>, String> {
+    let manager = session_manager.lock().map_err(|e| e.to_string())?;
+    storage::get_notify_rules(manager.db_path()).map_err(|e| e.to_string())
+}
+
+/// Sets when the app recorded as `app_name` raises notifications.
+/// `rule` is one of `always`, `first_time`, `flagged_only` or `never`.
+#[tauri::command]
+pub fn set_notification_rule(
+    session_manager: State<'_, Mutex<SessionManager>>,
+    app_name: String,
+    rule: String,
+) -> Result<(), String> {
+    let rule: NotifyRule = rule.parse().map_err(|e: anyhow::Error| e.to_string())?;
+    let app_name = app_name.trim();
+    if app_name.is_empty() {
+        return Err("Notification rules need an app name".to_string());
+    }
+    let manager = session_manager.lock().map_err(|e| e.to_string())?;
+    storage::set_notify_rule(manager.db_path(), app_name, rule).map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+pub fn clear_notification_rule(
+    session_manager: State<'_, Mutex<SessionManager>>,
+    app_name: String,
+) -> Result<bool, String> {
+    let manager = session_manager.lock().map_err(|e| e.to_string())?;
+    storage::delete_notify_rule(manager.db_path(), app_name.trim()).map_err(|e| e.to_string())
+}
+
+/// The notification rule for apps without one of their own.
+#[tauri::command]
+pub fn get_default_notification_rule(
+    session_manager: State<'_, Mutex<SessionManager>>,
+) -> Result<NotifyRule, String> {
+    let manager = session_manager.lock().map_err(|e| e.to_string())?;
+    notifications::default_rule(manager.db_path()).map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+pub fn set_default_notification_rule(
+    session_manager: State<'_, Mutex<SessionManager>>,
+    rule: String,
+) -> Result<(), String> {
+    let rule: NotifyRule = rule.parse().map_err(|e: anyhow::Error| e.to_string())?;
+    let manager = session_manager.lock().map_err(|e| e.to_string())?;
+    notifications::set_default_rule(manager.db_path(), rule).map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+pub fn get_quiet_hours(
+    session_manager: State<'_, Mutex<SessionManager>>,
+) -> Result<Vec<QuietHours>, String> {
+    let manager = session_manager.lock().map_err(|e| e.to_string())?;
+    storage::get_quiet_hours(manager.db_path()).map_err(|e| e.to_string())
+}
+
+/// Adds a weekly quiet hours window and returns its ID. `days` is a
+/// comma-separated list such as `mon,wed` (empty for every day), and
+/// `start`/`end` are local `HH:MM` times.
+#[tauri::command]
+pub fn add_quiet_hours(
+    session_manager: State<'_, Mutex<SessionManager>>,
+    days: String,
+    start: String,
+    end: String,
+) -> Result<i64, String> {
+    let window = QuietHours::parse(&days, &start, &end).map_err(|e| format!("{:#}", e))?;
+    let manager = session_manager.lock().map_err(|e| e.to_string())?;
+    storage::insert_quiet_hours(manager.db_path(), &window).map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+pub fn delete_quiet_hours(
+    session_manager: State<'_, Mutex<SessionManager>>,
+    id: i64,
+) -> Result<bool, String> {
+    let manager = session_manager.lock().map_err(|e| e.to_string())?;
+    storage::delete_quiet_hours(manager.db_path(), id).map_err(|e| e.to_string())
+}
+
 #[tauri::command]
 pub fn pause_tracking(session_manager: State<'_, Mutex<SessionManager>>) -> Result<(), String> {
     let manager = session_manager.lock().map_err(|e| e.to_string())?;
@@ -97,6 +354,110 @@ pub async fn export_csv(
     Ok(())
 }
 
+/// Replays a saved `log stream` capture (ndjson or syslog) into a scratch
+/// database and returns the sessions it produced. The live database is
+/// never touched.
+#[tauri::command]
+pub fn replay_log_capture(path: String, subsystem: String) -> Result<Vec<SessionDto>, String> {
+    let subsystem = subsystem
+        .parse::<platform_macos::Subsystem>()
+        .map_err(|e| e.to_string())?;
+    let scratch_db = std::env::temp_dir().join("webcam-tracker-replay.db");
+
+    let sessions = crate::camera::replay::replay_capture(
+        std::path::Path::new(&path),
+        subsystem,
+        &scratch_db,
+    )
+    .map_err(|e| e.to_string())?;
+
+    Ok(sessions.into_iter().map(SessionDto::from).collect())
+}
+
+/// Drops the sessions that started between `start` and `end` (RFC 3339) and
+/// recomputes them from the event journal with the current session rules.
+#[tauri::command]
+pub fn rebuild_sessions(
+    app: AppHandle,
+    session_manager: State<'_, Mutex<SessionManager>>,
+    start: String,
+    end: String,
+) -> Result<RebuildSummary, String> {
+    let parse = |s: &str| {
+        DateTime::parse_from_rfc3339(s)
+            .map(|t| t.with_timezone(&Utc))
+            .map_err(|e| format!("Invalid timestamp {}: {}", s, e))
+    };
+    let (start, end) = (parse(&start)?, parse(&end)?);
+
+    // Held throughout so no live session starts while the range is rebuilt
+    let manager = session_manager.lock().map_err(|e| e.to_string())?;
+    let summary = crate::camera::replay::rebuild_sessions(manager.db_path(), start, end)
+        .map_err(|e| e.to_string())?;
+    drop(manager);
+
+    let _ = app.emit("session-updated", ());
+    Ok(summary)
+}
+
+/// Probes every log subsystem again, ignoring the cached detection, and
+/// returns what each probe found. If a different subsystem wins, the camera
+/// source is restarted so it streams the new one.
+#[tauri::command]
+pub fn redetect_subsystem(
+    session_manager: State<'_, Mutex<SessionManager>>,
+    camera_monitor: State<'_, Arc<Mutex<CameraMonitor>>>,
+) -> Result<Detection, String> {
+    let db_path = {
+        let manager = session_manager.lock().map_err(|e| e.to_string())?;
+        manager.db_path().to_path_buf()
+    };
+
+    let previous = platform_macos::cached_detection(&db_path).map(|(subsystem, _)| subsystem);
+    let detection = platform_macos::redetect(&db_path, platform_macos::current_os_build());
+
+    if previous != Some(detection.subsystem) {
+        let mut monitor = camera_monitor.lock().map_err(|e| e.to_string())?;
+        if monitor.is_running() {
+            // The monitoring loop starts it again with the new subsystem
+            monitor.stop();
+        }
+    }
+
+    Ok(detection)
+}
+
+/// Starts reconstructing the last `days` days (7 by default) of sessions from
+/// the unified log in the background. Progress is reported through
+/// `backfill-progress` events.
+#[tauri::command]
+pub fn start_backfill(
+    app: AppHandle,
+    session_manager: State<'_, Mutex<SessionManager>>,
+    days: Option<i64>,
+) -> Result<(), String> {
+    if backfill::is_running() {
+        return Err("A backfill is already running".to_string());
+    }
+
+    let db_path = {
+        let manager = session_manager.lock().map_err(|e| e.to_string())?;
+        manager.db_path().to_path_buf()
+    };
+    let days = days.unwrap_or(backfill::DEFAULT_BACKFILL_DAYS);
+
+    std::thread::spawn(move || {
+        let result = backfill::run_backfill(&db_path, days, |progress| {
+            let _ = app.emit("backfill-progress", progress);
+        });
+        if result.is_ok() {
+            let _ = app.emit("session-updated", ());
+        }
+    });
+
+    Ok(())
+}
+
 #[tauri::command]
 pub fn check_consent() -> Result<bool, String> {
     // Check if user has previously consented
@@ -150,8 +511,11 @@ pub fn check_log_access() -> Result<bool, String> {
 }
 
 #[tauri::command]
-pub fn get_app_icon(app_name: String) -> Result<Option<String>, String> {
-    match crate::icons::get_icon_data_url(&app_name) {
+pub fn get_app_icon(
+    app_name: String,
+    bundle_id: Option<String>,
+) -> Result<Option<String>, String> {
+    match crate::icons::get_icon_data_url(&app_name, bundle_id.as_deref()) {
         Ok(data_url) => Ok(Some(data_url)),
         Err(_) => Ok(None),
     }
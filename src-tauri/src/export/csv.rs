@@ -7,7 +7,7 @@ pub fn export_sessions_to_csv(sessions: &[Session], path: &str) -> Result<()> {
     let mut file = File::create(path)?;
     
     // Write header
-    writeln!(file, "App Name,Start Time,End Time,Duration (seconds),Status")?;
+    writeln!(file, "App Name,Bundle ID,PID,Sensor,Confirmed By,Source,Start Time,End Time,Duration (seconds),Status,End Reason,Merged Interruptions,Screen Locked")?;
     
     // Write rows
     for session in sessions {
@@ -28,7 +28,20 @@ pub fn export_sessions_to_csv(sessions: &[Session], path: &str) -> Result<()> {
         // Escape commas in app name
         let app_name = session.app_name.replace(',', ";");
         
-        writeln!(file, "{},{},{},{},{}", app_name, start_time, end_time, duration, status)?;
+        writeln!(
+            file,
+            "{},{},{},{},{},{},{},{},{},{},{},{},{}",
+            app_name,
+            session.bundle_id.as_deref().unwrap_or(""),
+            session.pid.map(|p| p.to_string()).unwrap_or_default(),
+            session.sensor,
+            session.confirmed_by.join(";"),
+            session.source,
+            start_time, end_time, duration, status,
+            session.end_reason.map(|r| r.to_string()).unwrap_or_default(),
+            session.merged_interruptions,
+            session.screen_locked
+        )?;
     }
     
     Ok(())
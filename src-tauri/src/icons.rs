@@ -3,11 +3,16 @@ use base64::Engine;
 use std::path::{Path, PathBuf};
 use std::process::Command;
 
-/// Resolves the app icon for a given process name and returns it as a
-/// base64-encoded PNG data URL (e.g. `data:image/png;base64,...`).
-pub fn get_icon_data_url(process_name: &str) -> Result<String> {
-    let app_path =
-        find_app_bundle(process_name).context("Could not find app bundle")?;
+/// Resolves the app icon for a given app and returns it as a base64-encoded
+/// PNG data URL (e.g. `data:image/png;base64,...`).
+///
+/// When a bundle ID is known the bundle is looked up by ID, which is exact;
+/// otherwise we fall back to guessing from the process name.
+pub fn get_icon_data_url(process_name: &str, bundle_id: Option<&str>) -> Result<String> {
+    let app_path = bundle_id
+        .and_then(find_bundle_by_id)
+        .or_else(|| find_app_bundle(process_name))
+        .context("Could not find app bundle")?;
 
     let icon_path =
         find_icon_file(&app_path).context("Could not find icon file in app bundle")?;
@@ -45,15 +50,35 @@ fn find_app_bundle(process_name: &str) -> Option<PathBuf> {
     candidates.into_iter().find(|p| p.exists())
 }
 
+/// Locates the `.app` bundle with the given `CFBundleIdentifier` via
+/// Spotlight.
+fn find_bundle_by_id(bundle_id: &str) -> Option<PathBuf> {
+    // Bundle IDs are reverse-DNS; anything else would break out of the query
+    if !bundle_id
+        .chars()
+        .all(|c| c.is_ascii_alphanumeric() || c == '.' || c == '-' || c == '_')
+    {
+        return None;
+    }
+
+    spotlight_first_match(&format!(
+        "kMDItemContentType == 'com.apple.application-bundle' && kMDItemCFBundleIdentifier == '{}'",
+        bundle_id
+    ))
+}
+
 /// Uses Spotlight to find an app bundle by name.
 fn find_via_spotlight(process_name: &str) -> Option<PathBuf> {
-    let query = format!(
+    spotlight_first_match(&format!(
         "kMDItemContentType == 'com.apple.application-bundle' && kMDItemFSName == '{}.app'",
         process_name
-    );
+    ))
+}
 
+/// Runs an `mdfind` query and returns the first existing path it prints.
+fn spotlight_first_match(query: &str) -> Option<PathBuf> {
     let output = Command::new("mdfind")
-        .arg(&query)
+        .arg(query)
         .output()
         .ok()?;
 
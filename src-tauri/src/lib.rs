@@ -2,16 +2,29 @@ mod camera;
 mod commands;
 mod export;
 mod icons;
+mod notifications;
 mod session;
 mod storage;
 
 use std::sync::{Arc, Mutex};
 use tauri::{Emitter, Manager};
 
+/// How often the tracker and its running sessions record that they are
+/// still alive.
+const HEARTBEAT_INTERVAL: std::time::Duration = std::time::Duration::from_secs(30);
+
+/// How often running sessions are checked against the processes (and
+/// ControlCenter attributions) that are actually still there.
+const LIVENESS_INTERVAL: std::time::Duration = std::time::Duration::from_secs(60);
+
+/// How often sleeps and screen lock changes are looked for.
+const POWER_POLL_INTERVAL: std::time::Duration = std::time::Duration::from_secs(10);
+
 pub fn run() {
     tauri::Builder::default()
         .plugin(tauri_plugin_dialog::init())
         .plugin(tauri_plugin_shell::init())
+        .plugin(tauri_plugin_notification::init())
         .setup(|app| {
             // Initialize storage
             let app_data_dir = app.path().app_data_dir().unwrap();
@@ -22,21 +35,157 @@ pub fn run() {
             // Initialize session manager
             let session_manager = session::SessionManager::new(db_path.clone());
             
-            // Recover orphaned sessions on startup
-            session_manager.recover_orphaned_sessions()?;
+            // Read what the previous run left behind before this one starts
+            // writing heartbeats
+            let last_alive = session_manager.last_alive()?;
+            let orphans = session_manager.get_active_sessions()?;
             
             // Initialize camera monitor
-            let camera_monitor = Arc::new(Mutex::new(camera::CameraMonitor::new()));
+            let mut monitor = camera::CameraMonitor::new();
+            let status_rx = monitor.watch_status();
+            let camera_monitor = Arc::new(Mutex::new(monitor));
             
             // Store in app state (wrapped in Mutex for thread safety)
             app.manage(Mutex::new(session_manager));
             app.manage(camera_monitor.clone());
             
+            // Forward source health transitions (running, restarting, ...) to the UI
+            let status_handle = app.handle().clone();
+            std::thread::spawn(move || {
+                for status in status_rx {
+                    let _ = status_handle.emit("monitor-status", status);
+                }
+            });
+
+            // Recover sessions left running, or missed, while we were not running
+            let recovery_handle = app.handle().clone();
+            std::thread::spawn(move || {
+                let live = recovery_handle.state::<Mutex<session::SessionManager>>();
+                match camera::backfill::recover_downtime(&live, &orphans, last_alive, chrono::Utc::now()) {
+                    Ok(sessions) if sessions > 0 => {
+                        eprintln!("[webcam-tracker] Recovered {} sessions from downtime", sessions);
+                    }
+                    Ok(_) => {}
+                    Err(e) => eprintln!("[webcam-tracker] Downtime recovery failed: {:#}", e),
+                }
+                let _ = recovery_handle.emit("session-updated", ());
+            });
+
+            // Heartbeat, so the next launch knows when this run and its
+            // sessions were last seen
+            let alive_handle = app.handle().clone();
+            std::thread::spawn(move || loop {
+                if let Some(session_manager) = alive_handle.try_state::<Mutex<session::SessionManager>>() {
+                    if let Ok(manager) = session_manager.lock() {
+                        let _ = manager.heartbeat();
+                    }
+                }
+                std::thread::sleep(HEARTBEAT_INTERVAL);
+            });
+
+            // End sessions whose app went away without a stop being logged,
+            // or whose starts no stop has balanced for too long, and resume
+            // those a sleep ended that ControlCenter still lists after wake
+            let liveness_handle = app.handle().clone();
+            let liveness_monitor = camera_monitor.clone();
+            std::thread::spawn(move || loop {
+                std::thread::sleep(LIVENESS_INTERVAL);
+                let processes = match session::liveness::ProcessSnapshot::capture() {
+                    Ok(processes) => Some(processes),
+                    Err(e) => {
+                        eprintln!("[webcam-tracker] Failed to list processes: {:#}", e);
+                        None
+                    }
+                };
+                let attributions = liveness_monitor.lock().unwrap().attributions();
+
+                if let Some(session_manager) = liveness_handle.try_state::<Mutex<session::SessionManager>>() {
+                    let ended = match session_manager.lock() {
+                        Ok(manager) => {
+                            let now = chrono::Utc::now();
+                            match manager.resume_slept_sessions(attributions.as_ref()) {
+                                Ok(0) => {}
+                                Ok(_) => {
+                                    let _ = liveness_handle.emit("session-updated", ());
+                                }
+                                Err(e) => eprintln!("[webcam-tracker] Failed to resume sessions after wake: {:#}", e),
+                            }
+                            manager
+                                .end_stale_sessions(processes.as_ref(), attributions.as_ref(), now)
+                                .and_then(|stale| Ok(stale + manager.end_timed_out_sessions(now)?.len()))
+                        }
+                        Err(_) => continue,
+                    };
+                    match ended {
+                        Ok(0) => {}
+                        Ok(_) => {
+                            let _ = liveness_handle.emit("session-ended", ());
+                            let _ = liveness_handle.emit("session-updated", ());
+                        }
+                        Err(e) => eprintln!("[webcam-tracker] Liveness check failed: {:#}", e),
+                    }
+                }
+            });
+
+            // Split sessions at sleep and flag those running while the screen is locked
+            let power_handle = app.handle().clone();
+            std::thread::spawn(move || {
+                let mut watcher = camera::power::PowerWatcher::new();
+                loop {
+                    std::thread::sleep(POWER_POLL_INTERVAL);
+                    let events = watcher.poll();
+                    if events.is_empty() {
+                        continue;
+                    }
+
+                    if let Some(session_manager) = power_handle.try_state::<Mutex<session::SessionManager>>() {
+                        if let Ok(manager) = session_manager.lock() {
+                            for event in events {
+                                let result = match event {
+                                    camera::power::PowerEvent::Slept { slept_at, woke_at } => {
+                                        eprintln!("[webcam-tracker] Slept from {} to {}", slept_at, woke_at);
+                                        manager.split_at_sleep(slept_at, woke_at).map(|_| ())
+                                    }
+                                    camera::power::PowerEvent::ScreenLocked => manager.set_screen_locked(true),
+                                    camera::power::PowerEvent::ScreenUnlocked => manager.set_screen_locked(false),
+                                };
+                                if let Err(e) = result {
+                                    eprintln!("[webcam-tracker] Failed to apply power event: {:#}", e);
+                                }
+                            }
+                        }
+                    }
+                    let _ = power_handle.emit("session-updated", ());
+                }
+            });
+
+            // Reconstruct recent history from the unified log on first launch
+            let backfill_handle = app.handle().clone();
+            let backfill_db_path = db_path.clone();
+            std::thread::spawn(move || {
+                let result = camera::backfill::backfill_on_first_launch(&backfill_db_path, |progress| {
+                    let _ = backfill_handle.emit("backfill-progress", progress);
+                });
+                match result {
+                    Ok(()) => {
+                        let _ = backfill_handle.emit("session-updated", ());
+                    }
+                    Err(e) => eprintln!("[webcam-tracker] Backfill failed: {:#}", e),
+                }
+            });
+
+            // Notify about stops once their grace period has passed
+            let (stop_notices, pending_stops) = std::sync::mpsc::channel();
+            let notifier_handle = app.handle().clone();
+            std::thread::spawn(move || {
+                notify_stops_after_grace(notifier_handle, pending_stops);
+            });
+
             // Start camera monitoring in background
             let app_handle = app.handle().clone();
             let db_path_clone = db_path.clone();
             std::thread::spawn(move || {
-                start_camera_monitoring(app_handle, db_path_clone, camera_monitor);
+                start_camera_monitoring(app_handle, db_path_clone, camera_monitor, stop_notices);
             });
 
             Ok(())
@@ -46,17 +195,40 @@ pub fn run() {
                 // Graceful shutdown - close all active sessions
                 if let Some(session_manager) = window.try_state::<Mutex<session::SessionManager>>() {
                     if let Ok(manager) = session_manager.lock() {
-                        let _ = manager.end_all_active_sessions();
+                        let _ = manager.end_all_active_sessions(session::EndReason::Shutdown);
+                        let _ = manager.heartbeat();
                     }
                 }
             }
         })
         .invoke_handler(tauri::generate_handler![
             commands::get_sessions,
+            commands::get_session_events,
             commands::get_tracking_status,
+            commands::get_grace_period,
+            commands::set_grace_period,
+            commands::get_alias_rules,
+            commands::get_default_alias_rules,
+            commands::add_alias_rule,
+            commands::delete_alias_rule,
+            commands::get_app_policies,
+            commands::set_app_policy,
+            commands::clear_app_policy,
+            commands::get_notification_rules,
+            commands::set_notification_rule,
+            commands::clear_notification_rule,
+            commands::get_default_notification_rule,
+            commands::set_default_notification_rule,
+            commands::get_quiet_hours,
+            commands::add_quiet_hours,
+            commands::delete_quiet_hours,
             commands::pause_tracking,
             commands::resume_tracking,
             commands::export_csv,
+            commands::replay_log_capture,
+            commands::rebuild_sessions,
+            commands::redetect_subsystem,
+            commands::start_backfill,
             commands::check_consent,
             commands::grant_consent,
             commands::check_log_access,
@@ -69,9 +241,15 @@ pub fn run() {
 
 fn start_camera_monitoring(
     app: tauri::AppHandle,
-    _db_path: std::path::PathBuf,
+    db_path: std::path::PathBuf,
     camera_monitor: Arc<Mutex<camera::CameraMonitor>>,
+    stop_notices: std::sync::mpsc::Sender<PendingStop>,
 ) {
+    // Pick the event source once; the session loop below is source-agnostic.
+    // It matches app names by the same alias rules sessions are named by
+    let aliases = app.state::<Mutex<session::SessionManager>>().lock().unwrap().aliases();
+    camera_monitor.lock().unwrap().set_source(camera::select_source(&db_path, aliases));
+
     loop {
         // Check if tracking is enabled
         let is_tracking = {
@@ -114,23 +292,48 @@ fn start_camera_monitoring(
                         
                         if let Some(session_manager) = app.try_state::<Mutex<session::SessionManager>>() {
                             match event {
-                                camera::CameraEvent::Started { app_name, .. } => {
+                                camera::CameraEvent::Started { app_name, bundle_id, pid, sensor, timestamp } => {
                                     let manager = session_manager.lock().unwrap();
-                                    if let Ok(session_id) = manager.start_session(app_name) {
+                                    let policy = manager.app_policy(&app_name, bundle_id.as_deref()).unwrap_or_default();
+                                    if policy == session::policy::AppPolicy::Ignore {
+                                        continue;
+                                    }
+                                    if let Ok(session_id) = manager.start_session(app_name, bundle_id, pid, sensor, timestamp) {
+                                        // Nested starts and quick restarts continue a session
+                                        // that has already been notified about
+                                        let started = storage::get_session(manager.db_path(), session_id)
+                                            .ok()
+                                            .flatten()
+                                            .filter(|session| session.start_time == timestamp);
+                                        if let Some(session) = &started {
+                                            notifications::notify_session(&app, &manager, session, notifications::SessionChange::Started);
+                                        }
                                         drop(manager);
                                         let _ = app.emit("session-started", session_id);
                                         let _ = app.emit("session-updated", ());
+                                        if let (Some(session), session::policy::AppPolicy::Flag) = (started, policy) {
+                                            let _ = app.emit("app-flagged", commands::SessionDto::from(session));
+                                        }
                                     }
                                 }
-                                camera::CameraEvent::Stopped { app_name, .. } => {
+                                camera::CameraEvent::Stopped { app_name, bundle_id, pid, sensor, timestamp } => {
                                     let manager = session_manager.lock().unwrap();
-                                    match manager.end_session_for_app(&app_name) {
-                                        Ok(true) => {
-                                            drop(manager);
-                                            let _ = app.emit("session-ended", ());
-                                            let _ = app.emit("session-updated", ());
-                                        }
-                                        _ => {}
+                                    let ended = manager
+                                        .end_session_for_app(&app_name, bundle_id.as_deref(), pid, sensor, timestamp)
+                                        .unwrap_or_default();
+                                    if !ended.is_empty() {
+                                        let grace_secs = manager.grace_period_secs();
+                                        drop(manager);
+                                        let _ = stop_notices.send(PendingStop::new(ended, timestamp, grace_secs));
+                                        let _ = app.emit("session-ended", ());
+                                        let _ = app.emit("session-updated", ());
+                                    }
+                                }
+                                camera::CameraEvent::Confirmed { app_name, bundle_id, pid, sensor, source } => {
+                                    let manager = session_manager.lock().unwrap();
+                                    if let Ok(true) = manager.confirm_session(&app_name, bundle_id.as_deref(), pid, sensor, &source) {
+                                        drop(manager);
+                                        let _ = app.emit("session-updated", ());
                                     }
                                 }
                             }
@@ -139,6 +342,13 @@ fn start_camera_monitoring(
                     
                     // Stop monitor when loop exits
                     let mut monitor = camera_monitor.lock().unwrap();
+                    if let camera::SourceHealth::Failed(reason) = monitor.health() {
+                        eprintln!(
+                            "[webcam-tracker] Camera source {} failed: {}",
+                            monitor.source_name().unwrap_or("unknown"),
+                            reason
+                        );
+                    }
                     monitor.stop();
                 }
             } else {
@@ -157,3 +367,61 @@ fn start_camera_monitoring(
     }
 }
 
+/// Sessions a stop ended, to be notified about once the grace period has
+/// passed without a restart continuing them.
+struct PendingStop {
+    session_ids: Vec<i64>,
+    ended_at: chrono::DateTime<chrono::Utc>,
+    due: std::time::Instant,
+}
+
+impl PendingStop {
+    fn new(session_ids: Vec<i64>, ended_at: chrono::DateTime<chrono::Utc>, grace_secs: i64) -> Self {
+        // A second more, for the restart's event to arrive
+        let wait = std::time::Duration::from_secs(grace_secs.max(0) as u64 + 1);
+        Self { session_ids, ended_at, due: std::time::Instant::now() + wait }
+    }
+}
+
+/// Notifies about the stops received on `stops` as each comes due, on this
+/// one thread however many stops are waiting.
+fn notify_stops_after_grace(app: tauri::AppHandle, stops: std::sync::mpsc::Receiver<PendingStop>) {
+    let mut pending: Vec<PendingStop> = Vec::new();
+    loop {
+        let next_due = pending.iter().map(|stop| stop.due).min();
+        let received = match next_due {
+            Some(due) => stops.recv_timeout(due.saturating_duration_since(std::time::Instant::now())),
+            None => stops.recv().map_err(|_| std::sync::mpsc::RecvTimeoutError::Disconnected),
+        };
+        match received {
+            Ok(stop) => pending.push(stop),
+            Err(std::sync::mpsc::RecvTimeoutError::Timeout) => {}
+            Err(std::sync::mpsc::RecvTimeoutError::Disconnected) => return,
+        }
+
+        let now = std::time::Instant::now();
+        let (due, waiting): (Vec<_>, Vec<_>) = pending.into_iter().partition(|stop| stop.due <= now);
+        pending = waiting;
+        if due.is_empty() {
+            continue;
+        }
+
+        let session_manager = match app.try_state::<Mutex<session::SessionManager>>() {
+            Some(session_manager) => session_manager,
+            None => continue,
+        };
+        let manager = session_manager.lock().unwrap();
+        for stop in due {
+            for id in stop.session_ids {
+                if let Ok(Some(session)) = storage::get_session(manager.db_path(), id) {
+                    // Unless a restart continued it; its next end notifies instead
+                    let still_ended = session.end_time == Some(stop.ended_at)
+                        && session.end_reason == Some(session::EndReason::Stopped);
+                    if still_ended {
+                        notifications::notify_session(&app, &manager, &session, notifications::SessionChange::Stopped);
+                    }
+                }
+            }
+        }
+    }
+}
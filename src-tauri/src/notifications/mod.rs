@@ -0,0 +1,234 @@
+pub mod rules;
+
+pub use rules::{NotifyRule, NotifyRuleEntry, QuietHours, SessionChange};
+
+use crate::session::policy::AppPolicy;
+use crate::session::{Session, SessionManager};
+use crate::storage;
+use anyhow::Result;
+use chrono::{DateTime, Local, Utc};
+use rules::{should_notify, NotifyContext};
+use std::path::Path;
+use tauri::{AppHandle, Runtime};
+use tauri_plugin_notification::NotificationExt;
+
+/// `meta` key holding the rule for apps without one of their own.
+const META_DEFAULT_RULE: &str = "notify_default_rule";
+
+/// A desktop notification about a session.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Notification {
+    pub title: String,
+    pub body: String,
+}
+
+/// The rule for apps without one of their own.
+pub fn default_rule(db_path: &Path) -> Result<NotifyRule> {
+    Ok(storage::get_meta(db_path, META_DEFAULT_RULE)?
+        .and_then(|rule| rule.parse().ok())
+        .unwrap_or_default())
+}
+
+pub fn set_default_rule(db_path: &Path, rule: NotifyRule) -> Result<()> {
+    storage::set_meta(db_path, META_DEFAULT_RULE, &rule.to_string())
+}
+
+/// The rule for the app recorded as `app_name`, falling back to the default.
+pub fn rule_for(db_path: &Path, app_name: &str) -> Result<NotifyRule> {
+    match storage::get_notify_rule(db_path, app_name)? {
+        Some(rule) => Ok(rule),
+        None => default_rule(db_path),
+    }
+}
+
+/// Whether `at` falls inside any quiet hours window, in local time.
+pub fn is_quiet(db_path: &Path, at: DateTime<Utc>) -> Result<bool> {
+    let local = at.with_timezone(&Local).naive_local();
+    Ok(storage::get_quiet_hours(db_path)?
+        .iter()
+        .any(|window| window.contains(local)))
+}
+
+/// The notification `session` starting or stopping calls for, if any.
+/// Stops are judged at the session's end, starts at its start.
+pub fn notification_for(
+    manager: &SessionManager,
+    session: &Session,
+    change: SessionChange,
+) -> Result<Option<Notification>> {
+    let db_path = manager.db_path();
+    let at = match change {
+        SessionChange::Started => session.start_time,
+        SessionChange::Stopped => session.end_time.unwrap_or(session.start_time),
+    };
+    let reported_name = session.raw_app_name.as_deref().unwrap_or(&session.app_name);
+    let flagged =
+        manager.app_policy(reported_name, session.bundle_id.as_deref())? == AppPolicy::Flag;
+
+    let context = NotifyContext {
+        change,
+        flagged,
+        first_time: !storage::has_earlier_session(
+            db_path,
+            &session.app_name,
+            &session.sensor,
+            session.start_time,
+        )?,
+        quiet: is_quiet(db_path, at)?,
+    };
+    if !should_notify(rule_for(db_path, &session.app_name)?, context) {
+        return Ok(None);
+    }
+
+    let title = match (change, flagged) {
+        (SessionChange::Started, true) => {
+            format!(
+                "Flagged app {} is using the {}",
+                session.app_name, session.sensor
+            )
+        }
+        (SessionChange::Started, false) => {
+            format!("{} is using the {}", session.app_name, session.sensor)
+        }
+        (SessionChange::Stopped, _) => {
+            format!("{} stopped using the {}", session.app_name, session.sensor)
+        }
+    };
+    let body = match change {
+        SessionChange::Started => format!(
+            "Started at {}",
+            session.start_time.with_timezone(&Local).format("%H:%M")
+        ),
+        SessionChange::Stopped => format!(
+            "Used for {}",
+            format_duration(session.duration_secs.unwrap_or(0))
+        ),
+    };
+
+    Ok(Some(Notification { title, body }))
+}
+
+/// Decides whether `session` changing calls for a notification and shows
+/// it. Failures are logged rather than returned, so they never hold up
+/// session tracking.
+pub fn notify_session<R: Runtime>(
+    app: &AppHandle<R>,
+    manager: &SessionManager,
+    session: &Session,
+    change: SessionChange,
+) {
+    let notification = match notification_for(manager, session, change) {
+        Ok(Some(notification)) => notification,
+        Ok(None) => return,
+        Err(e) => {
+            eprintln!(
+                "[webcam-tracker] Failed to check notification rules: {:#}",
+                e
+            );
+            return;
+        }
+    };
+
+    if let Err(e) = app
+        .notification()
+        .builder()
+        .title(&notification.title)
+        .body(&notification.body)
+        .show()
+    {
+        eprintln!("[webcam-tracker] Failed to show notification: {}", e);
+    }
+}
+
+/// `1h 5m`, `12m 3s` or `40s`.
+fn format_duration(secs: i64) -> String {
+    let (hours, mins, secs) = (secs / 3600, (secs % 3600) / 60, secs % 60);
+    if hours > 0 {
+        format!("{}h {}m", hours, mins)
+    } else if mins > 0 {
+        format!("{}m {}s", mins, secs)
+    } else {
+        format!("{}s", secs)
+    }
+}
+
+// ---------------------------------------------------------------------------
+// Tests
+// ---------------------------------------------------------------------------
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::camera::Sensor;
+    use chrono::Duration;
+
+    #[test]
+    fn test_notification_for_follows_rules() {
+        let path = storage::scratch_db("notifications");
+        let manager = SessionManager::new(path.clone());
+        let t0 = Utc::now() - Duration::hours(2);
+
+        let first = manager
+            .start_session("FaceTime".into(), None, None, Sensor::Camera, t0)
+            .unwrap();
+        let first = storage::get_session(&path, first).unwrap().unwrap();
+        let notification = notification_for(&manager, &first, SessionChange::Started)
+            .unwrap()
+            .unwrap();
+        assert_eq!(notification.title, "FaceTime is using the camera");
+
+        // Only the app's first session notifies under first_time
+        storage::set_notify_rule(&path, "facetime", NotifyRule::FirstTime).unwrap();
+        assert!(notification_for(&manager, &first, SessionChange::Started)
+            .unwrap()
+            .is_some());
+        manager
+            .end_session_for_app(
+                "FaceTime",
+                None,
+                None,
+                Sensor::Camera,
+                t0 + Duration::seconds(75),
+            )
+            .unwrap();
+        let second = manager
+            .start_session(
+                "FaceTime".into(),
+                None,
+                None,
+                Sensor::Camera,
+                t0 + Duration::hours(1),
+            )
+            .unwrap();
+        let second = storage::get_session(&path, second).unwrap().unwrap();
+        assert!(notification_for(&manager, &second, SessionChange::Started)
+            .unwrap()
+            .is_none());
+
+        let first = storage::get_session(&path, first.id.unwrap())
+            .unwrap()
+            .unwrap();
+        let notification = notification_for(&manager, &first, SessionChange::Stopped)
+            .unwrap()
+            .unwrap();
+        assert_eq!(notification.title, "FaceTime stopped using the camera");
+        assert_eq!(notification.body, "Used for 1m 15s");
+
+        // Quiet all day silences everything but flagged apps starting
+        set_default_rule(&path, NotifyRule::FlaggedOnly).unwrap();
+        storage::delete_notify_rule(&path, "FaceTime").unwrap();
+        storage::insert_quiet_hours(&path, &QuietHours::parse("", "00:00", "00:00").unwrap())
+            .unwrap();
+        manager.set_app_policy("FaceTime", AppPolicy::Flag).unwrap();
+        let notification = notification_for(&manager, &second, SessionChange::Started)
+            .unwrap()
+            .unwrap();
+        assert_eq!(
+            notification.title,
+            "Flagged app FaceTime is using the camera"
+        );
+        assert!(notification_for(&manager, &first, SessionChange::Stopped)
+            .unwrap()
+            .is_none());
+    }
+}
@@ -0,0 +1,247 @@
+use anyhow::{Context, Result};
+use chrono::{Datelike, NaiveDateTime, NaiveTime, Weekday};
+use serde::{Deserialize, Serialize};
+
+// ---------------------------------------------------------------------------
+// Notification rules
+// ---------------------------------------------------------------------------
+
+/// When an app's sessions raise a desktop notification.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum NotifyRule {
+    /// On every session start and stop.
+    #[default]
+    Always,
+    /// Only for the first session the app ever records on a sensor.
+    FirstTime,
+    /// Only while the app's policy is `AppPolicy::Flag`.
+    FlaggedOnly,
+    Never,
+}
+
+impl std::fmt::Display for NotifyRule {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            NotifyRule::Always => write!(f, "always"),
+            NotifyRule::FirstTime => write!(f, "first_time"),
+            NotifyRule::FlaggedOnly => write!(f, "flagged_only"),
+            NotifyRule::Never => write!(f, "never"),
+        }
+    }
+}
+
+impl std::str::FromStr for NotifyRule {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        match s.trim().to_ascii_lowercase().as_str() {
+            "always" => Ok(NotifyRule::Always),
+            "first_time" => Ok(NotifyRule::FirstTime),
+            "flagged_only" => Ok(NotifyRule::FlaggedOnly),
+            "never" => Ok(NotifyRule::Never),
+            other => anyhow::bail!("Unknown notification rule: {}", other),
+        }
+    }
+}
+
+/// The rule set for one app, by the name its sessions are recorded under
+/// (after alias rules), ignoring case.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct NotifyRuleEntry {
+    pub app_name: String,
+    pub rule: NotifyRule,
+}
+
+/// Whether a notification is for a session starting or stopping.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SessionChange {
+    Started,
+    Stopped,
+}
+
+/// What is known about a session when deciding whether to notify.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct NotifyContext {
+    pub change: SessionChange,
+    /// The app's policy is `AppPolicy::Flag`.
+    pub flagged: bool,
+    /// No earlier session of the app on the sensor is recorded.
+    pub first_time: bool,
+    /// The session changed during quiet hours.
+    pub quiet: bool,
+}
+
+/// Whether `rule` calls for a notification in `context`. A flagged app
+/// starting is always alerted on unless its rule is `Never`, even during
+/// quiet hours; anything else stays silent during them.
+pub fn should_notify(rule: NotifyRule, context: NotifyContext) -> bool {
+    if rule == NotifyRule::Never {
+        return false;
+    }
+    if context.flagged && context.change == SessionChange::Started {
+        return true;
+    }
+    if context.quiet {
+        return false;
+    }
+
+    match rule {
+        NotifyRule::Always => true,
+        NotifyRule::FirstTime => context.first_time,
+        NotifyRule::FlaggedOnly => context.flagged,
+        NotifyRule::Never => false,
+    }
+}
+
+// ---------------------------------------------------------------------------
+// Quiet hours
+// ---------------------------------------------------------------------------
+
+/// A weekly window, in local time, during which notifications are held
+/// back, e.g. a recurring meeting. A window whose end is not after its
+/// start runs past midnight into the next day.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct QuietHours {
+    /// Database ID; `None` until stored.
+    pub id: Option<i64>,
+    /// The days the window starts on. Empty means every day.
+    pub days: Vec<Weekday>,
+    pub start: NaiveTime,
+    pub end: NaiveTime,
+}
+
+impl QuietHours {
+    /// Parses `days` as comma-separated names (`mon,wed` or `Monday`) and
+    /// `start`/`end` as `HH:MM`.
+    pub fn parse(days: &str, start: &str, end: &str) -> Result<Self> {
+        let days = days
+            .split(',')
+            .map(str::trim)
+            .filter(|day| !day.is_empty())
+            .map(|day| {
+                day.parse::<Weekday>()
+                    .map_err(|_| anyhow::anyhow!("Unknown day: {}", day))
+            })
+            .collect::<Result<Vec<_>>>()?;
+        let time = |s: &str| {
+            NaiveTime::parse_from_str(s.trim(), "%H:%M")
+                .with_context(|| format!("Invalid time {:?}, expected HH:MM", s))
+        };
+
+        Ok(Self {
+            id: None,
+            days,
+            start: time(start)?,
+            end: time(end)?,
+        })
+    }
+
+    /// The days as stored: lowercase three-letter names, comma-separated.
+    pub fn days_to_string(&self) -> String {
+        self.days
+            .iter()
+            .map(|day| day.to_string().to_lowercase())
+            .collect::<Vec<_>>()
+            .join(",")
+    }
+
+    fn starts_on(&self, day: Weekday) -> bool {
+        self.days.is_empty() || self.days.contains(&day)
+    }
+
+    /// Whether the local time `at` falls inside the window.
+    pub fn contains(&self, at: NaiveDateTime) -> bool {
+        let time = at.time();
+        let today = at.weekday();
+
+        if self.start < self.end {
+            self.starts_on(today) && time >= self.start && time < self.end
+        } else {
+            // Past midnight: either the evening part of a window starting
+            // today, or the morning part of one that started yesterday
+            (self.starts_on(today) && time >= self.start)
+                || (self.starts_on(today.pred()) && time < self.end)
+        }
+    }
+}
+
+// ---------------------------------------------------------------------------
+// Tests
+// ---------------------------------------------------------------------------
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::NaiveDate;
+
+    fn at(day: u32, hour: u32, min: u32) -> NaiveDateTime {
+        // June 2025: the 9th is a Monday
+        NaiveDate::from_ymd_opt(2025, 6, day)
+            .unwrap()
+            .and_hms_opt(hour, min, 0)
+            .unwrap()
+    }
+
+    #[test]
+    fn test_should_notify() {
+        let context = NotifyContext {
+            change: SessionChange::Started,
+            flagged: false,
+            first_time: false,
+            quiet: false,
+        };
+        assert!(should_notify(NotifyRule::Always, context));
+        assert!(!should_notify(NotifyRule::FirstTime, context));
+        assert!(!should_notify(NotifyRule::FlaggedOnly, context));
+        assert!(should_notify(
+            NotifyRule::FirstTime,
+            NotifyContext {
+                first_time: true,
+                ..context
+            }
+        ));
+
+        // Quiet hours hold back everything but a flagged app starting
+        let quiet = NotifyContext {
+            quiet: true,
+            ..context
+        };
+        assert!(!should_notify(NotifyRule::Always, quiet));
+        let flagged = NotifyContext {
+            flagged: true,
+            ..quiet
+        };
+        assert!(should_notify(NotifyRule::FlaggedOnly, flagged));
+        assert!(!should_notify(NotifyRule::Never, flagged));
+        assert!(!should_notify(
+            NotifyRule::FlaggedOnly,
+            NotifyContext {
+                change: SessionChange::Stopped,
+                ..flagged
+            }
+        ));
+    }
+
+    #[test]
+    fn test_quiet_hours() {
+        let standup = QuietHours::parse("mon, wed", "09:30", "10:00").unwrap();
+        assert_eq!(standup.days_to_string(), "mon,wed");
+        assert!(standup.contains(at(9, 9, 30)));
+        assert!(!standup.contains(at(9, 10, 0)));
+        assert!(!standup.contains(at(10, 9, 45)));
+        assert!(standup.contains(at(11, 9, 45)));
+
+        // Friday night into Saturday morning
+        let overnight = QuietHours::parse("fri", "22:00", "07:00").unwrap();
+        assert!(overnight.contains(at(13, 23, 0)));
+        assert!(overnight.contains(at(14, 6, 59)));
+        assert!(!overnight.contains(at(13, 6, 0)));
+
+        let every_day = QuietHours::parse("", "12:00", "13:00").unwrap();
+        assert!(every_day.contains(at(15, 12, 15)));
+
+        assert!(QuietHours::parse("someday", "09:00", "10:00").is_err());
+        assert!(QuietHours::parse("mon", "9am", "10:00").is_err());
+    }
+}
@@ -0,0 +1,193 @@
+use anyhow::{Context, Result};
+use regex::Regex;
+use serde::{Deserialize, Serialize};
+
+// ---------------------------------------------------------------------------
+// App name aliases
+// ---------------------------------------------------------------------------
+
+/// Bump whenever `default_rules` changes, so stored sessions are
+/// re-normalized on the next launch.
+pub const DEFAULT_RULES_VERSION: u32 = 1;
+
+/// How an alias rule matches the app a source reported.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum AliasMatch {
+    /// The whole name, ignoring case.
+    Exact,
+    /// The start of the name, ignoring case.
+    Prefix,
+    /// A regular expression on the name.
+    Regex,
+    /// The bundle ID, ignoring case.
+    BundleId,
+}
+
+impl std::fmt::Display for AliasMatch {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            AliasMatch::Exact => write!(f, "exact"),
+            AliasMatch::Prefix => write!(f, "prefix"),
+            AliasMatch::Regex => write!(f, "regex"),
+            AliasMatch::BundleId => write!(f, "bundle_id"),
+        }
+    }
+}
+
+impl std::str::FromStr for AliasMatch {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        match s.trim().to_ascii_lowercase().as_str() {
+            "exact" => Ok(AliasMatch::Exact),
+            "prefix" => Ok(AliasMatch::Prefix),
+            "regex" => Ok(AliasMatch::Regex),
+            "bundle_id" => Ok(AliasMatch::BundleId),
+            other => anyhow::bail!("Unknown alias match: {}", other),
+        }
+    }
+}
+
+/// Records apps matching `pattern` under `app_name`.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct AliasRule {
+    /// Database ID of a user rule; `None` for built-in ones.
+    pub id: Option<i64>,
+    pub kind: AliasMatch,
+    pub pattern: String,
+    /// The canonical name. For `AliasMatch::Regex` rules, `$1`-style
+    /// references to the pattern's groups are expanded.
+    pub app_name: String,
+}
+
+impl AliasRule {
+    pub fn new(kind: AliasMatch, pattern: &str, app_name: &str) -> Self {
+        Self {
+            id: None,
+            kind,
+            pattern: pattern.to_string(),
+            app_name: app_name.to_string(),
+        }
+    }
+
+    /// Checks that the rule can be applied: neither side is empty and a
+    /// regex pattern compiles.
+    pub fn validate(&self) -> Result<()> {
+        if self.pattern.trim().is_empty() || self.app_name.trim().is_empty() {
+            anyhow::bail!("Alias rules need a pattern and an app name");
+        }
+        if self.kind == AliasMatch::Regex {
+            Regex::new(&self.pattern)
+                .with_context(|| format!("Invalid alias pattern {:?}", self.pattern))?;
+        }
+        Ok(())
+    }
+}
+
+/// The rules every install starts with, applied after the user's own.
+pub fn default_rules() -> Vec<AliasRule> {
+    vec![
+        AliasRule::new(AliasMatch::BundleId, "us.zoom.xos", "Zoom"),
+        AliasRule::new(AliasMatch::Exact, "zoom.us", "Zoom"),
+        AliasRule::new(AliasMatch::Exact, "MSTeams", "Microsoft Teams"),
+        // "Google Chrome Helper", "Slack Helper (Renderer)", ...
+        AliasRule::new(AliasMatch::Regex, r"^(.+?) Helper(?: \(.+\))?$", "$1"),
+    ]
+}
+
+/// A compiled set of alias rules. The first rule that matches decides the
+/// name; apps no rule matches keep theirs.
+#[derive(Debug, Default)]
+pub struct Aliases {
+    rules: Vec<(AliasRule, Option<Regex>)>,
+}
+
+impl Aliases {
+    /// `user_rules` in order, then `default_rules`. Rules that fail
+    /// `AliasRule::validate` are skipped.
+    pub fn new(user_rules: Vec<AliasRule>) -> Self {
+        let rules = user_rules
+            .into_iter()
+            .chain(default_rules())
+            .filter_map(|rule| {
+                if let Err(e) = rule.validate() {
+                    eprintln!("[webcam-tracker] Skipping alias rule: {:#}", e);
+                    return None;
+                }
+                let regex = match rule.kind {
+                    AliasMatch::Regex => Regex::new(&rule.pattern).ok(),
+                    _ => None,
+                };
+                Some((rule, regex))
+            })
+            .collect();
+
+        Self { rules }
+    }
+
+    /// The name to record an app under, given the name and bundle ID its
+    /// source reported.
+    pub fn normalize(&self, app_name: &str, bundle_id: Option<&str>) -> String {
+        let lower = app_name.to_lowercase();
+        for (rule, regex) in &self.rules {
+            let matched = match rule.kind {
+                AliasMatch::Exact => lower == rule.pattern.to_lowercase(),
+                AliasMatch::Prefix => lower.starts_with(&rule.pattern.to_lowercase()),
+                AliasMatch::BundleId => {
+                    bundle_id.is_some_and(|id| id.eq_ignore_ascii_case(&rule.pattern))
+                }
+                AliasMatch::Regex => {
+                    if let Some(captures) = regex.as_ref().and_then(|r| r.captures(app_name)) {
+                        let mut expanded = String::new();
+                        captures.expand(&rule.app_name, &mut expanded);
+                        return expanded;
+                    }
+                    false
+                }
+            };
+            if matched {
+                return rule.app_name.clone();
+            }
+        }
+
+        app_name.to_string()
+    }
+}
+
+// ---------------------------------------------------------------------------
+// Tests
+// ---------------------------------------------------------------------------
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_default_rules() {
+        let aliases = Aliases::new(vec![]);
+        assert_eq!(aliases.normalize("zoom.us", None), "Zoom");
+        assert_eq!(
+            aliases.normalize("Zoom Meetings", Some("us.zoom.xos")),
+            "Zoom"
+        );
+        assert_eq!(
+            aliases.normalize("Google Chrome Helper", None),
+            "Google Chrome"
+        );
+        assert_eq!(aliases.normalize("Slack Helper (Renderer)", None), "Slack");
+        assert_eq!(aliases.normalize("FaceTime", None), "FaceTime");
+    }
+
+    #[test]
+    fn test_user_rules_come_first() {
+        let aliases = Aliases::new(vec![
+            AliasRule::new(AliasMatch::Prefix, "zoom", "Zoom Workplace"),
+            AliasRule::new(AliasMatch::Regex, "(", "Broken"),
+        ]);
+        assert_eq!(aliases.normalize("zoom.us", None), "Zoom Workplace");
+        assert!(AliasRule::new(AliasMatch::Regex, "(", "Broken")
+            .validate()
+            .is_err());
+    }
+}
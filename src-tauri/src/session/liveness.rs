@@ -0,0 +1,105 @@
+use anyhow::{Context, Result};
+use chrono::{DateTime, Utc};
+use std::collections::HashSet;
+use std::process::Command;
+
+// ---------------------------------------------------------------------------
+// Process liveness
+// ---------------------------------------------------------------------------
+
+/// The processes that were running at one moment, for checking that the
+/// apps behind running sessions are still alive.
+#[derive(Debug, Clone)]
+pub struct ProcessSnapshot {
+    pub taken_at: DateTime<Utc>,
+    pids: HashSet<u32>,
+    /// Executable names, lowercased.
+    names: HashSet<String>,
+}
+
+impl ProcessSnapshot {
+    /// Lists the running processes with `ps`.
+    pub fn capture() -> Result<Self> {
+        let taken_at = Utc::now();
+        let output = Command::new("ps")
+            .args(["-axo", "pid=,comm="])
+            .output()
+            .context("Failed to run ps")?;
+        if !output.status.success() {
+            anyhow::bail!("ps exited with {}", output.status);
+        }
+
+        Ok(Self::parse(
+            &String::from_utf8_lossy(&output.stdout),
+            taken_at,
+        ))
+    }
+
+    /// Parses `ps -o pid=,comm=` output, where `comm` may be a full path
+    /// (macOS) or a bare name (Linux).
+    pub fn parse(ps: &str, taken_at: DateTime<Utc>) -> Self {
+        let mut pids = HashSet::new();
+        let mut names = HashSet::new();
+
+        for line in ps.lines() {
+            let line = line.trim_start();
+            let (pid, command) = match line.split_once(char::is_whitespace) {
+                Some(parts) => parts,
+                None => continue,
+            };
+            let pid = match pid.parse() {
+                Ok(pid) => pid,
+                Err(_) => continue,
+            };
+            pids.insert(pid);
+
+            let name = command.trim().rsplit('/').next().unwrap_or("");
+            if !name.is_empty() {
+                names.insert(name.to_lowercase());
+            }
+        }
+
+        Self {
+            taken_at,
+            pids,
+            names,
+        }
+    }
+
+    pub fn has_pid(&self, pid: u32) -> bool {
+        self.pids.contains(&pid)
+    }
+
+    /// Whether a process whose executable is called `name` was running,
+    /// ignoring case.
+    pub fn has_name(&self, name: &str) -> bool {
+        self.names.contains(&name.to_lowercase())
+    }
+}
+
+// ---------------------------------------------------------------------------
+// Tests
+// ---------------------------------------------------------------------------
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_ps_output() {
+        let ps = "    1 /sbin/launchd\n  \
+                  5678 /Applications/zoom.us.app/Contents/MacOS/zoom.us\n  \
+                  812 /Applications/Google Chrome.app/Contents/MacOS/Google Chrome\n\
+                  4242 firefox\n\
+                  garbage\n";
+        let snapshot = ProcessSnapshot::parse(ps, Utc::now());
+
+        assert!(snapshot.has_pid(5678));
+        assert!(snapshot.has_pid(4242));
+        assert!(!snapshot.has_pid(99));
+        assert!(snapshot.has_name("Zoom.us"));
+        assert!(snapshot.has_name("Google Chrome"));
+        assert!(snapshot.has_name("firefox"));
+        assert!(!snapshot.has_name("FaceTime"));
+    }
+}
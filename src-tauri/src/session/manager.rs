@@ -1,89 +1,910 @@
-use crate::session::models::{Session, SessionStatus};
+use crate::camera::journal::JournaledEvent;
+use crate::camera::platform_macos::{AttributionSnapshot, UNATTRIBUTED_APP};
+use crate::camera::Sensor;
+use crate::session::aliases::{AliasRule, Aliases};
+use crate::session::liveness::ProcessSnapshot;
+use crate::session::models::{EndReason, Session, SessionSource};
+use crate::session::policy::{AppPolicy, AppPolicyEntry};
 use crate::storage;
 use anyhow::Result;
-use chrono::Utc;
+use chrono::{DateTime, Duration, Utc};
 use std::collections::HashMap;
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
 use std::sync::{Arc, Mutex};
 
+/// `meta` key recording the last time the tracker was known to be running.
+const META_LAST_ALIVE: &str = "last_alive_at";
+
+/// `meta` key holding the configured grace period, in seconds.
+const META_GRACE_PERIOD: &str = "grace_period_secs";
+
+/// A start that no stop has balanced for this long no longer keeps its
+/// session open, so a lost `stopRunning` cannot keep a session open forever.
+pub const UNBALANCED_START_TIMEOUT: Duration = Duration::hours(12);
+
+/// Default grace period: a stop followed by a start of the same app within
+/// this many seconds continues the session.
+pub const DEFAULT_GRACE_PERIOD_SECS: i64 = 2;
+
+/// Identifies an active session: one per app instance and sensor.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+struct SessionKey {
+    /// Display name after alias rules.
+    app: String,
+    bundle_id: Option<String>,
+    /// PID of the instance, when the source reports one. Two instances (or
+    /// helper processes) of the same app get independent sessions.
+    pid: Option<u32>,
+    sensor: Sensor,
+}
+
+impl SessionKey {
+    fn new(app_name: &str, bundle_id: Option<&str>, pid: Option<u32>, sensor: Sensor) -> Self {
+        Self {
+            app: app_name.to_string(),
+            bundle_id: bundle_id.map(str::to_string),
+            pid,
+            sensor,
+        }
+    }
+
+    /// Whether an event for `self` refers to the session keyed by `active`:
+    /// same app and sensor, and either the same PID or one side
+    /// does not know the PID. Apps are the same by bundle ID when both sides
+    /// know it, otherwise by name.
+    fn matches(&self, active: &SessionKey) -> bool {
+        let same_app = match (&self.bundle_id, &active.bundle_id) {
+            (Some(ours), Some(theirs)) => ours == theirs,
+            _ => self.app == active.app,
+        };
+        same_app
+            && self.sensor == active.sensor
+            && (self.pid.is_none() || active.pid.is_none() || self.pid == active.pid)
+    }
+}
+
+/// A session recently ended by a stop, which a quick restart may continue.
+#[derive(Debug, Clone, Copy)]
+struct StoppedSession {
+    id: i64,
+    ended_at: DateTime<Utc>,
+}
+
+/// A session ended by sleep, which resumes from wake once ControlCenter
+/// lists its app again.
+#[derive(Debug, Clone)]
+struct SleptSession {
+    session: Session,
+    woke_at: DateTime<Utc>,
+    /// How many starts were open when the machine slept.
+    open: usize,
+}
+
 pub struct SessionManager {
     db_path: PathBuf,
+    /// Recorded on every session this manager starts.
+    session_source: SessionSource,
     is_tracking: Arc<Mutex<bool>>,
-    /// Maps app name → active session ID, allowing multiple concurrent webcam sessions
-    active_sessions: Arc<Mutex<HashMap<String, i64>>>,
+    /// Maps (app, pid, sensor) → active session ID, allowing multiple concurrent
+    /// webcam and microphone sessions, including several instances of one app
+    active_sessions: Arc<Mutex<HashMap<SessionKey, i64>>>,
+    /// Sessions adopted from a previous run, mapped to when they were last
+    /// seen alive. If nothing ends them, they are closed at that time.
+    adopted_sessions: Arc<Mutex<HashMap<i64, Option<DateTime<Utc>>>>>,
+    /// Sessions recently ended by a stop. A start of the same app instance
+    /// within `grace_period` continues them.
+    recently_stopped: Arc<Mutex<HashMap<SessionKey, StoppedSession>>>,
+    /// Sessions the last sleep ended, until their source confirms them
+    /// after wake or a new start replaces them.
+    slept_sessions: Arc<Mutex<HashMap<SessionKey, SleptSession>>>,
+    /// Maps active session ID → times of the starts not yet balanced by a
+    /// stop. Apps that open several capture sessions start the sensor more
+    /// than once; their session only ends once every start has been stopped.
+    open_starts: Arc<Mutex<HashMap<i64, Vec<DateTime<Utc>>>>>,
+    /// Maps active session ID → when a start or stop was last counted against
+    /// it. A session whose starts all time out is closed there.
+    last_counted: Arc<Mutex<HashMap<i64, DateTime<Utc>>>>,
+    /// Whether the screen is locked, as last reported to `set_screen_locked`.
+    screen_locked: Arc<Mutex<bool>>,
+    /// Rules mapping the names sources report to the name sessions get.
+    aliases: Arc<Mutex<Aliases>>,
+    grace_period: Arc<Mutex<Duration>>,
 }
 
 impl SessionManager {
     pub fn new(db_path: PathBuf) -> Self {
+        Self::with_source(db_path, SessionSource::Live)
+    }
+
+    /// Creates a manager whose sessions are marked with `session_source`,
+    /// e.g. for reconstructing history next to the live manager. The grace
+    /// period is read from the database, so every manager on it applies the
+    /// same rules.
+    pub fn with_source(db_path: PathBuf, session_source: SessionSource) -> Self {
+        let grace_secs = storage::get_meta(&db_path, META_GRACE_PERIOD)
+            .ok()
+            .flatten()
+            .and_then(|secs| secs.parse().ok())
+            .unwrap_or(DEFAULT_GRACE_PERIOD_SECS);
+        let aliases = Aliases::new(storage::get_alias_rules(&db_path).unwrap_or_default());
+
         Self {
             db_path,
+            session_source,
             is_tracking: Arc::new(Mutex::new(false)),
             active_sessions: Arc::new(Mutex::new(HashMap::new())),
+            adopted_sessions: Arc::new(Mutex::new(HashMap::new())),
+            recently_stopped: Arc::new(Mutex::new(HashMap::new())),
+            slept_sessions: Arc::new(Mutex::new(HashMap::new())),
+            open_starts: Arc::new(Mutex::new(HashMap::new())),
+            last_counted: Arc::new(Mutex::new(HashMap::new())),
+            screen_locked: Arc::new(Mutex::new(false)),
+            aliases: Arc::new(Mutex::new(aliases)),
+            grace_period: Arc::new(Mutex::new(Duration::seconds(grace_secs))),
         }
     }
 
-    /// Starts a new session for the given app.
-    /// If the same app already has an active session, it is ended first.
-    /// Other apps' sessions are left untouched.
-    pub fn start_session(&self, app_name: String) -> Result<i64> {
-        // If this specific app already has an active session, end it first
-        let existing_id = {
+    /// The database sessions are written to.
+    pub fn db_path(&self) -> &Path {
+        &self.db_path
+    }
+
+    /// Starts a new session for the given app instance and sensor at
+    /// `started_at` (the time the event was logged, not when we received it).
+    /// Apps are identified by bundle ID when both sides know one, falling back
+    /// to the display name after alias rules are applied, and instances by PID.
+    /// If the same instance already has an active session on that sensor, or
+    /// the app has one that either side started without a PID (which the
+    /// instance then adopts), the start is counted against it and its ID
+    /// returned, unless every earlier start is older than
+    /// `UNBALANCED_START_TIMEOUT`; then the stale session is ended first. If
+    /// the instance stopped its session within the grace period instead, that
+    /// session is continued. Other apps', instances' and sensors' sessions are
+    /// left untouched.
+    pub fn start_session(
+        &self,
+        app_name: String,
+        bundle_id: Option<String>,
+        pid: Option<u32>,
+        sensor: Sensor,
+        started_at: DateTime<Utc>,
+    ) -> Result<i64> {
+        let raw_app_name = app_name;
+        let app_name = self.normalize(&raw_app_name, bundle_id.as_deref());
+        let key = SessionKey::new(&app_name, bundle_id.as_deref(), pid, sensor);
+        // A start after wake replaces a session the sleep ended
+        self.slept_sessions
+            .lock()
+            .unwrap()
+            .retain(|slept, _| !key.matches(slept));
+
+        // If this instance already has an active session, nest the start
+        // inside it, or end it first if it has gone stale. An exact match
+        // wins; otherwise a session whose PID one side does not know is the
+        // same one, reported by a source with less detail
+        let existing = {
             let sessions = self.active_sessions.lock().unwrap();
-            sessions.get(&app_name).copied()
+            match sessions.get(&key) {
+                Some(id) => Some((key.clone(), *id)),
+                None => sessions
+                    .iter()
+                    .filter(|(active, _)| key.matches(active))
+                    .min_by_key(|(_, id)| **id)
+                    .map(|(k, v)| (k.clone(), *v)),
+            }
         };
-        if let Some(active_id) = existing_id {
-            let _ = self.end_session_by_id(active_id, &app_name);
+        if let Some((active_key, active_id)) = existing {
+            if self.open_start(active_id, started_at) {
+                // A session started without a PID is this instance's from now on
+                if let (None, Some(pid)) = (active_key.pid, pid) {
+                    storage::set_session_pid(&self.db_path, active_id, pid)?;
+                    let mut sessions = self.active_sessions.lock().unwrap();
+                    sessions.remove(&active_key);
+                    sessions.insert(SessionKey { pid: Some(pid), ..active_key }, active_id);
+                }
+                return Ok(active_id);
+            }
+            let _ = self.end_session_by_id(active_id, &active_key, started_at, EndReason::Restarted);
+        } else if let Some((stopped_key, id)) = self.take_recently_stopped(&key, started_at) {
+            storage::reopen_session(&self.db_path, id)?;
+            if *self.screen_locked.lock().unwrap() {
+                storage::mark_screen_locked(&self.db_path, &[id])?;
+            }
+            self.active_sessions.lock().unwrap().insert(stopped_key, id);
+            self.open_starts.lock().unwrap().insert(id, vec![started_at]);
+            self.last_counted.lock().unwrap().insert(id, started_at);
+            return Ok(id);
         }
 
-        let session = Session::new(app_name.clone(), Utc::now());
+        let mut session = Session::new(app_name, sensor, started_at);
+        session.raw_app_name = Some(raw_app_name);
+        session.bundle_id = bundle_id;
+        session.pid = pid;
+        session.source = self.session_source;
+        session.screen_locked = *self.screen_locked.lock().unwrap();
         let id = storage::insert_session(&self.db_path, &session)?;
 
         let mut sessions = self.active_sessions.lock().unwrap();
-        sessions.insert(app_name, id);
+        sessions.insert(key, id);
+        self.open_starts.lock().unwrap().insert(id, vec![started_at]);
+        self.last_counted.lock().unwrap().insert(id, started_at);
 
         Ok(id)
     }
 
-    /// Ends the active session for a specific app (by name).
-    /// Returns Ok(true) if a session was ended, Ok(false) if no active session for that app.
-    pub fn end_session_for_app(&self, app_name: &str) -> Result<bool> {
+    /// Counts another start at `at` against active session `id`. Returns
+    /// `false`, counting nothing, if all its earlier starts have timed out.
+    fn open_start(&self, id: i64, at: DateTime<Utc>) -> bool {
+        let mut open_starts = self.open_starts.lock().unwrap();
+        let starts = open_starts.entry(id).or_default();
+        starts.retain(|started_at| at - *started_at < UNBALANCED_START_TIMEOUT);
+        if starts.is_empty() {
+            return false;
+        }
+        starts.push(at);
+        self.last_counted.lock().unwrap().insert(id, at);
+        true
+    }
+
+    /// Balances one start of active session `id` with a stop at `at`.
+    /// Returns whether no start is left open, so the session should end.
+    fn close_start(&self, id: i64, at: DateTime<Utc>) -> bool {
+        let mut open_starts = self.open_starts.lock().unwrap();
+        let starts = match open_starts.get_mut(&id) {
+            Some(starts) => starts,
+            None => return true,
+        };
+        starts.retain(|started_at| at - *started_at < UNBALANCED_START_TIMEOUT);
+        starts.pop();
+        self.last_counted.lock().unwrap().insert(id, at);
+        starts.is_empty()
+    }
+
+    /// Ends every session whose starts have all gone unbalanced for
+    /// `UNBALANCED_START_TIMEOUT` by `now`, as `EndReason::StartTimedOut` at
+    /// the last start or stop counted against it. Timed-out starts of other
+    /// sessions are dropped, so a single stop ends them. Sessions adopted from
+    /// a previous run are left to crash recovery. Returns the IDs ended.
+    pub fn end_timed_out_sessions(&self, now: DateTime<Utc>) -> Result<Vec<i64>> {
+        let active: Vec<(SessionKey, i64)> = self
+            .active_sessions
+            .lock()
+            .unwrap()
+            .iter()
+            .map(|(key, id)| (key.clone(), *id))
+            .collect();
+
+        let mut ended = Vec::new();
+        for (key, id) in active {
+            if self.adopted_sessions.lock().unwrap().contains_key(&id) {
+                continue;
+            }
+            let timed_out = match self.open_starts.lock().unwrap().get_mut(&id) {
+                Some(starts) => {
+                    starts.retain(|started_at| now - *started_at < UNBALANCED_START_TIMEOUT);
+                    starts.is_empty()
+                }
+                None => false,
+            };
+            if !timed_out {
+                continue;
+            }
+
+            let last_counted = self.last_counted.lock().unwrap().get(&id).copied();
+            let ended_at = match (last_counted, storage::get_session(&self.db_path, id)?) {
+                (Some(at), _) => at,
+                (None, Some(session)) => session.start_time,
+                (None, None) => continue,
+            };
+            eprintln!(
+                "[webcam-tracker] Ending session {} after its starts went unbalanced",
+                id
+            );
+            self.end_session_by_id(id, &key, ended_at, EndReason::StartTimedOut)?;
+            ended.push(id);
+        }
+
+        Ok(ended)
+    }
+
+    /// Ends the active session for a specific app instance (by bundle ID, or
+    /// name when there is none, plus PID) and sensor at `ended_at`.
+    ///
+    /// An exact PID match wins. Without one, a stop that carries no PID ends
+    /// every session of that app on the sensor, and a stop with a PID ends
+    /// the app's session that was started without one. A session the app
+    /// started more than once only ends when the last start is stopped.
+    /// Returns the IDs of the sessions ended, if any.
+    pub fn end_session_for_app(
+        &self,
+        app_name: &str,
+        bundle_id: Option<&str>,
+        pid: Option<u32>,
+        sensor: Sensor,
+        ended_at: DateTime<Utc>,
+    ) -> Result<Vec<i64>> {
+        let app_name = self.normalize(app_name, bundle_id);
+        let key = SessionKey::new(&app_name, bundle_id, pid, sensor);
+        let matching: Vec<(SessionKey, i64)> = {
+            let sessions = self.active_sessions.lock().unwrap();
+            match sessions.get(&key) {
+                Some(id) => vec![(key.clone(), *id)],
+                None => sessions
+                    .iter()
+                    .filter(|(active, _)| key.matches(active))
+                    .map(|(k, v)| (k.clone(), *v))
+                    .collect(),
+            }
+        };
+
+        let mut ended = Vec::new();
+        for (active, id) in &matching {
+            if !self.close_start(*id, ended_at) {
+                continue;
+            }
+            ended.push(*id);
+            self.end_session_by_id(*id, active, ended_at, EndReason::Stopped)?;
+            if *self.grace_period.lock().unwrap() > Duration::zero() {
+                let mut stopped = self.recently_stopped.lock().unwrap();
+                stopped.insert(
+                    active.clone(),
+                    StoppedSession {
+                        id: *id,
+                        ended_at,
+                    },
+                );
+            }
+        }
+
+        Ok(ended)
+    }
+
+    /// Removes and returns the session `key` stopped within the grace period
+    /// before `started_at`, if any. Entries that are too old are dropped.
+    fn take_recently_stopped(
+        &self,
+        key: &SessionKey,
+        started_at: DateTime<Utc>,
+    ) -> Option<(SessionKey, i64)> {
+        let grace_period = *self.grace_period.lock().unwrap();
+        let mut stopped = self.recently_stopped.lock().unwrap();
+        stopped.retain(|_, session| started_at - session.ended_at <= grace_period);
+
+        // An exact match wins over one that only matches by leaving out the PID
+        let mut candidates = stopped
+            .iter()
+            .filter(|(_, session)| started_at >= session.ended_at)
+            .map(|(stopped_key, _)| stopped_key);
+        let found = match stopped.get(key) {
+            Some(session) if started_at >= session.ended_at => key.clone(),
+            _ => candidates.find(|stopped_key| key.matches(stopped_key))?.clone(),
+        };
+        stopped.remove(&found).map(|session| (found, session.id))
+    }
+
+    /// The name to record an app under, after alias rules.
+    fn normalize(&self, app_name: &str, bundle_id: Option<&str>) -> String {
+        self.aliases.lock().unwrap().normalize(app_name, bundle_id)
+    }
+
+    /// The alias rules this manager names apps by, shared so a `Reconciler`
+    /// in front of it matches apps by the same names. Reloaded in place
+    /// whenever the user's rules change.
+    pub fn aliases(&self) -> Arc<Mutex<Aliases>> {
+        self.aliases.clone()
+    }
+
+    /// The user's alias rules, in the order they apply.
+    pub fn alias_rules(&self) -> Result<Vec<AliasRule>> {
+        storage::get_alias_rules(&self.db_path)
+    }
+
+    /// Stores a user alias rule, applied after the user's existing rules and
+    /// before the built-in ones, and renames recorded sessions with it.
+    /// Returns its ID.
+    pub fn add_alias_rule(&self, rule: &AliasRule) -> Result<i64> {
+        rule.validate()?;
+        let id = storage::insert_alias_rule(&self.db_path, rule)?;
+        self.reload_aliases()?;
+        Ok(id)
+    }
+
+    /// Deletes a user alias rule and renames recorded sessions without it.
+    /// Returns whether it existed.
+    pub fn remove_alias_rule(&self, id: i64) -> Result<bool> {
+        let removed = storage::delete_alias_rule(&self.db_path, id)?;
+        if removed {
+            self.reload_aliases()?;
+        }
+        Ok(removed)
+    }
+
+    /// Reloads the alias rules, renames every recorded session with them and
+    /// re-keys running sessions under their new names. Returns the number of
+    /// sessions renamed.
+    fn reload_aliases(&self) -> Result<usize> {
+        let aliases = Aliases::new(storage::get_alias_rules(&self.db_path)?);
+        let renamed = storage::renormalize_sessions(&self.db_path, &aliases)?;
+        *self.aliases.lock().unwrap() = aliases;
+
+        let active: Vec<(SessionKey, i64)> = {
+            let sessions = self.active_sessions.lock().unwrap();
+            sessions.iter().map(|(k, v)| (k.clone(), *v)).collect()
+        };
+        let mut rekeyed = HashMap::new();
+        for (key, id) in active {
+            let key = match storage::get_session(&self.db_path, id)? {
+                Some(session) => SessionKey::new(
+                    &session.app_name,
+                    session.bundle_id.as_deref(),
+                    key.pid,
+                    key.sensor,
+                ),
+                None => key,
+            };
+            rekeyed.insert(key, id);
+        }
+        *self.active_sessions.lock().unwrap() = rekeyed;
+        // Keyed by the names they stopped under
+        self.recently_stopped.lock().unwrap().clear();
+        self.slept_sessions.lock().unwrap().clear();
+
+        Ok(renamed)
+    }
+
+    /// The policy for the app a source reported, looked up by the name its
+    /// sessions are recorded under. Apps without one are allowed.
+    pub fn app_policy(&self, app_name: &str, bundle_id: Option<&str>) -> Result<AppPolicy> {
+        let app_name = self.normalize(app_name, bundle_id);
+        Ok(storage::get_app_policy(&self.db_path, &app_name)?.unwrap_or_default())
+    }
+
+    /// Every app with a policy set.
+    pub fn app_policies(&self) -> Result<Vec<AppPolicyEntry>> {
+        storage::get_app_policies(&self.db_path)
+    }
+
+    /// Sets the policy for the app recorded as `app_name`. Only starts from
+    /// then on are affected; recorded and running sessions are kept.
+    pub fn set_app_policy(&self, app_name: &str, policy: AppPolicy) -> Result<()> {
+        let app_name = app_name.trim();
+        if app_name.is_empty() {
+            anyhow::bail!("App policies need an app name");
+        }
+        storage::set_app_policy(&self.db_path, app_name, policy)
+    }
+
+    /// Removes the policy for `app_name`, so the app is allowed again.
+    /// Returns whether it had one.
+    pub fn clear_app_policy(&self, app_name: &str) -> Result<bool> {
+        storage::delete_app_policy(&self.db_path, app_name.trim())
+    }
+
+    /// The grace period within which a stop and a start of the same app are
+    /// merged into one session, in seconds.
+    pub fn grace_period_secs(&self) -> i64 {
+        self.grace_period.lock().unwrap().num_seconds()
+    }
+
+    /// Sets and persists the grace period. Zero disables merging.
+    pub fn set_grace_period(&self, secs: i64) -> Result<()> {
+        let secs = secs.max(0);
+        storage::set_meta(&self.db_path, META_GRACE_PERIOD, &secs.to_string())?;
+        *self.grace_period.lock().unwrap() = Duration::seconds(secs);
+        Ok(())
+    }
+
+    /// Records that `source` also reported the active session of this app
+    /// instance and sensor, matched the same way as `end_session_for_app`.
+    /// Returns Ok(false) if there is no such session.
+    pub fn confirm_session(
+        &self,
+        app_name: &str,
+        bundle_id: Option<&str>,
+        pid: Option<u32>,
+        sensor: Sensor,
+        source: &str,
+    ) -> Result<bool> {
+        let app_name = self.normalize(app_name, bundle_id);
+        let key = SessionKey::new(&app_name, bundle_id, pid, sensor);
         let session_id = {
             let sessions = self.active_sessions.lock().unwrap();
-            sessions.get(app_name).copied()
+            sessions.get(&key).copied().or_else(|| {
+                sessions
+                    .iter()
+                    .find(|(active, _)| key.matches(active))
+                    .map(|(_, id)| *id)
+            })
+        };
+
+        match session_id {
+            Some(id) => {
+                storage::add_session_source(&self.db_path, id, source)?;
+                Ok(true)
+            }
+            None => Ok(false),
+        }
+    }
+
+    /// Ends running sessions whose app went away without a stop being seen,
+    /// at `now`. A session ends as `EndReason::ProcessExited` when its PID is
+    /// not in `processes`, or, when neither its PID nor bundle ID is known,
+    /// no process has its name. It ends as `EndReason::AttributionLost` when
+    /// ControlCenter published `attributions` since it started that leave it
+    /// out. Sessions started after `processes` was taken are left alone.
+    /// Returns the number of sessions ended.
+    pub fn end_stale_sessions(
+        &self,
+        processes: Option<&ProcessSnapshot>,
+        attributions: Option<&AttributionSnapshot>,
+        now: DateTime<Utc>,
+    ) -> Result<usize> {
+        let active: Vec<(SessionKey, i64)> = self
+            .active_sessions
+            .lock()
+            .unwrap()
+            .iter()
+            .map(|(key, id)| (key.clone(), *id))
+            .collect();
+
+        let mut ended = 0;
+        for (key, id) in active {
+            let session = match storage::get_session(&self.db_path, id)? {
+                Some(session) => session,
+                None => continue,
+            };
+
+            let exited = processes.is_some_and(|processes| {
+                if session.start_time >= processes.taken_at {
+                    return false;
+                }
+                match (session.pid, &session.bundle_id) {
+                    (Some(pid), _) => !processes.has_pid(pid),
+                    // Display names from a bundle ID need not match the
+                    // executable; ControlCenter covers those sessions
+                    (None, Some(_)) => false,
+                    (None, None) => {
+                        let name = session.raw_app_name.as_deref().unwrap_or(&session.app_name);
+                        name != UNATTRIBUTED_APP && !processes.has_name(name)
+                    }
+                }
+            });
+            let unattributed = attributions.is_some_and(|attributions| {
+                // ControlCenter lists the names apps report, not our aliases
+                attributions.omits(
+                    session.raw_app_name.as_deref().unwrap_or(&session.app_name),
+                    session.bundle_id.as_deref(),
+                    session.sensor,
+                    session.start_time,
+                )
+            });
+
+            let end_reason = if exited {
+                EndReason::ProcessExited
+            } else if unattributed {
+                EndReason::AttributionLost
+            } else {
+                continue;
+            };
+            eprintln!(
+                "[webcam-tracker] Ending stale session {} of {} ({})",
+                id, session.app_name, end_reason
+            );
+            self.end_session_by_id(id, &key, now.max(session.start_time), end_reason)?;
+            ended += 1;
+        }
+
+        Ok(ended)
+    }
+
+    /// Records whether the screen is locked. While it is, every running
+    /// session and every session started is flagged as `screen_locked`.
+    pub fn set_screen_locked(&self, locked: bool) -> Result<()> {
+        *self.screen_locked.lock().unwrap() = locked;
+        if !locked {
+            return Ok(());
+        }
+
+        let ids: Vec<i64> = self.active_sessions.lock().unwrap().values().copied().collect();
+        storage::mark_screen_locked(&self.db_path, &ids)
+    }
+
+    /// Splits every session that was running when the machine slept at
+    /// `slept_at`: it ends there as `EndReason::Sleep`. It only continues
+    /// after wake once its source confirms the app is still using the sensor:
+    /// a new start begins a new session as usual, and `resume_slept_sessions`
+    /// resumes it from `woke_at` while ControlCenter lists the app. Otherwise
+    /// it stays closed, and a stop after wake finds nothing to end. Returns
+    /// the number of sessions ended.
+    pub fn split_at_sleep(
+        &self,
+        slept_at: DateTime<Utc>,
+        woke_at: DateTime<Utc>,
+    ) -> Result<usize> {
+        let active: Vec<(SessionKey, i64)> = self
+            .active_sessions
+            .lock()
+            .unwrap()
+            .iter()
+            .map(|(key, id)| (key.clone(), *id))
+            .collect();
+
+        let mut slept = HashMap::new();
+        for (key, id) in active {
+            let session = match storage::get_session(&self.db_path, id)? {
+                Some(session) if session.start_time < slept_at => session,
+                _ => continue,
+            };
+            let open = self.open_count(id);
+            self.end_session_by_id(id, &key, slept_at, EndReason::Sleep)?;
+            slept.insert(key, SleptSession { session, woke_at, open });
+        }
+
+        let ended = slept.len();
+        *self.slept_sessions.lock().unwrap() = slept;
+        Ok(ended)
+    }
+
+    /// Resumes, from wake, each session the last sleep ended whose app
+    /// ControlCenter has listed as using the sensor since, with the starts it
+    /// had open. Sessions a list since wake leaves out stay closed for good.
+    /// Returns the number of sessions resumed.
+    pub fn resume_slept_sessions(&self, attributions: Option<&AttributionSnapshot>) -> Result<usize> {
+        let attributions = match attributions {
+            Some(attributions) => attributions,
+            None => return Ok(0),
         };
+        let slept: Vec<(SessionKey, SleptSession)> =
+            self.slept_sessions.lock().unwrap().drain().collect();
 
-        if let Some(id) = session_id {
-            self.end_session_by_id(id, app_name)?;
-            Ok(true)
-        } else {
-            Ok(false)
+        let mut resumed = 0;
+        for (key, slept) in slept {
+            let session = &slept.session;
+            // ControlCenter lists the names apps report, not our aliases
+            let app_name = session.raw_app_name.as_deref().unwrap_or(&session.app_name);
+            let bundle_id = session.bundle_id.as_deref();
+            if attributions.lists(app_name, bundle_id, session.sensor, slept.woke_at) {
+                self.resume_session(&key, slept.session, slept.woke_at, slept.open)?;
+                resumed += 1;
+            } else if !attributions.omits(app_name, bundle_id, session.sensor, slept.woke_at) {
+                // Nothing listed since wake yet
+                self.slept_sessions.lock().unwrap().insert(key, slept);
+            }
         }
+
+        Ok(resumed)
+    }
+
+    /// How many starts of active session `id` are open, at least one.
+    fn open_count(&self, id: i64) -> usize {
+        self.open_starts
+            .lock()
+            .unwrap()
+            .get(&id)
+            .map_or(1, |starts| starts.len().max(1))
+    }
+
+    /// Ends active session `id` at `slept_at` as `EndReason::Sleep` and
+    /// continues it in a new session from `woke_at`.
+    fn split_session(
+        &self,
+        id: i64,
+        key: &SessionKey,
+        session: Session,
+        slept_at: DateTime<Utc>,
+        woke_at: DateTime<Utc>,
+    ) -> Result<()> {
+        let open = self.open_count(id);
+        self.end_session_by_id(id, key, slept_at, EndReason::Sleep)?;
+        self.resume_session(key, session, woke_at, open)
+    }
+
+    /// Continues `session`, ended by sleep, in a new active session from
+    /// `woke_at` with `open` starts open.
+    fn resume_session(
+        &self,
+        key: &SessionKey,
+        session: Session,
+        woke_at: DateTime<Utc>,
+        open: usize,
+    ) -> Result<()> {
+        let mut resumed = Session::new(session.app_name, session.sensor, woke_at);
+        resumed.raw_app_name = session.raw_app_name;
+        resumed.bundle_id = session.bundle_id;
+        resumed.pid = session.pid;
+        resumed.confirmed_by = session.confirmed_by;
+        resumed.source = self.session_source;
+        resumed.screen_locked = *self.screen_locked.lock().unwrap();
+        let resumed_id = storage::insert_session(&self.db_path, &resumed)?;
+
+        self.active_sessions.lock().unwrap().insert(key.clone(), resumed_id);
+        self.open_starts
+            .lock()
+            .unwrap()
+            .insert(resumed_id, vec![woke_at; open]);
+        self.last_counted.lock().unwrap().insert(resumed_id, woke_at);
+
+        Ok(())
+    }
+
+    /// Ends the active session of the app instance and sensor `ended` was
+    /// recorded for, at `ended`'s end time and for its end reason, however
+    /// many of its starts are still open. A sleep end resumes the session at
+    /// `resumed_at` when given, as `resume_slept_sessions` does.
+    ///
+    /// Used to replay ends the event journal does not record. Returns the ID
+    /// of the session ended, if one was active.
+    pub fn end_session_like(
+        &self,
+        ended: &Session,
+        resumed_at: Option<DateTime<Utc>>,
+    ) -> Result<Option<i64>> {
+        let (ended_at, end_reason) = match (ended.end_time, ended.end_reason) {
+            (Some(ended_at), Some(end_reason)) => (ended_at, end_reason),
+            _ => return Ok(None),
+        };
+        let raw_app_name = ended.raw_app_name.as_deref().unwrap_or(&ended.app_name);
+        let app_name = self.normalize(raw_app_name, ended.bundle_id.as_deref());
+        let key = SessionKey::new(&app_name, ended.bundle_id.as_deref(), ended.pid, ended.sensor);
+        let found = {
+            let sessions = self.active_sessions.lock().unwrap();
+            match sessions.get(&key) {
+                Some(id) => Some((key.clone(), *id)),
+                None => sessions
+                    .iter()
+                    .filter(|(active, _)| key.matches(active))
+                    .min_by_key(|(_, id)| **id)
+                    .map(|(k, v)| (k.clone(), *v)),
+            }
+        };
+        let (active_key, id) = match found {
+            Some(found) => found,
+            None => return Ok(None),
+        };
+
+        match (end_reason, resumed_at) {
+            (EndReason::Sleep, Some(woke_at)) => match storage::get_session(&self.db_path, id)? {
+                Some(session) => self.split_session(id, &active_key, session, ended_at, woke_at)?,
+                None => return Ok(None),
+            },
+            _ => self.end_session_by_id(id, &active_key, ended_at, end_reason)?,
+        }
+
+        Ok(Some(id))
+    }
+
+    /// Tracks a session a previous run left running as active, so later
+    /// stop events for it end it. If nothing does, `end_all_active_sessions_at`
+    /// closes it at its last heartbeat (or `last_alive`, the last time the
+    /// previous run was known to be running) as `EndReason::CrashRecovered`.
+    pub fn adopt_session(&self, session: &Session, last_alive: Option<DateTime<Utc>>) {
+        if let Some(id) = session.id {
+            let key = SessionKey::new(
+                &session.app_name,
+                session.bundle_id.as_deref(),
+                session.pid,
+                session.sensor,
+            );
+            let last_seen = session
+                .last_seen
+                .or(last_alive)
+                .map(|t| t.max(session.start_time));
+
+            self.active_sessions.lock().unwrap().insert(key, id);
+            self.adopted_sessions.lock().unwrap().insert(id, last_seen);
+            // How many starts the previous run had counted is not stored
+            self.open_starts
+                .lock()
+                .unwrap()
+                .insert(id, vec![session.start_time]);
+        }
+    }
+
+    /// Hands the sessions this manager started and still has running over to
+    /// `to`, which tracks them as its own from then on, with their open starts,
+    /// so its next stops end them. Used to carry sessions that downtime
+    /// recovery found still running into the live manager. A session `to`
+    /// already has running for the same app instance and sensor is ended at
+    /// `now` as `EndReason::ReplayEnd` instead. Adopted sessions stay here.
+    /// Returns the number of sessions handed over.
+    pub fn hand_over_sessions(&self, to: &SessionManager, now: DateTime<Utc>) -> Result<usize> {
+        let running: Vec<(SessionKey, i64)> = {
+            let adopted = self.adopted_sessions.lock().unwrap();
+            let sessions = self.active_sessions.lock().unwrap();
+            sessions
+                .iter()
+                .filter(|(_, id)| !adopted.contains_key(*id))
+                .map(|(k, v)| (k.clone(), *v))
+                .collect()
+        };
+
+        let mut handed_over = 0;
+        for (key, id) in running {
+            let duplicate = to
+                .active_sessions
+                .lock()
+                .unwrap()
+                .keys()
+                .any(|active| key.matches(active));
+            if duplicate {
+                self.end_session_by_id(id, &key, now, EndReason::ReplayEnd)?;
+                continue;
+            }
+
+            self.active_sessions.lock().unwrap().remove(&key);
+            let starts = self.open_starts.lock().unwrap().remove(&id);
+            let last_counted = self.last_counted.lock().unwrap().remove(&id);
+            to.active_sessions.lock().unwrap().insert(key, id);
+            to.open_starts
+                .lock()
+                .unwrap()
+                .insert(id, starts.unwrap_or_else(|| vec![now]));
+            to.last_counted
+                .lock()
+                .unwrap()
+                .insert(id, last_counted.unwrap_or(now));
+            handed_over += 1;
+        }
+
+        Ok(handed_over)
     }
 
     /// Ends a session by its database ID and removes it from active tracking.
-    fn end_session_by_id(&self, session_id: i64, app_name: &str) -> Result<()> {
-        let end_time = Utc::now();
-        storage::update_session(&self.db_path, session_id, end_time)?;
+    fn end_session_by_id(
+        &self,
+        session_id: i64,
+        key: &SessionKey,
+        end_time: DateTime<Utc>,
+        end_reason: EndReason,
+    ) -> Result<()> {
+        storage::update_session(&self.db_path, session_id, end_time, end_reason)?;
 
         let mut sessions = self.active_sessions.lock().unwrap();
-        sessions.remove(app_name);
+        sessions.remove(key);
+        self.adopted_sessions.lock().unwrap().remove(&session_id);
+        self.open_starts.lock().unwrap().remove(&session_id);
+        self.last_counted.lock().unwrap().remove(&session_id);
 
         Ok(())
     }
 
-    /// Ends all currently active sessions (used for pause/shutdown).
-    pub fn end_all_active_sessions(&self) -> Result<()> {
-        let snapshot: Vec<(String, i64)> = {
+    /// Ends all currently active sessions now (used for pause/shutdown).
+    pub fn end_all_active_sessions(&self, end_reason: EndReason) -> Result<()> {
+        self.end_all_active_sessions_at(Utc::now(), end_reason)
+    }
+
+    /// Ends all currently active sessions at `end_time` for `end_reason`.
+    /// Adopted sessions are closed at their last heartbeat as
+    /// `EndReason::CrashRecovered` instead. None of them can be continued
+    /// by a later start.
+    pub fn end_all_active_sessions_at(
+        &self,
+        end_time: DateTime<Utc>,
+        end_reason: EndReason,
+    ) -> Result<()> {
+        let snapshot: Vec<(SessionKey, i64)> = {
             let sessions = self.active_sessions.lock().unwrap();
             sessions.iter().map(|(k, v)| (k.clone(), *v)).collect()
         };
 
-        let end_time = Utc::now();
-        for (app_name, session_id) in snapshot {
-            let _ = storage::update_session(&self.db_path, session_id, end_time);
+        for (key, session_id) in snapshot {
+            let adopted = self.adopted_sessions.lock().unwrap().remove(&session_id);
+            let _ = match adopted {
+                Some(last_seen) => storage::update_session(
+                    &self.db_path,
+                    session_id,
+                    last_seen.unwrap_or(end_time),
+                    EndReason::CrashRecovered,
+                ),
+                None => storage::update_session(&self.db_path, session_id, end_time, end_reason),
+            };
             let mut sessions = self.active_sessions.lock().unwrap();
-            sessions.remove(&app_name);
+            sessions.remove(&key);
         }
+        self.recently_stopped.lock().unwrap().clear();
+        self.slept_sessions.lock().unwrap().clear();
+        self.open_starts.lock().unwrap().clear();
+        self.last_counted.lock().unwrap().clear();
 
         Ok(())
     }
@@ -94,14 +915,14 @@ impl SessionManager {
     }
 
     pub fn pause_tracking(&self) -> Result<()> {
-        self.end_all_active_sessions()?;
+        self.end_all_active_sessions(EndReason::Paused)?;
         let mut tracking = self.is_tracking.lock().unwrap();
         *tracking = false;
         Ok(())
     }
 
     pub fn resume_tracking(&self) -> Result<()> {
-        self.end_all_active_sessions()?;
+        self.end_all_active_sessions(EndReason::Paused)?;
         let mut tracking = self.is_tracking.lock().unwrap();
         *tracking = true;
         Ok(())
@@ -119,20 +940,650 @@ impl SessionManager {
         storage::get_active_sessions(&self.db_path)
     }
 
-    pub fn recover_orphaned_sessions(&self) -> Result<()> {
-        // Find all running sessions and close them
-        let sessions = storage::get_all_sessions(&self.db_path)?;
+    /// The journaled events that are evidence for session `session_id`.
+    pub fn get_session_events(&self, session_id: i64) -> Result<Vec<JournaledEvent>> {
+        let session = storage::get_session(&self.db_path, session_id)?
+            .ok_or_else(|| anyhow::anyhow!("Session {} not found", session_id))?;
+        storage::get_session_events(&self.db_path, &session)
+    }
+
+    /// Records that the tracker is running now, on the tracker itself and on
+    /// every session this manager has running.
+    pub fn heartbeat(&self) -> Result<()> {
         let now = Utc::now();
+        let ids: Vec<i64> = self.active_sessions.lock().unwrap().values().copied().collect();
 
-        for session in sessions {
-            if session.status == SessionStatus::Running {
-                if let Some(id) = session.id {
-                    storage::update_session(&self.db_path, id, now)?;
-                }
-            }
-        }
+        storage::touch_sessions(&self.db_path, &ids, now)?;
+        storage::set_meta(&self.db_path, META_LAST_ALIVE, &now.to_rfc3339())
+    }
 
-        Ok(())
+    /// The last heartbeat, by this run or a previous one.
+    pub fn last_alive(&self) -> Result<Option<DateTime<Utc>>> {
+        Ok(storage::get_meta(&self.db_path, META_LAST_ALIVE)?
+            .and_then(|s| DateTime::parse_from_rfc3339(&s).ok())
+            .map(|t| t.with_timezone(&Utc)))
     }
 }
 
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::session::aliases::AliasMatch;
+    use crate::session::models::SessionStatus;
+    use chrono::Duration;
+
+    fn manager(name: &str) -> (SessionManager, storage::ScratchDb) {
+        let path = storage::scratch_db(&format!("manager-{}", name));
+        (SessionManager::new(path.clone()), path)
+    }
+
+    #[test]
+    fn test_instances_tracked_independently() {
+        let (manager, path) = manager("instances");
+        let t0 = Utc::now();
+
+        manager
+            .start_session("Chrome".into(), None, Some(100), Sensor::Camera, t0)
+            .unwrap();
+        manager
+            .start_session("Chrome".into(), None, Some(200), Sensor::Camera, t0)
+            .unwrap();
+        assert_eq!(storage::get_active_sessions(&path).unwrap().len(), 2);
+
+        let ended = manager
+            .end_session_for_app("Chrome", None, Some(100), Sensor::Camera, t0 + Duration::seconds(5))
+            .unwrap();
+        assert_eq!(ended.len(), 1);
+
+        let active = storage::get_active_sessions(&path).unwrap();
+        assert_eq!(active.len(), 1);
+        assert_eq!(active[0].pid, Some(200));
+    }
+
+    #[test]
+    fn test_start_with_pid_adopts_session_without_one() {
+        let (manager, path) = manager("adopt-pid");
+        let t0 = Utc::now();
+
+        let first = manager
+            .start_session("FaceTime".into(), None, None, Sensor::Camera, t0)
+            .unwrap();
+        let second = manager
+            .start_session("FaceTime".into(), None, Some(42), Sensor::Camera, t0)
+            .unwrap();
+        assert_eq!(first, second);
+        let active = storage::get_active_sessions(&path).unwrap();
+        assert_eq!(active.len(), 1);
+        assert_eq!(active[0].pid, Some(42));
+
+        // Both sources' stops are needed to end it
+        let later = t0 + Duration::seconds(5);
+        assert!(manager
+            .end_session_for_app("FaceTime", None, None, Sensor::Camera, later)
+            .unwrap()
+            .is_empty());
+        assert_eq!(
+            manager
+                .end_session_for_app("FaceTime", None, Some(42), Sensor::Camera, later)
+                .unwrap(),
+            vec![first]
+        );
+    }
+
+    #[test]
+    fn test_stop_without_pid_ends_all_instances() {
+        let (manager, _db) = manager("no-pid");
+        let t0 = Utc::now();
+
+        manager
+            .start_session("Chrome".into(), None, Some(100), Sensor::Camera, t0)
+            .unwrap();
+        manager
+            .start_session("Chrome".into(), None, Some(200), Sensor::Camera, t0)
+            .unwrap();
+
+        manager
+            .end_session_for_app("Chrome", None, None, Sensor::Camera, t0)
+            .unwrap();
+        assert!(!manager.has_active_sessions());
+    }
+
+    #[test]
+    fn test_confirm_session_records_sources() {
+        let (manager, _db) = manager("confirm");
+        manager
+            .start_session("FaceTime".into(), None, Some(1234), Sensor::Camera, Utc::now())
+            .unwrap();
+
+        assert!(manager
+            .confirm_session("FaceTime", None, Some(1234), Sensor::Camera, "cmio")
+            .unwrap());
+        assert!(!manager
+            .confirm_session("zoom.us", None, None, Sensor::Camera, "cmio")
+            .unwrap());
+
+        let sessions = manager.get_all_sessions().unwrap();
+        assert_eq!(sessions[0].confirmed_by, vec!["cmio"]);
+    }
+
+    #[test]
+    fn test_flapping_merged_within_grace_period() {
+        let (manager, path) = manager("grace");
+        let t0 = Utc::now() - Duration::minutes(5);
+        let at = |secs| t0 + Duration::seconds(secs);
+
+        let id = manager
+            .start_session("zoom.us".into(), None, Some(5678), Sensor::Camera, at(0))
+            .unwrap();
+        manager
+            .end_session_for_app("zoom.us", None, Some(5678), Sensor::Camera, at(10))
+            .unwrap();
+        let continued = manager
+            .start_session("zoom.us".into(), None, Some(5678), Sensor::Camera, at(11))
+            .unwrap();
+        assert_eq!(continued, id);
+        manager
+            .end_session_for_app("zoom.us", None, None, Sensor::Camera, at(20))
+            .unwrap();
+
+        // Outside the grace period, so a new session
+        let later = manager
+            .start_session("zoom.us".into(), None, Some(5678), Sensor::Camera, at(30))
+            .unwrap();
+        assert_ne!(later, id);
+
+        let session = storage::get_session(&path, id).unwrap().unwrap();
+        assert_eq!(session.status, SessionStatus::Completed);
+        assert_eq!(session.duration_secs, Some(20));
+        assert_eq!(session.merged_interruptions, 1);
+    }
+
+    #[test]
+    fn test_grace_period_persists_and_can_be_disabled() {
+        let (manager, path) = manager("grace-off");
+        assert_eq!(manager.grace_period_secs(), DEFAULT_GRACE_PERIOD_SECS);
+        manager.set_grace_period(0).unwrap();
+
+        let restarted = SessionManager::new(path.clone());
+        assert_eq!(restarted.grace_period_secs(), 0);
+
+        let t0 = Utc::now();
+        let id = restarted
+            .start_session("zoom.us".into(), None, None, Sensor::Camera, t0)
+            .unwrap();
+        restarted
+            .end_session_for_app("zoom.us", None, None, Sensor::Camera, t0)
+            .unwrap();
+        let next = restarted
+            .start_session("zoom.us".into(), None, None, Sensor::Camera, t0)
+            .unwrap();
+        assert_ne!(next, id);
+    }
+
+    #[test]
+    fn test_end_reason_recorded_on_every_path() {
+        let (manager, _db) = manager("end-reasons");
+        let t0 = Utc::now();
+
+        let stale = t0 - UNBALANCED_START_TIMEOUT - Duration::hours(1);
+        let restarted = manager
+            .start_session("Chrome".into(), None, Some(100), Sensor::Camera, stale)
+            .unwrap();
+        let stopped = manager
+            .start_session("Chrome".into(), None, Some(100), Sensor::Camera, t0)
+            .unwrap();
+        manager
+            .end_session_for_app("Chrome", None, Some(100), Sensor::Camera, t0)
+            .unwrap();
+        let paused = manager
+            .start_session("FaceTime".into(), None, None, Sensor::Camera, t0)
+            .unwrap();
+        manager.pause_tracking().unwrap();
+
+        let reasons: HashMap<i64, EndReason> = manager
+            .get_all_sessions()
+            .unwrap()
+            .into_iter()
+            .map(|s| (s.id.unwrap(), s.end_reason.unwrap()))
+            .collect();
+        assert_eq!(reasons[&restarted], EndReason::Restarted);
+        assert_eq!(reasons[&stopped], EndReason::Stopped);
+        assert_eq!(reasons[&paused], EndReason::Paused);
+        assert!(!EndReason::Stopped.is_truncated());
+        assert!(EndReason::Paused.is_truncated());
+    }
+
+    #[test]
+    fn test_nested_starts_need_balanced_stops() {
+        let (manager, path) = manager("nested");
+        let t0 = Utc::now() - Duration::minutes(10);
+
+        let id = manager
+            .start_session("zoom.us".into(), None, Some(7), Sensor::Camera, t0)
+            .unwrap();
+        let nested = manager
+            .start_session(
+                "zoom.us".into(),
+                None,
+                Some(7),
+                Sensor::Camera,
+                t0 + Duration::seconds(5),
+            )
+            .unwrap();
+        assert_eq!(nested, id);
+
+        // The first stop only balances the nested start
+        let ended = manager
+            .end_session_for_app(
+                "zoom.us",
+                None,
+                Some(7),
+                Sensor::Camera,
+                t0 + Duration::seconds(30),
+            )
+            .unwrap();
+        assert!(ended.is_empty());
+        assert!(manager.has_active_sessions());
+
+        let ended = manager
+            .end_session_for_app(
+                "zoom.us",
+                None,
+                Some(7),
+                Sensor::Camera,
+                t0 + Duration::seconds(60),
+            )
+            .unwrap();
+        assert_eq!(ended, vec![id]);
+        let session = storage::get_session(&path, id).unwrap().unwrap();
+        assert_eq!(session.duration_secs, Some(60));
+        assert_eq!(session.end_reason, Some(EndReason::Stopped));
+    }
+
+    #[test]
+    fn test_unbalanced_starts_time_out() {
+        let (manager, path) = manager("unbalanced");
+        let t0 = Utc::now() - UNBALANCED_START_TIMEOUT - Duration::hours(2);
+
+        let id = manager
+            .start_session("zoom.us".into(), None, None, Sensor::Camera, t0)
+            .unwrap();
+        manager
+            .start_session("zoom.us".into(), None, None, Sensor::Camera, t0)
+            .unwrap();
+
+        // Both starts are past the timeout, so a single stop ends the session
+        let later = t0 + UNBALANCED_START_TIMEOUT + Duration::minutes(1);
+        let ended = manager
+            .end_session_for_app("zoom.us", None, None, Sensor::Camera, later)
+            .unwrap();
+        assert_eq!(ended, vec![id]);
+        let session = storage::get_session(&path, id).unwrap().unwrap();
+        assert_eq!(session.status, SessionStatus::Completed);
+    }
+
+    #[test]
+    fn test_timed_out_sessions_end_without_a_later_event() {
+        let (manager, path) = manager("timed-out");
+        let t0 = Utc::now() - UNBALANCED_START_TIMEOUT - Duration::hours(3);
+
+        let id = manager
+            .start_session("zoom.us".into(), None, None, Sensor::Camera, t0)
+            .unwrap();
+        manager
+            .start_session("zoom.us".into(), None, None, Sensor::Camera, t0 + Duration::hours(1))
+            .unwrap();
+        manager
+            .end_session_for_app("zoom.us", None, None, Sensor::Camera, t0 + Duration::hours(2))
+            .unwrap();
+        let fresh = manager
+            .start_session("FaceTime".into(), None, None, Sensor::Camera, Utc::now())
+            .unwrap();
+
+        let ended = manager.end_timed_out_sessions(Utc::now()).unwrap();
+        assert_eq!(ended, vec![id]);
+
+        // Closed at the stop that last balanced one of its starts
+        let session = storage::get_session(&path, id).unwrap().unwrap();
+        assert_eq!(session.end_reason, Some(EndReason::StartTimedOut));
+        assert_eq!(session.end_time, Some(t0 + Duration::hours(2)));
+        assert!(EndReason::StartTimedOut.is_truncated());
+        let fresh = storage::get_session(&path, fresh).unwrap().unwrap();
+        assert_eq!(fresh.status, SessionStatus::Running);
+    }
+
+    #[test]
+    fn test_stale_sessions_end_when_process_exits() {
+        let (manager, path) = manager("stale-process");
+        let t0 = Utc::now() - Duration::minutes(5);
+
+        let alive = manager
+            .start_session("zoom.us".into(), None, Some(5678), Sensor::Camera, t0)
+            .unwrap();
+        let crashed = manager
+            .start_session("Chrome".into(), None, Some(812), Sensor::Camera, t0)
+            .unwrap();
+        let by_name = manager
+            .start_session("Photo Booth".into(), None, None, Sensor::Camera, t0)
+            .unwrap();
+
+        let now = Utc::now();
+        let processes = ProcessSnapshot::parse("5678 zoom.us\n1 launchd\n", now);
+        let ended = manager.end_stale_sessions(Some(&processes), None, now).unwrap();
+        assert_eq!(ended, 2);
+
+        let session = |id| storage::get_session(&path, id).unwrap().unwrap();
+        assert_eq!(session(alive).status, SessionStatus::Running);
+        assert_eq!(session(crashed).end_reason, Some(EndReason::ProcessExited));
+        assert_eq!(session(by_name).end_reason, Some(EndReason::ProcessExited));
+        assert_eq!(session(crashed).end_time, Some(now));
+    }
+
+    #[test]
+    fn test_stale_sessions_end_when_attribution_lost() {
+        use crate::camera::platform_macos::{Attribution, AttributionList};
+
+        let (manager, path) = manager("stale-attribution");
+        let t0 = Utc::now() - Duration::minutes(5);
+
+        let facetime = manager
+            .start_session(
+                "FaceTime".into(),
+                Some("com.apple.FaceTime".into()),
+                None,
+                Sensor::Camera,
+                t0,
+            )
+            .unwrap();
+        let zoom = manager
+            .start_session(
+                "zoom.us".into(),
+                Some("us.zoom.xos".into()),
+                None,
+                Sensor::Camera,
+                t0,
+            )
+            .unwrap();
+        // Listed without a bundle ID, so matched by the name it reported
+        let zoom_mic = manager
+            .start_session("zoom.us".into(), None, None, Sensor::Microphone, t0)
+            .unwrap();
+
+        // A list from before the sessions started says nothing about them
+        let list = |listed_at| AttributionSnapshot {
+            camera: Some(AttributionList {
+                listed_at,
+                apps: vec![Attribution {
+                    app_name: "zoom.us".to_string(),
+                    bundle_id: Some("us.zoom.xos".to_string()),
+                }],
+            }),
+            microphone: Some(AttributionList {
+                listed_at,
+                apps: vec![Attribution {
+                    app_name: "zoom.us".to_string(),
+                    bundle_id: None,
+                }],
+            }),
+        };
+        let now = Utc::now();
+        let stale = list(t0 - Duration::minutes(1));
+        assert_eq!(manager.end_stale_sessions(None, Some(&stale), now).unwrap(), 0);
+
+        let current = list(t0 + Duration::minutes(1));
+        assert_eq!(manager.end_stale_sessions(None, Some(&current), now).unwrap(), 1);
+        let session = storage::get_session(&path, facetime).unwrap().unwrap();
+        assert_eq!(session.end_reason, Some(EndReason::AttributionLost));
+        let session = storage::get_session(&path, zoom).unwrap().unwrap();
+        assert_eq!(session.status, SessionStatus::Running);
+        let session = storage::get_session(&path, zoom_mic).unwrap().unwrap();
+        assert_eq!(session.app_name, "Zoom");
+        assert_eq!(session.status, SessionStatus::Running);
+    }
+
+    #[test]
+    fn test_sleep_splits_sessions_and_lock_is_flagged() {
+        use crate::camera::platform_macos::{Attribution, AttributionList};
+
+        let (manager, path) = manager("sleep");
+        let t0 = Utc::now() - Duration::hours(3);
+        let slept_at = t0 + Duration::minutes(10);
+        let woke_at = t0 + Duration::hours(2);
+
+        let before = manager
+            .start_session("zoom.us".into(), None, Some(7), Sensor::Camera, t0)
+            .unwrap();
+        manager.set_screen_locked(true).unwrap();
+        assert_eq!(manager.split_at_sleep(slept_at, woke_at).unwrap(), 1);
+
+        let session = storage::get_session(&path, before).unwrap().unwrap();
+        assert_eq!(session.end_time, Some(slept_at));
+        assert_eq!(session.end_reason, Some(EndReason::Sleep));
+        assert!(session.screen_locked);
+
+        // Only a list from after wake confirms the app kept the camera
+        let listed = |listed_at| AttributionSnapshot {
+            camera: Some(AttributionList {
+                listed_at,
+                apps: vec![Attribution {
+                    app_name: "zoom.us".to_string(),
+                    bundle_id: None,
+                }],
+            }),
+            microphone: None,
+        };
+        let before_sleep = listed(t0);
+        assert_eq!(manager.resume_slept_sessions(Some(&before_sleep)).unwrap(), 0);
+        let after_wake = listed(woke_at + Duration::minutes(1));
+        assert_eq!(manager.resume_slept_sessions(Some(&after_wake)).unwrap(), 1);
+        manager.set_screen_locked(false).unwrap();
+
+        // The stop after wake ends the session that resumed from wake
+        let later = woke_at + Duration::minutes(5);
+        assert_eq!(
+            manager
+                .end_session_for_app("zoom.us", None, Some(7), Sensor::Camera, later)
+                .unwrap()
+                .len(),
+            1
+        );
+        let after = manager
+            .get_all_sessions()
+            .unwrap()
+            .into_iter()
+            .find(|s| s.id != Some(before))
+            .unwrap();
+        assert_eq!(after.start_time, woke_at);
+        assert_eq!(after.duration_secs, Some(300));
+        assert_eq!(after.end_reason, Some(EndReason::Stopped));
+        assert!(after.screen_locked);
+
+        let unlocked = manager
+            .start_session("FaceTime".into(), None, None, Sensor::Camera, later)
+            .unwrap();
+        let session = storage::get_session(&path, unlocked).unwrap().unwrap();
+        assert!(!session.screen_locked);
+    }
+
+    #[test]
+    fn test_sleep_without_capture_after_wake_stays_closed() {
+        use crate::camera::platform_macos::{Attribution, AttributionList};
+
+        let (manager, path) = manager("sleep-closed");
+        let t0 = Utc::now() - Duration::hours(3);
+        let slept_at = t0 + Duration::minutes(10);
+        let woke_at = t0 + Duration::hours(2);
+
+        let before = manager
+            .start_session("zoom.us".into(), None, Some(7), Sensor::Camera, t0)
+            .unwrap();
+        assert_eq!(manager.split_at_sleep(slept_at, woke_at).unwrap(), 1);
+
+        // ControlCenter's first list after wake leaves the app out
+        let listed = |listed_at, apps: Vec<Attribution>| AttributionSnapshot {
+            camera: Some(AttributionList { listed_at, apps }),
+            microphone: None,
+        };
+        let empty = listed(woke_at + Duration::minutes(1), vec![]);
+        assert_eq!(manager.resume_slept_sessions(Some(&empty)).unwrap(), 0);
+        let zoom = Attribution {
+            app_name: "zoom.us".to_string(),
+            bundle_id: None,
+        };
+        let later = listed(woke_at + Duration::minutes(2), vec![zoom]);
+        assert_eq!(manager.resume_slept_sessions(Some(&later)).unwrap(), 0);
+
+        // A stop after wake finds nothing to end
+        let stopped_at = woke_at + Duration::minutes(5);
+        assert!(manager
+            .end_session_for_app("zoom.us", None, Some(7), Sensor::Camera, stopped_at)
+            .unwrap()
+            .is_empty());
+        let sessions = manager.get_all_sessions().unwrap();
+        assert_eq!(sessions.len(), 1);
+        assert_eq!(sessions[0].end_time, Some(slept_at));
+        assert_eq!(sessions[0].end_reason, Some(EndReason::Sleep));
+
+        // A start after wake begins a session of its own
+        let restarted_at = woke_at + Duration::minutes(10);
+        let restarted = manager
+            .start_session("zoom.us".into(), None, Some(7), Sensor::Camera, restarted_at)
+            .unwrap();
+        assert_ne!(restarted, before);
+        let session = storage::get_session(&path, restarted).unwrap().unwrap();
+        assert_eq!(session.start_time, restarted_at);
+    }
+
+    #[test]
+    fn test_ignored_apps_record_nothing() {
+        use crate::camera::replay::apply_event;
+        use crate::camera::CameraEvent;
+
+        let (manager, _db) = manager("policies");
+        // Policies apply to the recorded name, so this covers zoom.us too
+        manager.set_app_policy("zoom", AppPolicy::Ignore).unwrap();
+        manager.set_app_policy("FaceTime", AppPolicy::Flag).unwrap();
+        assert_eq!(
+            manager.app_policy("zoom.us", None).unwrap(),
+            AppPolicy::Ignore
+        );
+
+        let t0 = Utc::now() - Duration::minutes(30);
+        for app_name in ["zoom.us", "FaceTime"] {
+            let started = CameraEvent::Started {
+                app_name: app_name.to_string(),
+                bundle_id: None,
+                pid: None,
+                sensor: Sensor::Camera,
+                timestamp: t0,
+            };
+            apply_event(&manager, started).unwrap();
+        }
+
+        let sessions = manager.get_all_sessions().unwrap();
+        assert_eq!(sessions.len(), 1);
+        assert_eq!(sessions[0].app_name, "FaceTime");
+
+        assert!(manager.clear_app_policy("ZOOM").unwrap());
+        assert_eq!(manager.app_policies().unwrap().len(), 1);
+        assert_eq!(
+            manager.app_policy("Photo Booth", None).unwrap(),
+            AppPolicy::Allow
+        );
+    }
+
+    #[test]
+    fn test_alias_rules_rename_recorded_and_running_sessions() {
+        let (manager, path) = manager("aliases");
+        let t0 = Utc::now() - Duration::minutes(30);
+
+        let zoom = manager
+            .start_session("zoom.us".into(), None, None, Sensor::Camera, t0)
+            .unwrap();
+        let chat = manager
+            .start_session(
+                "Chat Helper (GPU)".into(),
+                None,
+                Some(9),
+                Sensor::Camera,
+                t0,
+            )
+            .unwrap();
+        let session = storage::get_session(&path, zoom).unwrap().unwrap();
+        assert_eq!(session.app_name, "Zoom");
+        assert_eq!(session.raw_app_name.as_deref(), Some("zoom.us"));
+        assert_eq!(
+            storage::get_session(&path, chat).unwrap().unwrap().app_name,
+            "Chat"
+        );
+
+        let rule = AliasRule::new(AliasMatch::Prefix, "chat helper", "Team Chat");
+        let id = manager.add_alias_rule(&rule).unwrap();
+        assert_eq!(manager.alias_rules().unwrap().len(), 1);
+        assert_eq!(
+            storage::get_session(&path, chat).unwrap().unwrap().app_name,
+            "Team Chat"
+        );
+
+        // The running session was re-keyed, so its stop still finds it
+        assert_eq!(
+            manager
+                .end_session_for_app(
+                    "Chat Helper (GPU)",
+                    None,
+                    Some(9),
+                    Sensor::Camera,
+                    t0 + Duration::minutes(5),
+                )
+                .unwrap(),
+            vec![chat]
+        );
+        assert!(manager.remove_alias_rule(id).unwrap());
+        let session = storage::get_session(&path, chat).unwrap().unwrap();
+        assert_eq!(session.app_name, "Chat");
+        assert_eq!(session.status, SessionStatus::Completed);
+
+        assert!(manager
+            .add_alias_rule(&AliasRule::new(AliasMatch::Regex, "(", "Broken"))
+            .is_err());
+    }
+
+    #[test]
+    fn test_orphans_closed_at_last_heartbeat() {
+        let (manager, path) = manager("orphans");
+        let t0 = Utc::now() - Duration::hours(2);
+
+        manager
+            .start_session("FaceTime".into(), None, None, Sensor::Camera, t0)
+            .unwrap();
+        manager
+            .start_session("zoom.us".into(), None, None, Sensor::Camera, t0)
+            .unwrap();
+        let ids: Vec<i64> = manager
+            .get_all_sessions()
+            .unwrap()
+            .iter()
+            .filter_map(|s| s.id)
+            .collect();
+        storage::touch_sessions(&path, &ids[..1], t0 + Duration::minutes(5)).unwrap();
+
+        // A fresh manager, as after a crash and restart
+        let restarted = SessionManager::new(path.clone());
+        let orphans = restarted.get_active_sessions().unwrap();
+        for orphan in &orphans {
+            restarted.adopt_session(orphan, Some(t0 + Duration::minutes(10)));
+        }
+        restarted.end_all_active_sessions(EndReason::Shutdown).unwrap();
+
+        let sessions = restarted.get_all_sessions().unwrap();
+        assert!(sessions.iter().all(|s| s.status == SessionStatus::Completed));
+        assert!(sessions.iter().all(|s| s.end_reason == Some(EndReason::CrashRecovered)));
+
+        // The session with a heartbeat ends there, the other at the last time
+        // the tracker was alive
+        let touched = sessions.iter().find(|s| s.id == Some(ids[0])).unwrap();
+        assert_eq!(touched.duration_secs, Some(300));
+        let untouched = sessions.iter().find(|s| s.id == Some(ids[1])).unwrap();
+        assert_eq!(untouched.duration_secs, Some(600));
+    }
+}
@@ -1,6 +1,9 @@
+pub mod aliases;
+pub mod liveness;
 pub mod manager;
 pub mod models;
+pub mod policy;
 
 pub use manager::SessionManager;
-pub use models::{Session, SessionStatus};
+pub use models::{EndReason, Session, SessionSource, SessionStatus};
 
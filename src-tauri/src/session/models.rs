@@ -1,3 +1,4 @@
+use crate::camera::Sensor;
 use chrono::{DateTime, Utc};
 use serde::{Deserialize, Serialize};
 
@@ -5,10 +6,32 @@ use serde::{Deserialize, Serialize};
 pub struct Session {
     pub id: Option<i64>,
     pub app_name: String,
+    /// The name the source reported, before alias rules were applied.
+    pub raw_app_name: Option<String>,
+    /// Bundle identifier (e.g. `us.zoom.xos`), when the source reported one.
+    pub bundle_id: Option<String>,
+    /// PID of the process that used the sensor, when the source reported one.
+    pub pid: Option<u32>,
+    pub sensor: Sensor,
+    /// Origins (e.g. log subsystems) that reported this session, when the
+    /// source reconciles several of them.
+    pub confirmed_by: Vec<String>,
+    pub source: SessionSource,
     pub start_time: DateTime<Utc>,
     pub end_time: Option<DateTime<Utc>>,
     pub duration_secs: Option<i64>,
     pub status: SessionStatus,
+    /// Last heartbeat written while the session was running.
+    pub last_seen: Option<DateTime<Utc>>,
+    /// Why the session ended; `None` while it is running, and for sessions
+    /// recorded before end reasons were.
+    pub end_reason: Option<EndReason>,
+    /// Stops that were followed by a start within the grace period and so
+    /// merged into this session instead of splitting it.
+    pub merged_interruptions: u32,
+    /// The screen was locked at some point while the session was running,
+    /// so the user may have been away while the sensor was in use.
+    pub screen_locked: bool,
 }
 
 #[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
@@ -18,20 +41,110 @@ pub enum SessionStatus {
     Completed,
 }
 
+/// How a session came to be recorded.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum SessionSource {
+    /// Observed by the running camera monitor.
+    Live,
+    /// Reconstructed from historical logs by the backfill job.
+    Backfill,
+    /// Recomputed from the event journal by `rebuild_sessions`.
+    Rebuilt,
+}
+
+impl std::fmt::Display for SessionSource {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            SessionSource::Live => write!(f, "live"),
+            SessionSource::Backfill => write!(f, "backfill"),
+            SessionSource::Rebuilt => write!(f, "rebuilt"),
+        }
+    }
+}
+
+/// Why a session ended.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum EndReason {
+    /// The source reported the app stopped using the sensor.
+    Stopped,
+    /// The same app instance started the sensor again after its earlier
+    /// starts had gone unbalanced for too long, so the stale session was
+    /// closed at the new start.
+    Restarted,
+    /// Tracking was paused (or resumed) while the session was running.
+    Paused,
+    /// The app's window was closed while the session was running.
+    Shutdown,
+    /// Left running by a run that crashed or was killed; closed on the next
+    /// launch at its last heartbeat.
+    CrashRecovered,
+    /// Still running at the end of the window a log replay covered.
+    ReplayEnd,
+    /// No stop was seen, but the app's process is no longer running.
+    ProcessExited,
+    /// No stop was seen, but ControlCenter no longer lists the app as using
+    /// the sensor.
+    AttributionLost,
+    /// The machine went to sleep. If the app was still using the sensor on
+    /// wake, a new session continues from the wake time.
+    Sleep,
+    /// Every start went unbalanced by a stop for `UNBALANCED_START_TIMEOUT`,
+    /// so the session was closed at the last start or stop seen for it.
+    StartTimedOut,
+}
+
+impl EndReason {
+    /// Whether the end time is where tracking stopped looking rather than
+    /// where the sensor stopped, so the duration is not a real measurement.
+    pub fn is_truncated(&self) -> bool {
+        !matches!(self, EndReason::Stopped | EndReason::Sleep)
+    }
+}
+
+impl std::fmt::Display for EndReason {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            EndReason::Stopped => write!(f, "stopped"),
+            EndReason::Restarted => write!(f, "restarted"),
+            EndReason::Paused => write!(f, "paused"),
+            EndReason::Shutdown => write!(f, "shutdown"),
+            EndReason::CrashRecovered => write!(f, "crash_recovered"),
+            EndReason::ReplayEnd => write!(f, "replay_end"),
+            EndReason::ProcessExited => write!(f, "process_exited"),
+            EndReason::AttributionLost => write!(f, "attribution_lost"),
+            EndReason::Sleep => write!(f, "sleep"),
+            EndReason::StartTimedOut => write!(f, "start_timed_out"),
+        }
+    }
+}
+
 impl Session {
-    pub fn new(app_name: String, start_time: DateTime<Utc>) -> Self {
+    pub fn new(app_name: String, sensor: Sensor, start_time: DateTime<Utc>) -> Self {
         Self {
             id: None,
             app_name,
+            raw_app_name: None,
+            bundle_id: None,
+            pid: None,
+            sensor,
+            confirmed_by: Vec::new(),
+            source: SessionSource::Live,
             start_time,
             end_time: None,
             duration_secs: None,
             status: SessionStatus::Running,
+            last_seen: None,
+            end_reason: None,
+            merged_interruptions: 0,
+            screen_locked: false,
         }
     }
 
-    pub fn close(&mut self, end_time: DateTime<Utc>) {
+    pub fn close(&mut self, end_time: DateTime<Utc>, end_reason: EndReason) {
         self.end_time = Some(end_time);
+        self.end_reason = Some(end_reason);
         self.duration_secs = Some(
             (end_time - self.start_time)
                 .num_seconds()
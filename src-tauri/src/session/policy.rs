@@ -0,0 +1,50 @@
+use anyhow::Result;
+use serde::{Deserialize, Serialize};
+
+// ---------------------------------------------------------------------------
+// Per-app policies
+// ---------------------------------------------------------------------------
+
+/// What the tracker does when an app starts using a sensor.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum AppPolicy {
+    /// Record its sessions. Apps without a policy are allowed.
+    #[default]
+    Allow,
+    /// Record nothing for it.
+    Ignore,
+    /// Record its sessions and raise an alert when one starts.
+    Flag,
+}
+
+impl std::fmt::Display for AppPolicy {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            AppPolicy::Allow => write!(f, "allow"),
+            AppPolicy::Ignore => write!(f, "ignore"),
+            AppPolicy::Flag => write!(f, "flag"),
+        }
+    }
+}
+
+impl std::str::FromStr for AppPolicy {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        match s.trim().to_ascii_lowercase().as_str() {
+            "allow" => Ok(AppPolicy::Allow),
+            "ignore" => Ok(AppPolicy::Ignore),
+            "flag" => Ok(AppPolicy::Flag),
+            other => anyhow::bail!("Unknown app policy: {}", other),
+        }
+    }
+}
+
+/// The policy set for one app, by the name its sessions are recorded
+/// under (after alias rules), ignoring case.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct AppPolicyEntry {
+    pub app_name: String,
+    pub policy: AppPolicy,
+}
@@ -1,9 +1,25 @@
-use crate::session::models::{Session, SessionStatus};
+use crate::camera::journal::{EventKind, JournaledEvent};
+use crate::camera::Sensor;
+use crate::notifications::rules::{NotifyRule, NotifyRuleEntry, QuietHours};
+use crate::session::aliases::{AliasMatch, AliasRule, Aliases, DEFAULT_RULES_VERSION};
+use crate::session::models::{EndReason, Session, SessionSource, SessionStatus};
+use crate::session::policy::{AppPolicy, AppPolicyEntry};
 use anyhow::{Context, Result};
 use chrono::{DateTime, Utc};
-use rusqlite::{params, Connection};
+use rusqlite::{params, Connection, OptionalExtension, Row};
 use std::path::Path;
 
+/// Columns selected for every `Session` query, in the order `row_to_session`
+/// expects them.
+const SESSION_COLUMNS: &str =
+    "id, app_name, start_time, end_time, duration_secs, status, sensor, bundle_id, pid, \
+     confirmed_by, source, last_seen, end_reason, merged_interruptions, screen_locked, \
+     raw_app_name";
+
+/// Meta key recording which `DEFAULT_RULES_VERSION` stored app names were
+/// last normalized with.
+const META_ALIAS_VERSION: &str = "alias_rules_version";
+
 pub fn init_db(db_path: &Path) -> Result<()> {
     let conn = Connection::open(db_path)?;
     
@@ -18,29 +34,220 @@ pub fn init_db(db_path: &Path) -> Result<()> {
         )",
         [],
     )?;
+
+    // Small key/value store for settings and cached state
+    conn.execute(
+        "CREATE TABLE IF NOT EXISTS meta (
+            key TEXT PRIMARY KEY,
+            value TEXT NOT NULL
+        )",
+        [],
+    )?;
+
+    // Append-only journal of every parsed camera event. The same log line
+    // is never journaled twice (e.g. by a live run and a later replay).
+    conn.execute(
+        "CREATE TABLE IF NOT EXISTS camera_events (
+            id INTEGER PRIMARY KEY AUTOINCREMENT,
+            kind TEXT NOT NULL,
+            app_name TEXT NOT NULL,
+            bundle_id TEXT,
+            pid INTEGER,
+            sensor TEXT NOT NULL,
+            subsystem TEXT NOT NULL,
+            log_timestamp TEXT NOT NULL,
+            received_at TEXT NOT NULL,
+            line_hash TEXT,
+            UNIQUE (line_hash, kind, app_name, sensor)
+        )",
+        [],
+    )?;
+    conn.execute(
+        "CREATE INDEX IF NOT EXISTS camera_events_log_timestamp
+         ON camera_events (log_timestamp)",
+        [],
+    )?;
+
+    // Columns added after the initial release. Existing databases are
+    // upgraded in place; fresh ones get them straight away.
+    add_column_if_missing(&conn, "sessions", "sensor", "TEXT NOT NULL DEFAULT 'camera'")?;
+    add_column_if_missing(&conn, "sessions", "bundle_id", "TEXT")?;
+    add_column_if_missing(&conn, "sessions", "pid", "INTEGER")?;
+    add_column_if_missing(&conn, "sessions", "confirmed_by", "TEXT")?;
+    add_column_if_missing(&conn, "sessions", "source", "TEXT NOT NULL DEFAULT 'live'")?;
+    add_column_if_missing(&conn, "sessions", "last_seen", "TEXT")?;
+    add_column_if_missing(&conn, "sessions", "end_reason", "TEXT")?;
+    add_column_if_missing(
+        &conn,
+        "sessions",
+        "merged_interruptions",
+        "INTEGER NOT NULL DEFAULT 0",
+    )?;
+    add_column_if_missing(&conn, "sessions", "screen_locked", "INTEGER NOT NULL DEFAULT 0")?;
+    add_column_if_missing(&conn, "sessions", "raw_app_name", "TEXT")?;
+
+    // User rules mapping the names sources report to one name per app
+    conn.execute(
+        "CREATE TABLE IF NOT EXISTS app_aliases (
+            id INTEGER PRIMARY KEY AUTOINCREMENT,
+            kind TEXT NOT NULL,
+            pattern TEXT NOT NULL,
+            app_name TEXT NOT NULL
+        )",
+        [],
+    )?;
+
+    // Per-app allow/ignore/flag policies, by normalized app name
+    conn.execute(
+        "CREATE TABLE IF NOT EXISTS app_policies (
+            app_name TEXT PRIMARY KEY COLLATE NOCASE,
+            policy TEXT NOT NULL
+        )",
+        [],
+    )?;
+
+    // Per-app desktop notification rules, by normalized app name
+    conn.execute(
+        "CREATE TABLE IF NOT EXISTS notification_rules (
+            app_name TEXT PRIMARY KEY COLLATE NOCASE,
+            rule TEXT NOT NULL
+        )",
+        [],
+    )?;
+
+    // Weekly windows during which notifications are held back
+    conn.execute(
+        "CREATE TABLE IF NOT EXISTS quiet_hours (
+            id INTEGER PRIMARY KEY AUTOINCREMENT,
+            days TEXT NOT NULL,
+            start_time TEXT NOT NULL,
+            end_time TEXT NOT NULL
+        )",
+        [],
+    )?;
+    drop(conn);
+
+    // Sessions recorded before the current built-in alias rules are named
+    // with them too
+    let normalized_with: Option<u32> = get_meta(db_path, META_ALIAS_VERSION)?
+        .and_then(|version| version.parse().ok());
+    if normalized_with != Some(DEFAULT_RULES_VERSION) {
+        let aliases = Aliases::new(get_alias_rules(db_path)?);
+        renormalize_sessions(db_path, &aliases)?;
+        set_meta(db_path, META_ALIAS_VERSION, &DEFAULT_RULES_VERSION.to_string())?;
+    }
     
     Ok(())
 }
 
+/// Adds `column` to `table` unless it already exists.
+fn add_column_if_missing(
+    conn: &Connection,
+    table: &str,
+    column: &str,
+    definition: &str,
+) -> Result<()> {
+    let mut stmt = conn.prepare(&format!("PRAGMA table_info({})", table))?;
+    let exists = stmt
+        .query_map([], |row| row.get::<_, String>(1))?
+        .filter_map(|name| name.ok())
+        .any(|name| name == column);
+
+    if !exists {
+        conn.execute(
+            &format!("ALTER TABLE {} ADD COLUMN {} {}", table, column, definition),
+            [],
+        )?;
+    }
+
+    Ok(())
+}
+
+pub fn get_meta(db_path: &Path, key: &str) -> Result<Option<String>> {
+    let conn = Connection::open(db_path)?;
+
+    let value = conn
+        .query_row("SELECT value FROM meta WHERE key = ?1", params![key], |row| {
+            row.get(0)
+        })
+        .optional()?;
+
+    Ok(value)
+}
+
+pub fn set_meta(db_path: &Path, key: &str, value: &str) -> Result<()> {
+    let conn = Connection::open(db_path)?;
+
+    conn.execute(
+        "INSERT INTO meta (key, value) VALUES (?1, ?2)
+         ON CONFLICT(key) DO UPDATE SET value = excluded.value",
+        params![key, value],
+    )?;
+
+    Ok(())
+}
+
 pub fn insert_session(db_path: &Path, session: &Session) -> Result<i64> {
     let conn = Connection::open(db_path)?;
     
     conn.execute(
-        "INSERT INTO sessions (app_name, start_time, end_time, duration_secs, status)
-         VALUES (?1, ?2, ?3, ?4, ?5)",
+        "INSERT INTO sessions (app_name, start_time, end_time, duration_secs, status, sensor,
+                               bundle_id, pid, confirmed_by, source, last_seen, end_reason,
+                               merged_interruptions, screen_locked, raw_app_name)
+         VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10, ?11, ?12, ?13, ?14, ?15)",
         params![
             session.app_name,
             session.start_time.to_rfc3339(),
             session.end_time.map(|t| t.to_rfc3339()),
             session.duration_secs,
             status_to_string(&session.status),
+            sensor_to_string(&session.sensor),
+            session.bundle_id,
+            session.pid,
+            sources_to_string(&session.confirmed_by),
+            session.source.to_string(),
+            session.last_seen.map(|t| t.to_rfc3339()),
+            session.end_reason.map(|r| r.to_string()),
+            session.merged_interruptions,
+            session.screen_locked,
+            session.raw_app_name,
         ],
     )?;
     
     Ok(conn.last_insert_rowid())
 }
 
-pub fn update_session(db_path: &Path, id: i64, end_time: DateTime<Utc>) -> Result<()> {
+/// Records that `source` confirmed the session, unless it already had.
+pub fn add_session_source(db_path: &Path, id: i64, source: &str) -> Result<()> {
+    let conn = Connection::open(db_path)?;
+
+    let current: Option<String> = conn.query_row(
+        "SELECT confirmed_by FROM sessions WHERE id = ?1",
+        params![id],
+        |row| row.get(0),
+    )?;
+
+    let mut sources = string_to_sources(current);
+    if sources.iter().any(|s| s == source) {
+        return Ok(());
+    }
+    sources.push(source.to_string());
+
+    conn.execute(
+        "UPDATE sessions SET confirmed_by = ?1 WHERE id = ?2",
+        params![sources_to_string(&sources), id],
+    )?;
+
+    Ok(())
+}
+
+/// Completes the session at `end_time`, recording why it ended.
+pub fn update_session(
+    db_path: &Path,
+    id: i64,
+    end_time: DateTime<Utc>,
+    end_reason: EndReason,
+) -> Result<()> {
     let conn = Connection::open(db_path)?;
     
     let start_time_str: String = conn.query_row(
@@ -56,11 +263,13 @@ pub fn update_session(db_path: &Path, id: i64, end_time: DateTime<Utc>) -> Resul
     let duration_secs = (end_time - start_time).num_seconds().max(0);
     
     conn.execute(
-        "UPDATE sessions SET end_time = ?1, duration_secs = ?2, status = ?3 WHERE id = ?4",
+        "UPDATE sessions SET end_time = ?1, duration_secs = ?2, status = ?3, end_reason = ?4
+         WHERE id = ?5",
         params![
             end_time.to_rfc3339(),
             duration_secs,
             status_to_string(&SessionStatus::Completed),
+            end_reason.to_string(),
             id,
         ],
     )?;
@@ -68,26 +277,69 @@ pub fn update_session(db_path: &Path, id: i64, end_time: DateTime<Utc>) -> Resul
     Ok(())
 }
 
-pub fn get_all_sessions(db_path: &Path) -> Result<Vec<Session>> {
+/// Records the PID of the instance a session turned out to belong to.
+pub fn set_session_pid(db_path: &Path, id: i64, pid: u32) -> Result<()> {
     let conn = Connection::open(db_path)?;
-    let mut stmt = conn.prepare(
-        "SELECT id, app_name, start_time, end_time, duration_secs, status FROM sessions
-         ORDER BY start_time DESC",
+
+    conn.execute("UPDATE sessions SET pid = ?1 WHERE id = ?2", params![pid, id])?;
+
+    Ok(())
+}
+
+/// Flags the given running sessions as having run while the screen was
+/// locked.
+pub fn mark_screen_locked(db_path: &Path, ids: &[i64]) -> Result<()> {
+    let conn = Connection::open(db_path)?;
+
+    for id in ids {
+        conn.execute(
+            "UPDATE sessions SET screen_locked = 1 WHERE id = ?1 AND status = 'running'",
+            params![id],
+        )?;
+    }
+
+    Ok(())
+}
+
+/// Puts a session ended by a stop back to running, counting the stop as a
+/// merged interruption.
+pub fn reopen_session(db_path: &Path, id: i64) -> Result<()> {
+    let conn = Connection::open(db_path)?;
+
+    conn.execute(
+        "UPDATE sessions
+         SET end_time = NULL, duration_secs = NULL, status = ?1, end_reason = NULL,
+             merged_interruptions = merged_interruptions + 1
+         WHERE id = ?2",
+        params![status_to_string(&SessionStatus::Running), id],
     )?;
+
+    Ok(())
+}
+
+/// Records a heartbeat at `now` on the given running sessions.
+pub fn touch_sessions(db_path: &Path, ids: &[i64], now: DateTime<Utc>) -> Result<()> {
+    let conn = Connection::open(db_path)?;
+    let now = now.to_rfc3339();
+
+    for id in ids {
+        conn.execute(
+            "UPDATE sessions SET last_seen = ?1 WHERE id = ?2 AND status = 'running'",
+            params![now, id],
+        )?;
+    }
+
+    Ok(())
+}
+
+pub fn get_all_sessions(db_path: &Path) -> Result<Vec<Session>> {
+    let conn = Connection::open(db_path)?;
+    let mut stmt = conn.prepare(&format!(
+        "SELECT {} FROM sessions ORDER BY start_time DESC",
+        SESSION_COLUMNS
+    ))?;
     
-    let session_iter = stmt.query_map([], |row| {
-        Ok(Session {
-            id: Some(row.get(0)?),
-            app_name: row.get(1)?,
-            start_time: DateTime::parse_from_rfc3339(&row.get::<_, String>(2)?)
-                .unwrap()
-                .with_timezone(&Utc),
-            end_time: row.get::<_, Option<String>>(3)?
-                .map(|s| DateTime::parse_from_rfc3339(&s).unwrap().with_timezone(&Utc)),
-            duration_secs: row.get(4)?,
-            status: string_to_status(&row.get::<_, String>(5)?),
-        })
-    })?;
+    let session_iter = stmt.query_map([], row_to_session)?;
     
     let mut sessions = Vec::new();
     for session in session_iter {
@@ -97,27 +349,57 @@ pub fn get_all_sessions(db_path: &Path) -> Result<Vec<Session>> {
     Ok(sessions)
 }
 
-pub fn get_active_sessions(db_path: &Path) -> Result<Vec<Session>> {
+/// Start time of the oldest recorded session, if there is any.
+pub fn earliest_session_start(db_path: &Path) -> Result<Option<DateTime<Utc>>> {
     let conn = Connection::open(db_path)?;
 
-    let mut stmt = conn.prepare(
-        "SELECT id, app_name, start_time, end_time, duration_secs, status FROM sessions
-         WHERE status = 'running' ORDER BY start_time DESC",
-    )?;
-
-    let session_iter = stmt.query_map([], |row| {
-        Ok(Session {
-            id: Some(row.get(0)?),
-            app_name: row.get(1)?,
-            start_time: DateTime::parse_from_rfc3339(&row.get::<_, String>(2)?)
-                .unwrap()
-                .with_timezone(&Utc),
-            end_time: row.get::<_, Option<String>>(3)?
-                .map(|s| DateTime::parse_from_rfc3339(&s).unwrap().with_timezone(&Utc)),
-            duration_secs: row.get(4)?,
-            status: string_to_status(&row.get::<_, String>(5)?),
+    let earliest: Option<String> =
+        conn.query_row("SELECT MIN(start_time) FROM sessions", [], |row| row.get(0))?;
+
+    earliest
+        .map(|s| {
+            DateTime::parse_from_rfc3339(&s)
+                .map(|t| t.with_timezone(&Utc))
+                .context("Failed to parse start_time")
         })
-    })?;
+        .transpose()
+}
+
+/// Sessions that started within `[start, end)`, oldest first.
+pub fn get_sessions_between(
+    db_path: &Path,
+    start: DateTime<Utc>,
+    end: DateTime<Utc>,
+) -> Result<Vec<Session>> {
+    let conn = Connection::open(db_path)?;
+
+    let mut stmt = conn.prepare(&format!(
+        "SELECT {} FROM sessions WHERE start_time >= ?1 AND start_time < ?2 ORDER BY start_time",
+        SESSION_COLUMNS
+    ))?;
+
+    let session_iter = stmt.query_map(
+        params![start.to_rfc3339(), end.to_rfc3339()],
+        row_to_session,
+    )?;
+
+    let mut sessions = Vec::new();
+    for session in session_iter {
+        sessions.push(session?);
+    }
+
+    Ok(sessions)
+}
+
+pub fn get_active_sessions(db_path: &Path) -> Result<Vec<Session>> {
+    let conn = Connection::open(db_path)?;
+
+    let mut stmt = conn.prepare(&format!(
+        "SELECT {} FROM sessions WHERE status = 'running' ORDER BY start_time DESC",
+        SESSION_COLUMNS
+    ))?;
+
+    let session_iter = stmt.query_map([], row_to_session)?;
 
     let mut sessions = Vec::new();
     for session in session_iter {
@@ -127,6 +409,507 @@ pub fn get_active_sessions(db_path: &Path) -> Result<Vec<Session>> {
     Ok(sessions)
 }
 
+pub fn get_session(db_path: &Path, id: i64) -> Result<Option<Session>> {
+    let conn = Connection::open(db_path)?;
+
+    let session = conn
+        .query_row(
+            &format!("SELECT {} FROM sessions WHERE id = ?1", SESSION_COLUMNS),
+            params![id],
+            row_to_session,
+        )
+        .optional()?;
+
+    Ok(session)
+}
+
+/// Appends `event` to the journal. Returns `false` if its line was already
+/// journaled.
+pub fn insert_camera_event(db_path: &Path, event: &JournaledEvent) -> Result<bool> {
+    let conn = Connection::open(db_path)?;
+
+    let inserted = conn.execute(
+        "INSERT OR IGNORE INTO camera_events (kind, app_name, bundle_id, pid, sensor, subsystem,
+                                              log_timestamp, received_at, line_hash)
+         VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9)",
+        params![
+            event.kind.to_string(),
+            event.app_name,
+            event.bundle_id,
+            event.pid,
+            sensor_to_string(&event.sensor),
+            event.subsystem,
+            event.log_timestamp.to_rfc3339(),
+            event.received_at.to_rfc3339(),
+            event.line_hash,
+        ],
+    )?;
+
+    Ok(inserted > 0)
+}
+
+/// Journaled events that are evidence for `session`: same app (by name or
+/// bundle ID) and sensor, a compatible PID, and logged while it ran.
+pub fn get_session_events(db_path: &Path, session: &Session) -> Result<Vec<JournaledEvent>> {
+    let conn = Connection::open(db_path)?;
+    let end_time = session.end_time.unwrap_or_else(Utc::now);
+
+    let mut stmt = conn.prepare(
+        "SELECT id, kind, app_name, bundle_id, pid, sensor, subsystem, log_timestamp,
+                received_at, line_hash
+         FROM camera_events
+         WHERE (app_name = ?1 COLLATE NOCASE OR app_name = ?7 COLLATE NOCASE
+                OR bundle_id = ?2)
+           AND sensor = ?3
+           AND (?4 IS NULL OR pid IS NULL OR pid = ?4)
+           AND log_timestamp >= ?5 AND log_timestamp <= ?6
+         ORDER BY log_timestamp, id",
+    )?;
+
+    let event_iter = stmt.query_map(
+        params![
+            session.app_name,
+            session.bundle_id,
+            sensor_to_string(&session.sensor),
+            session.pid,
+            session.start_time.to_rfc3339(),
+            end_time.to_rfc3339(),
+            session.raw_app_name,
+        ],
+        row_to_camera_event,
+    )?;
+
+    let mut events = Vec::new();
+    for event in event_iter {
+        events.push(event?);
+    }
+
+    Ok(events)
+}
+
+/// Up to `limit` journaled events in log order, from those logged at `since`
+/// with an ID above `after_id` on. Passing the timestamp and ID of the last
+/// event of a page reads the next one.
+pub fn get_camera_events_page(
+    db_path: &Path,
+    since: DateTime<Utc>,
+    after_id: i64,
+    limit: usize,
+) -> Result<Vec<JournaledEvent>> {
+    let conn = Connection::open(db_path)?;
+
+    let mut stmt = conn.prepare(
+        "SELECT id, kind, app_name, bundle_id, pid, sensor, subsystem, log_timestamp,
+                received_at, line_hash
+         FROM camera_events
+         WHERE log_timestamp > ?1 OR (log_timestamp = ?1 AND id > ?2)
+         ORDER BY log_timestamp, id
+         LIMIT ?3",
+    )?;
+
+    let event_iter = stmt.query_map(
+        params![since.to_rfc3339(), after_id, limit as i64],
+        row_to_camera_event,
+    )?;
+
+    let mut events = Vec::new();
+    for event in event_iter {
+        events.push(event?);
+    }
+
+    Ok(events)
+}
+
+/// The distinct subsystems that journaled events within `[start, end)`.
+pub fn camera_event_subsystems_between(
+    db_path: &Path,
+    start: DateTime<Utc>,
+    end: DateTime<Utc>,
+) -> Result<Vec<String>> {
+    let conn = Connection::open(db_path)?;
+
+    let mut stmt = conn.prepare(
+        "SELECT DISTINCT subsystem FROM camera_events
+         WHERE log_timestamp >= ?1 AND log_timestamp < ?2
+         ORDER BY subsystem",
+    )?;
+
+    let subsystem_iter = stmt.query_map(params![start.to_rfc3339(), end.to_rfc3339()], |row| {
+        row.get::<_, String>(0)
+    })?;
+
+    let mut subsystems = Vec::new();
+    for subsystem in subsystem_iter {
+        subsystems.push(subsystem?);
+    }
+
+    Ok(subsystems)
+}
+
+/// Log timestamp of the oldest journaled event, if there is any.
+pub fn earliest_camera_event(db_path: &Path) -> Result<Option<DateTime<Utc>>> {
+    let conn = Connection::open(db_path)?;
+
+    let earliest: Option<String> = conn.query_row(
+        "SELECT MIN(log_timestamp) FROM camera_events",
+        [],
+        |row| row.get(0),
+    )?;
+
+    earliest
+        .map(|s| {
+            DateTime::parse_from_rfc3339(&s)
+                .map(|t| t.with_timezone(&Utc))
+                .context("Failed to parse log_timestamp")
+        })
+        .transpose()
+}
+
+/// Deletes the sessions with the given IDs in one transaction. Returns how
+/// many were deleted.
+pub fn delete_sessions(db_path: &Path, ids: &[i64]) -> Result<usize> {
+    let mut conn = Connection::open(db_path)?;
+    let tx = conn.transaction()?;
+
+    let mut deleted = 0;
+    for id in ids {
+        deleted += tx.execute("DELETE FROM sessions WHERE id = ?1", params![id])?;
+    }
+    tx.commit()?;
+
+    Ok(deleted)
+}
+
+/// The user's alias rules, in the order they apply.
+pub fn get_alias_rules(db_path: &Path) -> Result<Vec<AliasRule>> {
+    let conn = Connection::open(db_path)?;
+
+    let mut stmt = conn.prepare("SELECT id, kind, pattern, app_name FROM app_aliases ORDER BY id")?;
+    let rule_iter = stmt.query_map([], |row| {
+        Ok((
+            row.get::<_, i64>(0)?,
+            row.get::<_, String>(1)?,
+            row.get::<_, String>(2)?,
+            row.get::<_, String>(3)?,
+        ))
+    })?;
+
+    let mut rules = Vec::new();
+    for rule in rule_iter {
+        let (id, kind, pattern, app_name) = rule?;
+        let kind: AliasMatch = match kind.parse() {
+            Ok(kind) => kind,
+            Err(_) => continue,
+        };
+        rules.push(AliasRule {
+            id: Some(id),
+            kind,
+            pattern,
+            app_name,
+        });
+    }
+
+    Ok(rules)
+}
+
+/// Stores a user alias rule after the existing ones and returns its ID.
+pub fn insert_alias_rule(db_path: &Path, rule: &AliasRule) -> Result<i64> {
+    let conn = Connection::open(db_path)?;
+
+    conn.execute(
+        "INSERT INTO app_aliases (kind, pattern, app_name) VALUES (?1, ?2, ?3)",
+        params![rule.kind.to_string(), rule.pattern, rule.app_name],
+    )?;
+
+    Ok(conn.last_insert_rowid())
+}
+
+/// Deletes a user alias rule. Returns whether it existed.
+pub fn delete_alias_rule(db_path: &Path, id: i64) -> Result<bool> {
+    let conn = Connection::open(db_path)?;
+
+    let deleted = conn.execute("DELETE FROM app_aliases WHERE id = ?1", params![id])?;
+
+    Ok(deleted > 0)
+}
+
+/// Every app policy, by app name.
+pub fn get_app_policies(db_path: &Path) -> Result<Vec<AppPolicyEntry>> {
+    let conn = Connection::open(db_path)?;
+
+    let mut stmt = conn.prepare("SELECT app_name, policy FROM app_policies ORDER BY app_name")?;
+    let entry_iter = stmt.query_map([], |row| {
+        Ok((row.get::<_, String>(0)?, row.get::<_, String>(1)?))
+    })?;
+
+    let mut entries = Vec::new();
+    for entry in entry_iter {
+        let (app_name, policy) = entry?;
+        if let Ok(policy) = policy.parse() {
+            entries.push(AppPolicyEntry { app_name, policy });
+        }
+    }
+
+    Ok(entries)
+}
+
+/// The policy set for `app_name`, ignoring case, if any.
+pub fn get_app_policy(db_path: &Path, app_name: &str) -> Result<Option<AppPolicy>> {
+    let conn = Connection::open(db_path)?;
+
+    let policy: Option<String> = conn
+        .query_row(
+            "SELECT policy FROM app_policies WHERE app_name = ?1",
+            params![app_name],
+            |row| row.get(0),
+        )
+        .optional()?;
+
+    Ok(policy.and_then(|p| p.parse().ok()))
+}
+
+/// Sets the policy for `app_name`, replacing any it had.
+pub fn set_app_policy(db_path: &Path, app_name: &str, policy: AppPolicy) -> Result<()> {
+    let conn = Connection::open(db_path)?;
+
+    conn.execute(
+        "INSERT INTO app_policies (app_name, policy) VALUES (?1, ?2)
+         ON CONFLICT(app_name) DO UPDATE SET policy = excluded.policy",
+        params![app_name, policy.to_string()],
+    )?;
+
+    Ok(())
+}
+
+/// Removes the policy for `app_name`. Returns whether it had one.
+pub fn delete_app_policy(db_path: &Path, app_name: &str) -> Result<bool> {
+    let conn = Connection::open(db_path)?;
+
+    let deleted = conn.execute("DELETE FROM app_policies WHERE app_name = ?1", params![app_name])?;
+
+    Ok(deleted > 0)
+}
+
+/// Every per-app notification rule, by app name.
+pub fn get_notify_rules(db_path: &Path) -> Result<Vec<NotifyRuleEntry>> {
+    let conn = Connection::open(db_path)?;
+
+    let mut stmt = conn.prepare("SELECT app_name, rule FROM notification_rules ORDER BY app_name")?;
+    let entry_iter = stmt.query_map([], |row| {
+        Ok((row.get::<_, String>(0)?, row.get::<_, String>(1)?))
+    })?;
+
+    let mut entries = Vec::new();
+    for entry in entry_iter {
+        let (app_name, rule) = entry?;
+        if let Ok(rule) = rule.parse() {
+            entries.push(NotifyRuleEntry { app_name, rule });
+        }
+    }
+
+    Ok(entries)
+}
+
+/// The notification rule set for `app_name`, ignoring case, if any.
+pub fn get_notify_rule(db_path: &Path, app_name: &str) -> Result<Option<NotifyRule>> {
+    let conn = Connection::open(db_path)?;
+
+    let rule: Option<String> = conn
+        .query_row(
+            "SELECT rule FROM notification_rules WHERE app_name = ?1",
+            params![app_name],
+            |row| row.get(0),
+        )
+        .optional()?;
+
+    Ok(rule.and_then(|r| r.parse().ok()))
+}
+
+/// Sets the notification rule for `app_name`, replacing any it had.
+pub fn set_notify_rule(db_path: &Path, app_name: &str, rule: NotifyRule) -> Result<()> {
+    let conn = Connection::open(db_path)?;
+
+    conn.execute(
+        "INSERT INTO notification_rules (app_name, rule) VALUES (?1, ?2)
+         ON CONFLICT(app_name) DO UPDATE SET rule = excluded.rule",
+        params![app_name, rule.to_string()],
+    )?;
+
+    Ok(())
+}
+
+/// Removes the notification rule for `app_name`. Returns whether it had one.
+pub fn delete_notify_rule(db_path: &Path, app_name: &str) -> Result<bool> {
+    let conn = Connection::open(db_path)?;
+
+    let deleted = conn.execute("DELETE FROM notification_rules WHERE app_name = ?1", params![app_name])?;
+
+    Ok(deleted > 0)
+}
+
+/// Every quiet hours window, oldest first.
+pub fn get_quiet_hours(db_path: &Path) -> Result<Vec<QuietHours>> {
+    let conn = Connection::open(db_path)?;
+
+    let mut stmt = conn.prepare("SELECT id, days, start_time, end_time FROM quiet_hours ORDER BY id")?;
+    let window_iter = stmt.query_map([], |row| {
+        Ok((
+            row.get::<_, i64>(0)?,
+            row.get::<_, String>(1)?,
+            row.get::<_, String>(2)?,
+            row.get::<_, String>(3)?,
+        ))
+    })?;
+
+    let mut windows = Vec::new();
+    for window in window_iter {
+        let (id, days, start, end) = window?;
+        if let Ok(mut window) = QuietHours::parse(&days, &start, &end) {
+            window.id = Some(id);
+            windows.push(window);
+        }
+    }
+
+    Ok(windows)
+}
+
+/// Stores a quiet hours window and returns its ID.
+pub fn insert_quiet_hours(db_path: &Path, window: &QuietHours) -> Result<i64> {
+    let conn = Connection::open(db_path)?;
+
+    conn.execute(
+        "INSERT INTO quiet_hours (days, start_time, end_time) VALUES (?1, ?2, ?3)",
+        params![
+            window.days_to_string(),
+            window.start.format("%H:%M").to_string(),
+            window.end.format("%H:%M").to_string(),
+        ],
+    )?;
+
+    Ok(conn.last_insert_rowid())
+}
+
+/// Deletes a quiet hours window. Returns whether it existed.
+pub fn delete_quiet_hours(db_path: &Path, id: i64) -> Result<bool> {
+    let conn = Connection::open(db_path)?;
+
+    let deleted = conn.execute("DELETE FROM quiet_hours WHERE id = ?1", params![id])?;
+
+    Ok(deleted > 0)
+}
+
+/// Whether a session of `app_name` on `sensor` started before `before`.
+pub fn has_earlier_session(
+    db_path: &Path,
+    app_name: &str,
+    sensor: &Sensor,
+    before: DateTime<Utc>,
+) -> Result<bool> {
+    let conn = Connection::open(db_path)?;
+
+    let exists = conn.query_row(
+        "SELECT EXISTS (SELECT 1 FROM sessions
+         WHERE app_name = ?1 COLLATE NOCASE AND sensor = ?2 AND start_time < ?3)",
+        params![app_name, sensor_to_string(sensor), before.to_rfc3339()],
+        |row| row.get(0),
+    )?;
+
+    Ok(exists)
+}
+
+/// Renames every session to what `aliases` make of the name its source
+/// reported, keeping that name in `raw_app_name`. Returns the number of
+/// sessions renamed.
+pub fn renormalize_sessions(db_path: &Path, aliases: &Aliases) -> Result<usize> {
+    let mut conn = Connection::open(db_path)?;
+    let tx = conn.transaction()?;
+
+    // Rows from before raw names were kept still have theirs in app_name
+    tx.execute(
+        "UPDATE sessions SET raw_app_name = app_name WHERE raw_app_name IS NULL",
+        [],
+    )?;
+
+    let rows: Vec<(i64, String, String, Option<String>)> = {
+        let mut stmt = tx.prepare("SELECT id, app_name, raw_app_name, bundle_id FROM sessions")?;
+        let rows = stmt.query_map([], |row| {
+            Ok((row.get(0)?, row.get(1)?, row.get(2)?, row.get(3)?))
+        })?;
+        rows.collect::<rusqlite::Result<_>>()?
+    };
+
+    let mut renamed = 0;
+    for (id, app_name, raw_app_name, bundle_id) in rows {
+        let normalized = aliases.normalize(&raw_app_name, bundle_id.as_deref());
+        if normalized != app_name {
+            tx.execute(
+                "UPDATE sessions SET app_name = ?1 WHERE id = ?2",
+                params![normalized, id],
+            )?;
+            renamed += 1;
+        }
+    }
+    tx.commit()?;
+
+    Ok(renamed)
+}
+
+fn row_to_camera_event(row: &Row) -> rusqlite::Result<JournaledEvent> {
+    Ok(JournaledEvent {
+        id: Some(row.get(0)?),
+        kind: match row.get::<_, String>(1)?.as_str() {
+            "started" => EventKind::Started,
+            _ => EventKind::Stopped,
+        },
+        app_name: row.get(2)?,
+        bundle_id: row.get(3)?,
+        pid: row.get(4)?,
+        sensor: string_to_sensor(&row.get::<_, String>(5)?),
+        subsystem: row.get(6)?,
+        log_timestamp: timestamp_column(row, 7)?,
+        received_at: timestamp_column(row, 8)?,
+        line_hash: row.get(9)?,
+    })
+}
+
+/// Reads an RFC 3339 timestamp from column `idx`, failing the row rather
+/// than panicking when it is malformed.
+fn timestamp_column(row: &Row, idx: usize) -> rusqlite::Result<DateTime<Utc>> {
+    let value: String = row.get(idx)?;
+    DateTime::parse_from_rfc3339(&value)
+        .map(|t| t.with_timezone(&Utc))
+        .map_err(|e| {
+            rusqlite::Error::FromSqlConversionFailure(idx, rusqlite::types::Type::Text, Box::new(e))
+        })
+}
+
+/// Maps a row selected with `SESSION_COLUMNS` to a `Session`.
+fn row_to_session(row: &Row) -> rusqlite::Result<Session> {
+    Ok(Session {
+        id: Some(row.get(0)?),
+        app_name: row.get(1)?,
+        bundle_id: row.get(7)?,
+        pid: row.get(8)?,
+        confirmed_by: string_to_sources(row.get(9)?),
+        source: string_to_source(&row.get::<_, String>(10)?),
+        sensor: string_to_sensor(&row.get::<_, String>(6)?),
+        start_time: DateTime::parse_from_rfc3339(&row.get::<_, String>(2)?)
+            .unwrap()
+            .with_timezone(&Utc),
+        end_time: row.get::<_, Option<String>>(3)?
+            .map(|s| DateTime::parse_from_rfc3339(&s).unwrap().with_timezone(&Utc)),
+        duration_secs: row.get(4)?,
+        status: string_to_status(&row.get::<_, String>(5)?),
+        last_seen: row.get::<_, Option<String>>(11)?
+            .map(|s| DateTime::parse_from_rfc3339(&s).unwrap().with_timezone(&Utc)),
+        end_reason: row.get::<_, Option<String>>(12)?
+            .and_then(|s| string_to_end_reason(&s)),
+        merged_interruptions: row.get(13)?,
+        screen_locked: row.get(14)?,
+        raw_app_name: row.get(15)?,
+    })
+}
+
 fn status_to_string(status: &SessionStatus) -> &str {
     match status {
         SessionStatus::Running => "running",
@@ -142,3 +925,187 @@ fn string_to_status(s: &str) -> SessionStatus {
     }
 }
 
+fn sensor_to_string(sensor: &Sensor) -> &str {
+    match sensor {
+        Sensor::Camera => "camera",
+        Sensor::Microphone => "microphone",
+    }
+}
+
+fn string_to_sensor(s: &str) -> Sensor {
+    match s {
+        "microphone" => Sensor::Microphone,
+        _ => Sensor::Camera,
+    }
+}
+
+fn string_to_source(s: &str) -> SessionSource {
+    match s {
+        "backfill" => SessionSource::Backfill,
+        "rebuilt" => SessionSource::Rebuilt,
+        _ => SessionSource::Live,
+    }
+}
+
+fn string_to_end_reason(s: &str) -> Option<EndReason> {
+    match s {
+        "stopped" => Some(EndReason::Stopped),
+        "restarted" => Some(EndReason::Restarted),
+        "paused" => Some(EndReason::Paused),
+        "shutdown" => Some(EndReason::Shutdown),
+        "crash_recovered" => Some(EndReason::CrashRecovered),
+        "replay_end" => Some(EndReason::ReplayEnd),
+        "process_exited" => Some(EndReason::ProcessExited),
+        "attribution_lost" => Some(EndReason::AttributionLost),
+        "sleep" => Some(EndReason::Sleep),
+        "start_timed_out" => Some(EndReason::StartTimedOut),
+        _ => None,
+    }
+}
+
+/// Stores a list of sources as a comma-separated string, or NULL when empty.
+fn sources_to_string(sources: &[String]) -> Option<String> {
+    if sources.is_empty() {
+        None
+    } else {
+        Some(sources.join(","))
+    }
+}
+
+fn string_to_sources(s: Option<String>) -> Vec<String> {
+    s.map(|s| {
+        s.split(',')
+            .filter(|source| !source.is_empty())
+            .map(|source| source.to_string())
+            .collect()
+    })
+    .unwrap_or_default()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::storage::{scratch_db, scratch_path};
+
+    #[test]
+    fn test_init_db_upgrades_original_schema() {
+        let path = scratch_path("db-upgrade");
+        {
+            let conn = Connection::open(&path).unwrap();
+            conn.execute(
+                "CREATE TABLE sessions (
+                    id INTEGER PRIMARY KEY AUTOINCREMENT,
+                    app_name TEXT NOT NULL,
+                    start_time TEXT NOT NULL,
+                    end_time TEXT,
+                    duration_secs INTEGER,
+                    status TEXT NOT NULL
+                )",
+                [],
+            )
+            .unwrap();
+            conn.execute(
+                "INSERT INTO sessions (app_name, start_time, status)
+                 VALUES ('FaceTime', '2025-06-10T21:23:01+00:00', 'running')",
+                [],
+            )
+            .unwrap();
+        }
+
+        init_db(&path).unwrap();
+        // Running twice must be a no-op
+        init_db(&path).unwrap();
+
+        let sessions = get_all_sessions(&path).unwrap();
+        assert_eq!(sessions.len(), 1);
+        assert_eq!(sessions[0].sensor, Sensor::Camera);
+        assert_eq!(sessions[0].bundle_id, None);
+    }
+
+    #[test]
+    fn test_sensor_round_trips() {
+        let path = scratch_db("db-sensor");
+
+        let session = Session::new("zoom.us".to_string(), Sensor::Microphone, Utc::now());
+        insert_session(&path, &session).unwrap();
+
+        let sessions = get_all_sessions(&path).unwrap();
+        assert_eq!(sessions[0].sensor, Sensor::Microphone);
+    }
+
+    #[test]
+    fn test_add_session_source_appends_once() {
+        let path = scratch_db("db-sources");
+
+        let session = Session::new("FaceTime".to_string(), Sensor::Camera, Utc::now());
+        let id = insert_session(&path, &session).unwrap();
+        add_session_source(&path, id, "cameracapture").unwrap();
+        add_session_source(&path, id, "controlcenter").unwrap();
+        add_session_source(&path, id, "cameracapture").unwrap();
+
+        let sessions = get_all_sessions(&path).unwrap();
+        assert_eq!(sessions[0].confirmed_by, vec!["cameracapture", "controlcenter"]);
+    }
+
+    #[test]
+    fn test_meta_round_trips() {
+        let path = scratch_db("db-meta");
+
+        assert_eq!(get_meta(&path, "detected_subsystem").unwrap(), None);
+        set_meta(&path, "detected_subsystem", "cmio").unwrap();
+        set_meta(&path, "detected_subsystem", "controlcenter").unwrap();
+        assert_eq!(
+            get_meta(&path, "detected_subsystem").unwrap().as_deref(),
+            Some("controlcenter")
+        );
+    }
+
+    #[test]
+    fn test_session_events_match_app_and_time() {
+        let path = scratch_db("db-events");
+        let t0 = Utc::now() - chrono::Duration::minutes(10);
+
+        let event = |kind, app: &str, pid, minutes, line: &str| JournaledEvent {
+            id: None,
+            kind,
+            app_name: app.to_string(),
+            bundle_id: None,
+            pid,
+            sensor: Sensor::Camera,
+            subsystem: "cameracapture".to_string(),
+            log_timestamp: t0 + chrono::Duration::minutes(minutes),
+            received_at: Utc::now(),
+            line_hash: Some(line.to_string()),
+        };
+        let first = event(EventKind::Started, "FaceTime", Some(1), 0, "a");
+        assert!(insert_camera_event(&path, &first).unwrap());
+        // The same line journaled again is ignored
+        assert!(!insert_camera_event(&path, &first).unwrap());
+        for other in [
+            event(EventKind::Stopped, "facetime", None, 5, "b"),
+            event(EventKind::Started, "FaceTime", Some(2), 1, "c"),
+            event(EventKind::Started, "zoom.us", None, 1, "d"),
+            event(EventKind::Started, "FaceTime", Some(1), 9, "e"),
+        ] {
+            insert_camera_event(&path, &other).unwrap();
+        }
+
+        let mut session = Session::new("FaceTime".to_string(), Sensor::Camera, t0);
+        session.pid = Some(1);
+        session.end_time = Some(t0 + chrono::Duration::minutes(5));
+        let id = insert_session(&path, &session).unwrap();
+        let session = get_session(&path, id).unwrap().unwrap();
+
+        let events = get_session_events(&path, &session).unwrap();
+        let kinds: Vec<EventKind> = events.iter().map(|e| e.kind).collect();
+        assert_eq!(kinds, vec![EventKind::Started, EventKind::Stopped]);
+        assert_eq!(events[0].line_hash.as_deref(), Some("a"));
+
+        // A malformed row fails the query instead of panicking
+        Connection::open(&path)
+            .unwrap()
+            .execute("UPDATE camera_events SET received_at = 'garbage' WHERE line_hash = 'a'", [])
+            .unwrap();
+        assert!(get_session_events(&path, &session).is_err());
+    }
+}
@@ -2,3 +2,54 @@ pub mod db;
 
 pub use db::*;
 
+#[cfg(test)]
+use std::path::PathBuf;
+
+/// A throwaway test database file, deleted when dropped. Dereferences to
+/// its path.
+#[cfg(test)]
+pub struct ScratchDb(PathBuf);
+
+#[cfg(test)]
+impl std::ops::Deref for ScratchDb {
+    type Target = PathBuf;
+
+    fn deref(&self) -> &PathBuf {
+        &self.0
+    }
+}
+
+#[cfg(test)]
+impl AsRef<std::path::Path> for ScratchDb {
+    fn as_ref(&self) -> &std::path::Path {
+        &self.0
+    }
+}
+
+#[cfg(test)]
+impl Drop for ScratchDb {
+    fn drop(&mut self) {
+        let _ = std::fs::remove_file(&self.0);
+    }
+}
+
+/// Path of a throwaway test database named after `name`, removed first so
+/// every run starts empty. Names must be unique across the test suite.
+#[cfg(test)]
+pub fn scratch_path(name: &str) -> ScratchDb {
+    let path = std::env::temp_dir().join(format!(
+        "webcam-tracker-{}-{}.db",
+        name,
+        std::process::id()
+    ));
+    let _ = std::fs::remove_file(&path);
+    ScratchDb(path)
+}
+
+/// A freshly initialized throwaway test database at `scratch_path(name)`.
+#[cfg(test)]
+pub fn scratch_db(name: &str) -> ScratchDb {
+    let path = scratch_path(name);
+    init_db(&path).unwrap();
+    path
+}
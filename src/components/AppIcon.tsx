@@ -1,38 +1,41 @@
 import { useState, useEffect } from "react";
 import { getAppIcon } from "../lib/commands";
 
-// Module-level cache so icons are fetched only once per app across all rows
+// Module-level cache so icons are fetched only once per app across all rows.
+// Keyed by bundle ID when known, since display names are not stable.
 const iconCache = new Map<string, string | null>();
 
 interface AppIconProps {
   appName: string;
+  bundleId?: string | null;
   size?: number;
 }
 
-export default function AppIcon({ appName, size = 40 }: AppIconProps) {
+export default function AppIcon({ appName, bundleId = null, size = 40 }: AppIconProps) {
+  const cacheKey = bundleId ?? appName;
   const [iconUrl, setIconUrl] = useState<string | null>(
-    iconCache.get(appName) ?? null
+    iconCache.get(cacheKey) ?? null
   );
-  const [loaded, setLoaded] = useState(iconCache.has(appName));
+  const [loaded, setLoaded] = useState(iconCache.has(cacheKey));
 
   useEffect(() => {
-    if (iconCache.has(appName)) {
-      setIconUrl(iconCache.get(appName) ?? null);
+    if (iconCache.has(cacheKey)) {
+      setIconUrl(iconCache.get(cacheKey) ?? null);
       setLoaded(true);
       return;
     }
 
     let cancelled = false;
 
-    getAppIcon(appName).then((url) => {
+    getAppIcon(appName, bundleId).then((url) => {
       if (!cancelled) {
-        iconCache.set(appName, url);
+        iconCache.set(cacheKey, url);
         setIconUrl(url);
         setLoaded(true);
       }
     }).catch(() => {
       if (!cancelled) {
-        iconCache.set(appName, null);
+        iconCache.set(cacheKey, null);
         setLoaded(true);
       }
     });
@@ -40,7 +43,7 @@ export default function AppIcon({ appName, size = 40 }: AppIconProps) {
     return () => {
       cancelled = true;
     };
-  }, [appName]);
+  }, [appName, bundleId, cacheKey]);
 
   if (!loaded) {
     return (
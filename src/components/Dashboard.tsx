@@ -1,18 +1,30 @@
 import { useState, useEffect } from "react";
 import TrackingControls from "./TrackingControls";
 import TrackingPausedAlert from "./TrackingPausedAlert";
+import FlaggedAppAlert from "./FlaggedAppAlert";
 import StatsCards from "./StatsCards";
 import SessionList from "./SessionList";
 import { getSessions, getTrackingStatus, resumeTracking } from "../lib/commands";
+import { listen } from "@tauri-apps/api/event";
 import type { Session } from "../types/session";
 
 export default function Dashboard() {
   const [sessions, setSessions] = useState<Session[]>([]);
   const [isTracking, setIsTracking] = useState(false);
   const [isLoading, setIsLoading] = useState(true);
+  const [flagged, setFlagged] = useState<Session | null>(null);
 
   useEffect(() => {
     loadData();
+
+    // Sessions of apps whose policy is "flag"
+    const unlisten = listen<Session>("app-flagged", (event) => {
+      setFlagged(event.payload);
+    });
+
+    return () => {
+      unlisten.then((fn) => fn());
+    };
   }, []);
 
   const loadData = async () => {
@@ -68,6 +80,11 @@ export default function Dashboard() {
         <TrackingPausedAlert onResume={handleResumeFromAlert} />
       )}
 
+      {/* Flagged app banner */}
+      {flagged && (
+        <FlaggedAppAlert session={flagged} onDismiss={() => setFlagged(null)} />
+      )}
+
       {/* Header */}
       <header className="sticky top-0 z-50 bg-white/95 backdrop-blur-sm border-b border-neutral-200">
         <div className="max-w-6xl mx-auto px-6 py-4">
@@ -55,7 +55,7 @@ export default function SessionRow({ session }: SessionRowProps) {
       <div className="col-span-12 sm:col-span-4">
         <div className="flex items-center gap-3">
           <div className="relative flex-shrink-0">
-            <AppIcon appName={session.app_name} size={40} />
+            <AppIcon appName={session.app_name} bundleId={session.bundle_id} size={40} />
             {isRunning && (
               <div className="absolute -bottom-0.5 -right-0.5 w-3.5 h-3.5 bg-sage-400 rounded-full border-2 border-white" />
             )}
@@ -65,7 +65,11 @@ export default function SessionRow({ session }: SessionRowProps) {
               {session.app_name}
             </p>
             <p className="text-xs text-neutral-400 truncate">
-              {session.app_name.toLowerCase().replace(/\s+/g, ".")}
+              {session.sensor === "microphone" ? "Microphone" : "Camera"} ·{" "}
+              {session.bundle_id ??
+                session.app_name.toLowerCase().replace(/\s+/g, ".")}
+              {session.source === "backfill" && " · from history"}
+              {session.screen_locked && " · screen locked"}
             </p>
           </div>
         </div>
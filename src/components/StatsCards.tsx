@@ -11,7 +11,8 @@ export default function StatsCards({ sessions }: StatsCardsProps) {
 
   const avgDurationSecs = (() => {
     const completed = sessions.filter(
-      (s) => s.status === "completed" && s.duration_secs !== null
+      (s) =>
+        s.status === "completed" && s.duration_secs !== null && !s.truncated
     );
     if (completed.length === 0) return 0;
     const total = completed.reduce((sum, s) => sum + (s.duration_secs || 0), 0);
@@ -1,10 +1,12 @@
 import { useState, useEffect } from "react";
 import { getTrackingStatus, pauseTracking, resumeTracking } from "../lib/commands";
 import { listen } from "@tauri-apps/api/event";
+import type { MonitorStatus } from "../types/monitor";
 
 export function useTracking() {
   const [isTracking, setIsTracking] = useState(false);
   const [isLoading, setIsLoading] = useState(true);
+  const [monitorStatus, setMonitorStatus] = useState<MonitorStatus | null>(null);
 
   const refreshStatus = async () => {
     try {
@@ -38,11 +40,17 @@ export function useTracking() {
       refreshStatus();
     });
 
+    // Listen for camera source health transitions (e.g. log stream restarts)
+    const unlistenStatus = listen<MonitorStatus>("monitor-status", (event) => {
+      setMonitorStatus(event.payload);
+    });
+
     return () => {
       unlisten.then((fn) => fn());
+      unlistenStatus.then((fn) => fn());
     };
   }, []);
 
-  return { isTracking, isLoading, toggleTracking, refreshStatus };
+  return { isTracking, isLoading, monitorStatus, toggleTracking, refreshStatus };
 }
 
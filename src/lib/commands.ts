@@ -3,16 +3,76 @@ import { invoke } from "@tauri-apps/api/core";
 export interface Session {
   id: number | null;
   app_name: string;
+  bundle_id: string | null;
+  pid: number | null;
+  sensor: "camera" | "microphone";
+  confirmed_by: string[];
+  source: "live" | "backfill" | "rebuilt";
   start_time: string;
   end_time: string | null;
   duration_secs: number | null;
   status: "running" | "completed";
+  end_reason:
+    | "stopped"
+    | "restarted"
+    | "paused"
+    | "shutdown"
+    | "crash_recovered"
+    | "replay_end"
+    | "process_exited"
+    | "attribution_lost"
+    | "sleep"
+    | "start_timed_out"
+    | null;
+  truncated: boolean;
+  merged_interruptions: number;
+  screen_locked: boolean;
 }
 
+export type Subsystem = "controlcenter" | "skylight" | "cameracapture" | "cmio";
+
+export interface ProbeEvidence {
+  subsystem: Subsystem;
+  matches: number;
+  sample: string | null;
+  error: string | null;
+}
+
+export interface Detection {
+  subsystem: Subsystem;
+  fallback: boolean;
+  os_build: string | null;
+  probes: ProbeEvidence[];
+}
+
+export type BackfillProgress =
+  | { state: "running"; lines: number; sessions: number; fraction: number }
+  | { state: "completed"; lines: number; sessions: number }
+  | { state: "failed"; error: string };
+
 export async function getSessions(): Promise<Session[]> {
   return await invoke("get_sessions");
 }
 
+export interface CameraEvent {
+  id: number | null;
+  kind: "started" | "stopped";
+  app_name: string;
+  bundle_id: string | null;
+  pid: number | null;
+  sensor: "camera" | "microphone";
+  subsystem: string;
+  log_timestamp: string;
+  received_at: string;
+  line_hash: string | null;
+}
+
+export async function getSessionEvents(
+  sessionId: number
+): Promise<CameraEvent[]> {
+  return await invoke("get_session_events", { sessionId });
+}
+
 export async function getTrackingStatus(): Promise<boolean> {
   return await invoke("get_tracking_status");
 }
@@ -29,6 +89,148 @@ export async function exportCsv(): Promise<void> {
   return await invoke("export_csv");
 }
 
+export async function replayLogCapture(
+  path: string,
+  subsystem: string
+): Promise<Session[]> {
+  return await invoke("replay_log_capture", { path, subsystem });
+}
+
+export async function redetectSubsystem(): Promise<Detection> {
+  return await invoke("redetect_subsystem");
+}
+
+export async function startBackfill(days: number | null = null): Promise<void> {
+  return await invoke("start_backfill", { days });
+}
+
+export interface RebuildSummary {
+  removed: number;
+  rebuilt: number;
+  events: number;
+}
+
+export async function rebuildSessions(
+  start: string,
+  end: string
+): Promise<RebuildSummary> {
+  return await invoke("rebuild_sessions", { start, end });
+}
+
+export async function getGracePeriod(): Promise<number> {
+  return await invoke("get_grace_period");
+}
+
+export async function setGracePeriod(secs: number): Promise<void> {
+  return await invoke("set_grace_period", { secs });
+}
+
+export type AliasMatch = "exact" | "prefix" | "regex" | "bundle_id";
+
+export interface AliasRule {
+  id: number | null;
+  kind: AliasMatch;
+  pattern: string;
+  app_name: string;
+}
+
+export async function getAliasRules(): Promise<AliasRule[]> {
+  return await invoke("get_alias_rules");
+}
+
+export async function getDefaultAliasRules(): Promise<AliasRule[]> {
+  return await invoke("get_default_alias_rules");
+}
+
+export async function addAliasRule(
+  kind: AliasMatch,
+  pattern: string,
+  appName: string
+): Promise<number> {
+  return await invoke("add_alias_rule", { kind, pattern, appName });
+}
+
+export async function deleteAliasRule(id: number): Promise<boolean> {
+  return await invoke("delete_alias_rule", { id });
+}
+
+export type AppPolicy = "allow" | "ignore" | "flag";
+
+export interface AppPolicyEntry {
+  app_name: string;
+  policy: AppPolicy;
+}
+
+export async function getAppPolicies(): Promise<AppPolicyEntry[]> {
+  return await invoke("get_app_policies");
+}
+
+export async function setAppPolicy(
+  appName: string,
+  policy: AppPolicy
+): Promise<void> {
+  return await invoke("set_app_policy", { appName, policy });
+}
+
+export async function clearAppPolicy(appName: string): Promise<boolean> {
+  return await invoke("clear_app_policy", { appName });
+}
+
+export type NotifyRule = "always" | "first_time" | "flagged_only" | "never";
+
+export interface NotifyRuleEntry {
+  app_name: string;
+  rule: NotifyRule;
+}
+
+export interface QuietHours {
+  id: number | null;
+  /** e.g. ["Mon", "Wed"]; empty for every day */
+  days: string[];
+  /** Local time, "HH:MM:SS" */
+  start: string;
+  end: string;
+}
+
+export async function getNotificationRules(): Promise<NotifyRuleEntry[]> {
+  return await invoke("get_notification_rules");
+}
+
+export async function setNotificationRule(
+  appName: string,
+  rule: NotifyRule
+): Promise<void> {
+  return await invoke("set_notification_rule", { appName, rule });
+}
+
+export async function clearNotificationRule(appName: string): Promise<boolean> {
+  return await invoke("clear_notification_rule", { appName });
+}
+
+export async function getDefaultNotificationRule(): Promise<NotifyRule> {
+  return await invoke("get_default_notification_rule");
+}
+
+export async function setDefaultNotificationRule(rule: NotifyRule): Promise<void> {
+  return await invoke("set_default_notification_rule", { rule });
+}
+
+export async function getQuietHours(): Promise<QuietHours[]> {
+  return await invoke("get_quiet_hours");
+}
+
+export async function addQuietHours(
+  days: string,
+  start: string,
+  end: string
+): Promise<number> {
+  return await invoke("add_quiet_hours", { days, start, end });
+}
+
+export async function deleteQuietHours(id: number): Promise<boolean> {
+  return await invoke("delete_quiet_hours", { id });
+}
+
 export async function checkConsent(): Promise<boolean> {
   return await invoke("check_consent");
 }
@@ -41,8 +243,11 @@ export async function checkLogAccess(): Promise<boolean> {
   return await invoke("check_log_access");
 }
 
-export async function getAppIcon(appName: string): Promise<string | null> {
-  return await invoke("get_app_icon", { appName });
+export async function getAppIcon(
+  appName: string,
+  bundleId: string | null = null
+): Promise<string | null> {
+  return await invoke("get_app_icon", { appName, bundleId });
 }
 
 export async function openCameraSettings(): Promise<void> {
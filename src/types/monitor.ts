@@ -0,0 +1,8 @@
+export type MonitorStatus =
+  | { state: "stopped" }
+  | { state: "running" }
+  | {
+      state: "restarting";
+      detail: { attempt: number; retry_in_secs: number; reason: string };
+    }
+  | { state: "failed"; detail: string };
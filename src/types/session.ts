@@ -1,9 +1,29 @@
 export interface Session {
   id: number | null;
   app_name: string;
+  bundle_id: string | null;
+  pid: number | null;
+  sensor: "camera" | "microphone";
+  confirmed_by: string[];
+  source: "live" | "backfill" | "rebuilt";
   start_time: string;
   end_time: string | null;
   duration_secs: number | null;
   status: "running" | "completed";
+  end_reason:
+    | "stopped"
+    | "restarted"
+    | "paused"
+    | "shutdown"
+    | "crash_recovered"
+    | "replay_end"
+    | "process_exited"
+    | "attribution_lost"
+    | "sleep"
+    | "start_timed_out"
+    | null;
+  truncated: boolean;
+  merged_interruptions: number;
+  screen_locked: boolean;
 }
 